@@ -0,0 +1,77 @@
+#![feature(test)]
+
+//! Lexer throughput benchmarks.
+//!
+//! Every input is embedded in the binary, so that the numbers only measure lexing (and not disk
+//! I/O). `b.bytes` is set on every benchmark, which makes the harness report throughput in MB/s.
+
+extern crate rgo;
+extern crate test;
+
+use test::Bencher;
+
+/// A "Hello, world" sized program.
+const SMALL: &'static str = include_str!("../tests/data/pass/hello.go");
+
+/// A real-world source file of a few thousand lines.
+const MEDIUM: &'static str = include_str!("../tests/data/pass/viper.go");
+
+/// A large, machine-generated source file.
+const LARGE: &'static str = include_str!("../tests/data/pass/rewriteAMD64.go");
+
+/// Build a source string made up almost exclusively of identifiers and keywords, which stresses
+/// identifier scanning and keyword lookup.
+fn ident_heavy_src() -> String {
+    let mut src = String::from("package identifiers\n\n");
+
+    for i in 0..2000 {
+        src.push_str(&format!("var someRatherLongIdentifier{0}, anotherIdentifier{0}, \
+                               shortIdent{0} someTypeName\n",
+                              i));
+        src.push_str(&format!("func function{0}(receiverValue, otherValue typeName) \
+                               returnType {{ return receiverValue }}\n",
+                              i));
+    }
+
+    src
+}
+
+fn bench_tokenize(b: &mut Bencher, src: &str) {
+    b.bytes = src.len() as u64;
+
+    b.iter(|| rgo::lexer::tokenize(src));
+}
+
+#[bench]
+fn lex_small(b: &mut Bencher) {
+    bench_tokenize(b, SMALL);
+}
+
+#[bench]
+fn lex_medium(b: &mut Bencher) {
+    bench_tokenize(b, MEDIUM);
+}
+
+#[bench]
+fn lex_large(b: &mut Bencher) {
+    bench_tokenize(b, LARGE);
+}
+
+#[bench]
+fn lex_ident_heavy(b: &mut Bencher) {
+    let src = ident_heavy_src();
+    bench_tokenize(b, &src);
+}
+
+/// Keyword lookup on its own, over the words of `ident_heavy_src`: mostly non-keyword identifiers,
+/// many of which share a first letter with a keyword.
+#[bench]
+fn keyword_lookup(b: &mut Bencher) {
+    let src = ident_heavy_src();
+    let words: Vec<&str> = src.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    b.bytes = words.iter().map(|w| w.len() as u64).sum();
+
+    b.iter(|| words.iter().filter(|w| rgo::lexer::TokenKind::is_keyword_str(w)).count());
+}
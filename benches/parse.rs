@@ -28,7 +28,7 @@ macro_rules! bench_lex {
                 b.bytes = src.len() as u64;
 
                 b.iter(|| {
-                    rgo::lexer::tokenize(&src);
+                    rgo::lexer::tokenize(&src).unwrap();
                 });
             }
         }
@@ -0,0 +1,75 @@
+#!/usr/bin/env python3
+# Generate src/lexer/unicode.rs: the Unicode character classes used by Go identifiers.
+#
+# Usage: python3 scripts/unicode.py > src/lexer/unicode.rs
+#
+# The tables follow the Unicode version of the Python running this script.
+
+import sys
+import unicodedata
+
+
+def ranges(pred):
+    """Collapse the code points matching `pred` into inclusive (lo, hi) ranges."""
+    out = []
+    for cp in range(sys.maxunicode + 1):
+        if not pred(chr(cp)):
+            continue
+        if out and out[-1][1] == cp - 1:
+            out[-1][1] = cp
+        else:
+            out.append([cp, cp])
+    return out
+
+
+def table(name, doc, rs):
+    print("/// %s" % doc)
+    print("const %s: &'static [(char, char)] = &[" % name)
+    for lo, hi in rs:
+        print("    ('\\u{%x}', '\\u{%x}')," % (lo, hi))
+    print("];")
+
+
+print("// Generated by scripts/unicode.py from Unicode %s. Do not edit." % unicodedata.unidata_version)
+print("""//
+// Go's identifiers are made of letters (Unicode category L, plus `_`) and decimal digits (category
+// Nd). Rust's `char::is_alphabetic` and `char::is_numeric` are broader: the former also accepts
+// letter numbers (Nl, e.g. `Ⅷ`) and other alphabetic characters such as combining vowel signs and
+// circled letters, and the latter also accepts Nl and No (e.g. `²`).
+
+use std::cmp::Ordering;
+
+/// Whether `c` is a Unicode letter (category L).
+pub fn is_letter(c: char) -> bool {
+    match c {
+        'a'...'z' | 'A'...'Z' => true,
+        _ if (c as u32) < 0x80 => false,
+        _ => in_table(LETTER, c),
+    }
+}
+
+/// Whether `c` is a Unicode decimal digit (category Nd).
+pub fn is_digit(c: char) -> bool {
+    match c {
+        '0'...'9' => true,
+        _ if (c as u32) < 0x80 => false,
+        _ => in_table(DIGIT, c),
+    }
+}
+
+fn in_table(table: &[(char, char)], c: char) -> bool {
+    table.binary_search_by(|&(lo, hi)| {
+            if c < lo {
+                Ordering::Greater
+            } else if c > hi {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+""")
+table("LETTER", "Category L.", ranges(lambda c: unicodedata.category(c).startswith("L")))
+print()
+table("DIGIT", "Category Nd.", ranges(lambda c: unicodedata.category(c) == "Nd"))
@@ -0,0 +1,170 @@
+//! Structural diffing of AST values, for tests.
+//!
+//! `assert_eq!` on two big AST values dumps both of them in full when it fails, which makes it
+//! very hard to spot what actually went wrong. Instead, we walk the pretty-printed (`{:#?}`)
+//! representations of both values side by side and report the path of the first field that
+//! differs, e.g. `top_level_decls[0].signature.result`.
+//!
+//! This only relies on `Debug`, so it works for every node type without any extra boilerplate.
+
+use std::fmt;
+
+/// The first difference found between two values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// Path of the differing field, e.g. `import_decls[1].item.specs`.
+    pub path: String,
+    /// The line of the expected value's debug output where the difference starts.
+    pub expected: String,
+    /// The line of the actual value's debug output where the difference starts.
+    pub got: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+
+        write!(f,
+               "first difference at `{}`:\n  expected: {}\n       got: {}",
+               path,
+               self.expected,
+               self.got)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum FrameKind {
+    /// `Name {`: children are named fields.
+    Struct,
+    /// `[`: children are indexed elements.
+    List,
+    /// `Name(`: children are positional fields.
+    Tuple,
+}
+
+struct Frame {
+    kind: FrameKind,
+    path: String,
+    children: usize,
+}
+
+/// Tracks the path of each line of a pretty-printed `Debug` representation.
+struct PathTracker {
+    frames: Vec<Frame>,
+}
+
+impl PathTracker {
+    fn new() -> PathTracker {
+        PathTracker { frames: Vec::new() }
+    }
+
+    /// Feed the next line to the tracker and return the path of the value it contains.
+    fn line_path(&mut self, line: &str) -> String {
+        let content = line.trim();
+
+        // A closing delimiter ends the innermost frame.
+        if content.starts_with('}') || content.starts_with(']') || content.starts_with(')') {
+            return match self.frames.pop() {
+                Some(frame) => frame.path,
+                None => String::new(),
+            };
+        }
+
+        let (segment, value) = match self.frames.last_mut() {
+            Some(frame) => {
+                let index = frame.children;
+                frame.children += 1;
+
+                match frame.kind {
+                    FrameKind::Struct => {
+                        match content.find(": ") {
+                            Some(i) => (format!(".{}", &content[..i]), &content[i + 2..]),
+                            None => (String::new(), content),
+                        }
+                    }
+                    FrameKind::List => (format!("[{}]", index), content),
+                    // Most tuples only have one field (e.g. `Some(x)`), which isn't worth
+                    // cluttering the path with.
+                    FrameKind::Tuple if index == 0 => (String::new(), content),
+                    FrameKind::Tuple => (format!(".{}", index), content),
+                }
+            }
+            None => (String::new(), content),
+        };
+
+        let path = match self.frames.last() {
+            Some(frame) => format!("{}{}", frame.path, segment),
+            None => segment,
+        };
+
+        let opened = if value.ends_with('{') {
+            Some(FrameKind::Struct)
+        } else if value.ends_with('[') {
+            Some(FrameKind::List)
+        } else if value.ends_with('(') {
+            Some(FrameKind::Tuple)
+        } else {
+            None
+        };
+
+        if let Some(kind) = opened {
+            self.frames.push(Frame {
+                kind: kind,
+                path: path.clone(),
+                children: 0,
+            });
+        }
+
+        path
+    }
+}
+
+/// Find the first difference between the debug representations of `expected` and `got`.
+///
+/// Returns `None` if both values have the same debug representation.
+pub fn first_difference<T: fmt::Debug>(expected: &T, got: &T) -> Option<Difference> {
+    let expected_repr = format!("{:#?}", expected);
+    let got_repr = format!("{:#?}", got);
+
+    let mut expected_lines = expected_repr.lines();
+    let mut got_lines = got_repr.lines();
+    let mut tracker = PathTracker::new();
+
+    loop {
+        match (expected_lines.next(), got_lines.next()) {
+            (None, None) => return None,
+            (Some(e), Some(g)) if e == g => {
+                tracker.line_path(e);
+            }
+            (e, g) => {
+                let path = tracker.line_path(e.or(g).unwrap());
+
+                return Some(Difference {
+                    path: path.trim_left_matches('.').into(),
+                    expected: e.unwrap_or("<nothing>").trim().into(),
+                    got: g.unwrap_or("<nothing>").trim().into(),
+                });
+            }
+        }
+    }
+}
+
+/// Assert that two AST values are equal, reporting the path of the first difference on failure
+/// instead of dumping both values.
+pub fn assert_ast_eq<T: fmt::Debug + PartialEq>(expected: &T, got: &T) {
+    if expected == got {
+        return;
+    }
+
+    match first_difference(expected, got) {
+        Some(diff) => panic!("AST mismatch, {}", diff),
+        None => {
+            panic!("AST values are not equal, but have identical debug representations:\n{:#?}",
+                   got)
+        }
+    }
+}
@@ -0,0 +1,920 @@
+//! Re-emission of AST nodes as Go source.
+
+use std::fmt;
+use std::str;
+use num::{Zero, Signed, Integer};
+use num::bigint::BigInt;
+use num::BigRational;
+use token::Spanned;
+use super::*;
+
+/// The maximum number of fractional digits printed for floats that have no finite decimal
+/// representation (e.g. `1/3`). Every literal the parser produces has one, as its denominator is a
+/// product of powers of 2 and 5, but other values can be constructed by hand.
+const MAX_FRACTIONAL_DIGITS: usize = 40;
+
+/// Formats a basic literal as valid Go source, such that lexing and parsing the output yields the
+/// same value.
+///
+/// Integers are always printed in decimal, and floats without an exponent. The AST does not
+/// record whether a string literal was raw, so all strings are printed as interpreted string
+/// literals.
+impl fmt::Display for BasicLit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BasicLit::Int(ref i) => write!(f, "{}", i),
+            BasicLit::Float(ref r) => write_decimal(f, r),
+            BasicLit::Imaginary(ref r) => {
+                try!(write_decimal(f, r));
+                write!(f, "i")
+            }
+            BasicLit::Rune(c) => {
+                try!(write!(f, "'"));
+                try!(write_escaped_char(f, c, '\''));
+                write!(f, "'")
+            }
+            BasicLit::Str(ref bytes) => {
+                try!(write!(f, "\""));
+
+                let mut rest = &bytes[..];
+                while !rest.is_empty() {
+                    let valid = match str::from_utf8(rest) {
+                        Ok(s) => s,
+                        Err(e) => str::from_utf8(&rest[..e.valid_up_to()]).unwrap(),
+                    };
+
+                    for c in valid.chars() {
+                        try!(write_escaped_char(f, c, '"'));
+                    }
+
+                    rest = &rest[valid.len()..];
+
+                    // Bytes that aren't valid UTF-8 can only be written as byte escapes.
+                    if let Some(&b) = rest.first() {
+                        try!(write!(f, "\\x{:02x}", b));
+                        rest = &rest[1..];
+                    }
+                }
+
+                write!(f, "\"")
+            }
+        }
+    }
+}
+
+/// Write a character inside a rune or string literal delimited by `quote`.
+fn write_escaped_char(f: &mut fmt::Formatter, c: char, quote: char) -> fmt::Result {
+    match c {
+        '\x07' => write!(f, "\\a"),
+        '\x08' => write!(f, "\\b"),
+        '\x0C' => write!(f, "\\f"),
+        '\n' => write!(f, "\\n"),
+        '\r' => write!(f, "\\r"),
+        '\t' => write!(f, "\\t"),
+        '\x0B' => write!(f, "\\v"),
+        '\\' => write!(f, "\\\\"),
+        c if c == quote => write!(f, "\\{}", c),
+        c if c.is_control() && (c as u32) < 0x80 => write!(f, "\\x{:02x}", c as u32),
+        c if c.is_control() => write!(f, "\\u{:04x}", c as u32),
+        c => write!(f, "{}", c),
+    }
+}
+
+/// Write a rational number as a decimal float literal, e.g. `3.25`.
+fn write_decimal(f: &mut fmt::Formatter, r: &BigRational) -> fmt::Result {
+    if r.is_negative() {
+        try!(write!(f, "-"));
+    }
+
+    let numer = r.numer().abs();
+    let denom = r.denom().abs();
+    let ten = BigInt::from(10);
+
+    let (int_part, mut rem) = numer.div_rem(&denom);
+    try!(write!(f, "{}.", int_part));
+
+    // Always print at least one fractional digit, so that the literal is still a float. A finite
+    // decimal representation is printed in full, however long, so that the value is exact.
+    let exact = has_finite_decimal(&denom);
+    let mut digits = 0;
+    while digits == 0 || (!rem.is_zero() && (exact || digits < MAX_FRACTIONAL_DIGITS)) {
+        let (digit, r) = (rem * &ten).div_rem(&denom);
+        try!(write!(f, "{}", digit));
+        rem = r;
+        digits += 1;
+    }
+
+    Ok(())
+}
+
+/// Whether a fraction with the (positive) denominator `denom` has a finite decimal
+/// representation, i.e. whether 2 and 5 are the only prime factors of `denom`.
+fn has_finite_decimal(denom: &BigInt) -> bool {
+    let mut denom = denom.clone();
+    for factor in &[BigInt::from(2), BigInt::from(5)] {
+        while denom.is_multiple_of(factor) {
+            denom = denom / factor;
+        }
+    }
+    denom == BigInt::from(1)
+}
+
+// The rest of the AST is printed on a single line, with blocks written as `{ a; b }`. The output
+// is valid Go, but not what gofmt would produce.
+
+/// Write `items`, separated by `sep`.
+fn write_joined<'a, T, I>(f: &mut fmt::Formatter, items: I, sep: &str) -> fmt::Result
+    where T: fmt::Display + 'a,
+          I: IntoIterator<Item = &'a T>
+{
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            try!(f.write_str(sep));
+        }
+        try!(write!(f, "{}", item));
+    }
+
+    Ok(())
+}
+
+/// Write a comma-separated list of spanned items.
+fn write_list<T>(f: &mut fmt::Formatter, items: &[Spanned<T>]) -> fmt::Result
+    where T: fmt::Display + fmt::Debug + Clone + PartialEq + Eq
+{
+    write_joined(f, items.iter().map(|x| &x.item), ", ")
+}
+
+// Expressions.
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expr::Unary(ref unary) => write!(f, "{}", unary),
+            Expr::Binary(ref binary) => write!(f, "{}", binary),
+        }
+    }
+}
+
+/// The precedence of an expression's outermost operator. Unary expressions bind more tightly than
+/// any binary operator.
+fn precedence(expr: &Expr) -> i32 {
+    match *expr {
+        Expr::Unary(_) => 6,
+        Expr::Binary(ref binary) => binary.op.precedence(),
+    }
+}
+
+/// Binary operators are left-associative, so the right-hand side needs parentheses even at equal
+/// precedence. The parser never produces a tree that needs them, as it records parentheses as
+/// operands.
+impl fmt::Display for BinaryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prec = self.op.precedence();
+
+        if precedence(&self.lhs.item) < prec {
+            try!(write!(f, "({})", self.lhs.item));
+        } else {
+            try!(write!(f, "{}", self.lhs.item));
+        }
+
+        try!(write!(f, " {} ", self.op));
+
+        if precedence(&self.rhs.item) <= prec {
+            write!(f, "({})", self.rhs.item)
+        } else {
+            write!(f, "{}", self.rhs.item)
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use super::BinaryOperation::*;
+
+        f.write_str(match *self {
+            Add => "+",
+            Sub => "-",
+            Mul => "*",
+            Div => "/",
+            Rem => "%",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "^",
+            BitClear => "&^",
+            LeftShift => "<<",
+            RightShift => ">>",
+            Equals => "==",
+            NotEqual => "!=",
+            LessThan => "<",
+            LessThanOrEqual => "<=",
+            GreaterThan => ">",
+            GreaterThanOrEqual => ">=",
+            LogAnd => "&&",
+            LogOr => "||",
+        })
+    }
+}
+
+impl fmt::Display for UnaryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnaryExpr::Primary(ref primary) => write!(f, "{}", primary),
+            UnaryExpr::UnaryOperation(ref op) => {
+                try!(write!(f, "{}", op.operator));
+
+                // Keep e.g. `- -x` from being lexed as a decrement.
+                if let UnaryExpr::UnaryOperation(ref inner) = op.operand.item {
+                    match (op.operator, inner.operator) {
+                        (UnaryOperator::Plus, UnaryOperator::Plus) |
+                        (UnaryOperator::Minus, UnaryOperator::Minus) |
+                        (UnaryOperator::And, UnaryOperator::And) |
+                        (UnaryOperator::And, UnaryOperator::Xor) => try!(f.write_str(" ")),
+                        _ => {}
+                    }
+                }
+
+                write!(f, "{}", op.operand.item)
+            }
+        }
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use super::UnaryOperator::*;
+
+        f.write_str(match *self {
+            Plus => "+",
+            Minus => "-",
+            Not => "!",
+            Xor => "^",
+            Deref => "*",
+            And => "&",
+            ChanReceive => "<-",
+        })
+    }
+}
+
+impl fmt::Display for PrimaryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PrimaryExpr::Operand(ref operand) => write!(f, "{}", operand),
+            PrimaryExpr::Conversion(ref conv) => {
+                try!(write_type_operand(f, &conv.typ.item));
+                write!(f, "({})", conv.expr.item)
+            }
+            PrimaryExpr::SelectorExpr(ref sel) => write!(f, "{}.{}", sel.operand, sel.selector),
+            PrimaryExpr::Indexing(ref index) => {
+                write!(f, "{}[{}]", index.operand.item, index.index.item)
+            }
+            PrimaryExpr::Slicing(ref slice) => {
+                let slicing = &slice.slicing;
+                try!(write!(f, "{}[", slice.operand.item));
+
+                if let Some(ref low) = slicing.low {
+                    try!(write!(f, "{}", low.item));
+                }
+                try!(f.write_str(":"));
+                if let Some(ref high) = slicing.high {
+                    try!(write!(f, "{}", high.item));
+                }
+                if let Some(ref max) = slicing.max {
+                    try!(write!(f, ":{}", max.item));
+                }
+
+                f.write_str("]")
+            }
+            PrimaryExpr::TypeAssertion(ref assertion) => {
+                match assertion.typ {
+                    Some(ref typ) => write!(f, "{}.({})", assertion.expr.item, typ.item),
+                    None => write!(f, "{}.(type)", assertion.expr.item),
+                }
+            }
+            PrimaryExpr::FuncCall(ref call) => {
+                try!(write!(f, "{}(", call.callee.item));
+
+                if let Some(ref typ) = call.args.typ {
+                    try!(write!(f, "{}", typ.item));
+                    if !call.args.expressions.is_empty() {
+                        try!(f.write_str(", "));
+                    }
+                }
+                try!(write_list(f, &call.args.expressions));
+                if call.args.variadic {
+                    try!(f.write_str("..."));
+                }
+
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Write a type that is followed by `.` or `(`, parenthesizing it where it would otherwise be
+/// ambiguous (e.g. `(*T)(x)` rather than `*T(x)`).
+fn write_type_operand(f: &mut fmt::Formatter, typ: &Type) -> fmt::Result {
+    match *typ {
+        Type::Literal(ref lit) => {
+            match **lit {
+                TypeLiteral::Pointer(_) |
+                TypeLiteral::Func(_) |
+                TypeLiteral::Chan(ChanType { direction: ChanDirection::Receive, .. }) => {
+                    write!(f, "({})", typ)
+                }
+                _ => write!(f, "{}", typ),
+            }
+        }
+        Type::Plain(_) => write!(f, "{}", typ),
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Lit(ref lit) => write!(f, "{}", lit),
+            Operand::Ident(ref ident) => write!(f, "{}", ident),
+            Operand::MethodExpr(ref method) => {
+                try!(write_type_operand(f, &method.receiver));
+                write!(f, ".{}", method.name)
+            }
+            Operand::Expr(ref expr) => write!(f, "({})", expr),
+        }
+    }
+}
+
+impl fmt::Display for MaybeQualifiedIdent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.package {
+            Some(ref package) => write!(f, "{}.{}", package, self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Literal::Basic(ref lit) => write!(f, "{}", lit),
+            Literal::Composite(ref lit) => write!(f, "{}{}", lit.typ.item, lit.val),
+            Literal::Func(ref lit) => write!(f, "func{} {}", lit.signature, lit.body),
+        }
+    }
+}
+
+impl fmt::Display for LiteralType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LiteralType::Struct(ref typ) => write!(f, "{}", typ),
+            LiteralType::Array(ref typ) => write!(f, "{}", typ),
+            LiteralType::Slice(ref typ) => write!(f, "{}", typ),
+            LiteralType::Map(ref typ) => write!(f, "{}", typ),
+            LiteralType::Type(ref name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for LiteralValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("{"));
+
+        for (i, elem) in self.elems.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str(", "));
+            }
+            if let Some(ref key) = elem.key {
+                try!(write!(f, "{}: ", key.item));
+            }
+            try!(write!(f, "{}", elem.elem.item));
+        }
+
+        f.write_str("}")
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Key::FieldName(ref name) => f.write_str(name),
+            Key::Expr(ref expr) => write!(f, "{}", expr),
+            Key::LiteralValue(ref val) => write!(f, "{}", val),
+        }
+    }
+}
+
+impl fmt::Display for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Elem::Expr(ref expr) => write!(f, "{}", expr),
+            Elem::LiteralValue(ref val) => write!(f, "{}", val),
+        }
+    }
+}
+
+// Types.
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Plain(ref name) => write!(f, "{}", name),
+            Type::Literal(ref lit) => write!(f, "{}", lit),
+        }
+    }
+}
+
+impl fmt::Display for TypeLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypeLiteral::Array(ref typ) => write!(f, "{}", typ),
+            TypeLiteral::Struct(ref typ) => write!(f, "{}", typ),
+            TypeLiteral::Pointer(ref typ) => write!(f, "*{}", typ.0),
+            TypeLiteral::Func(ref typ) => write!(f, "func{}", typ.signature),
+            TypeLiteral::Interface(ref typ) => write!(f, "{}", typ),
+            TypeLiteral::Slice(ref typ) => write!(f, "{}", typ),
+            TypeLiteral::Map(ref typ) => write!(f, "{}", typ),
+            TypeLiteral::Chan(ref typ) => write!(f, "{}", typ),
+        }
+    }
+}
+
+impl fmt::Display for ArrayType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}]{}", self.len, self.element_type)
+    }
+}
+
+impl fmt::Display for SliceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[]{}", self.element_type)
+    }
+}
+
+impl fmt::Display for MapType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "map[{}]{}", self.key_type, self.element_type)
+    }
+}
+
+impl fmt::Display for ChanType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str(match self.direction {
+            ChanDirection::Bidirectional => "chan ",
+            ChanDirection::Send => "chan<- ",
+            ChanDirection::Receive => "<-chan ",
+        }));
+
+        // `chan <-chan T` would be read as `chan<- chan T`.
+        match self.element_type {
+            Type::Literal(ref lit) => {
+                match **lit {
+                    TypeLiteral::Chan(ChanType { direction: ChanDirection::Receive, .. }) => {
+                        write!(f, "({})", self.element_type)
+                    }
+                    _ => write!(f, "{}", self.element_type),
+                }
+            }
+            Type::Plain(_) => write!(f, "{}", self.element_type),
+        }
+    }
+}
+
+impl fmt::Display for StructType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.field_decls.is_empty() {
+            return f.write_str("struct{}");
+        }
+
+        try!(f.write_str("struct { "));
+        try!(write_joined(f, &self.field_decls, "; "));
+        f.write_str(" }")
+    }
+}
+
+impl fmt::Display for FieldDecl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.inner {
+            InnerFieldDecl::Named { ref idents, ref typ } => {
+                try!(write_joined(f, idents, ", "));
+                try!(write!(f, " {}", typ));
+            }
+            InnerFieldDecl::Anonymous { is_ptr, ref type_name } => {
+                if is_ptr {
+                    try!(f.write_str("*"));
+                }
+                try!(write!(f, "{}", type_name));
+            }
+        }
+
+        match self.tag {
+            Some(ref tag) => write!(f, " {}", BasicLit::Str(tag.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for InterfaceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.specs.is_empty() && self.constraints.is_empty() {
+            return f.write_str("interface{}");
+        }
+
+        try!(f.write_str("interface { "));
+        try!(write_joined(f, &self.specs, "; "));
+        if !self.specs.is_empty() && !self.constraints.is_empty() {
+            try!(f.write_str("; "));
+        }
+        try!(write_joined(f, &self.constraints, "; "));
+        f.write_str(" }")
+    }
+}
+
+impl fmt::Display for MethodSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.method {
+            InnerMethodSpec::Signature(ref signature) => write!(f, "{}{}", self.name, signature),
+            InnerMethodSpec::InterfaceName(ref name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for TypeConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_joined(f, &self.terms, " | ")
+    }
+}
+
+impl fmt::Display for TypeTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.underlying {
+            try!(f.write_str("~"));
+        }
+        write!(f, "{}", self.typ)
+    }
+}
+
+/// Formats a signature as it follows `func` or a function name, e.g. `(a, b int) error`.
+impl fmt::Display for FuncSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "({})", self.parameters));
+
+        let decls = &self.result.decls;
+
+        if decls.is_empty() {
+            Ok(())
+        } else if decls.len() == 1 && decls[0].identifiers.is_empty() && !decls[0].variadic {
+            // A single unnamed result needs no parentheses.
+            write!(f, " {}", decls[0].typ)
+        } else {
+            write!(f, " ({})", self.result)
+        }
+    }
+}
+
+/// Formats the inside of a parameter list, without the parentheses.
+impl fmt::Display for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_joined(f, &self.decls, ", ")
+    }
+}
+
+impl fmt::Display for ParameterDecl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.identifiers.is_empty() {
+            try!(write_joined(f, &self.identifiers, ", "));
+            try!(f.write_str(" "));
+        }
+        if self.variadic {
+            try!(f.write_str("..."));
+        }
+        write!(f, "{}", self.typ)
+    }
+}
+
+// Statements.
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("{}");
+        }
+
+        try!(f.write_str("{ "));
+        try!(write_joined(f, self.0.iter().map(|s| &s.item), "; "));
+        f.write_str(" }")
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Statement::Decl(ref decl) => write!(f, "{}", decl),
+            Statement::Labeled(ref stmt) => {
+                write!(f, "{}: {}", stmt.label.item, stmt.statement.item)
+            }
+            Statement::Simple(ref stmt) => write!(f, "{}", stmt),
+            Statement::Go(ref stmt) => write!(f, "go {}", stmt.call.item),
+            Statement::Return(ref stmt) => {
+                try!(f.write_str("return"));
+                if !stmt.exprs.is_empty() {
+                    try!(f.write_str(" "));
+                }
+                write_list(f, &stmt.exprs)
+            }
+            Statement::Break(ref stmt) => write_branch(f, "break", stmt.label.as_ref()),
+            Statement::Continue(ref stmt) => write_branch(f, "continue", stmt.label.as_ref()),
+            Statement::Goto(ref stmt) => write_branch(f, "goto", Some(&stmt.label)),
+            Statement::Fallthrough(_) => f.write_str("fallthrough"),
+            Statement::Block(ref block) => write!(f, "{}", block),
+            Statement::If(ref stmt) => write!(f, "{}", stmt),
+            Statement::Switch(ref stmt) => write!(f, "{}", stmt),
+            Statement::Select(_) => f.write_str("select {}"),
+            Statement::For(ref stmt) => write!(f, "{}", stmt),
+            Statement::Defer(ref stmt) => write!(f, "defer {}", stmt.call.item),
+            Statement::Empty(_) => Ok(()),
+        }
+    }
+}
+
+fn write_branch(f: &mut fmt::Formatter,
+                keyword: &str,
+                label: Option<&Spanned<String>>)
+                -> fmt::Result {
+    match label {
+        Some(label) => write!(f, "{} {}", keyword, label.item),
+        None => f.write_str(keyword),
+    }
+}
+
+impl fmt::Display for SimpleStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SimpleStmt::EmptyStmt => Ok(()),
+            SimpleStmt::Expr(ref expr) => write!(f, "{}", expr.item),
+            SimpleStmt::Send(ref stmt) => write!(f, "{} <- {}", stmt.channel.item, stmt.expr.item),
+            SimpleStmt::IncDec(ref stmt) => {
+                write!(f, "{}{}", stmt.expr.item, if stmt.is_dec { "--" } else { "++" })
+            }
+            SimpleStmt::Assignment(ref stmt) => {
+                try!(write_list(f, &stmt.lhs));
+                match stmt.op {
+                    Some(op) => try!(write!(f, " {}= ", op)),
+                    None => try!(f.write_str(" = ")),
+                }
+                write_list(f, &stmt.rhs)
+            }
+            SimpleStmt::ShortVarDecl(ref decl) => {
+                try!(write_list(f, &decl.lhs));
+                try!(f.write_str(" := "));
+                write_list(f, &decl.rhs)
+            }
+        }
+    }
+}
+
+/// Write the optional simple statement that precedes the condition or tag of an `if` or `switch`.
+fn write_init(f: &mut fmt::Formatter, init: Option<&SimpleStmt>) -> fmt::Result {
+    match init {
+        Some(init) => write!(f, "{}; ", init),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for IfStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("if "));
+        try!(write_init(f, self.before_stmt.as_ref()));
+        try!(write!(f, "{} {}", self.condition.item, self.block));
+
+        match self.opt_else.as_ref().map(|e| &**e) {
+            Some(&Else::If(ref stmt)) => write!(f, " else {}", stmt),
+            Some(&Else::Block(ref block)) => write!(f, " else {}", block),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for SwitchStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("switch "));
+
+        match *self {
+            SwitchStmt::Expr(ref stmt) => {
+                try!(write_init(f, stmt.init.as_ref()));
+                if let Some(ref expr) = stmt.expr {
+                    try!(write!(f, "{} ", expr.item));
+                }
+
+                try!(f.write_str("{"));
+                for clause in &stmt.clauses {
+                    match clause.exprs {
+                        Some(ref exprs) => {
+                            try!(f.write_str(" case "));
+                            try!(write_list(f, exprs));
+                            try!(f.write_str(":"));
+                        }
+                        None => try!(f.write_str(" default:")),
+                    }
+                    try!(write_case_body(f, &clause.body));
+                }
+            }
+            SwitchStmt::Type(ref stmt) => {
+                try!(write_init(f, stmt.init.as_ref()));
+                if let Some(ref binding) = stmt.binding {
+                    try!(write!(f, "{} := ", binding.item));
+                }
+                try!(write!(f, "{}.(type) ", stmt.expr.item));
+
+                try!(f.write_str("{"));
+                for clause in &stmt.clauses {
+                    match clause.types {
+                        Some(ref types) => {
+                            try!(f.write_str(" case "));
+                            try!(write_list(f, types));
+                            try!(f.write_str(":"));
+                        }
+                        None => try!(f.write_str(" default:")),
+                    }
+                    try!(write_case_body(f, &clause.body));
+                }
+            }
+        }
+
+        let empty = match *self {
+            SwitchStmt::Expr(ref stmt) => stmt.clauses.is_empty(),
+            SwitchStmt::Type(ref stmt) => stmt.clauses.is_empty(),
+        };
+        f.write_str(if empty { "}" } else { " }" })
+    }
+}
+
+fn write_case_body(f: &mut fmt::Formatter, body: &[Spanned<Statement>]) -> fmt::Result {
+    for stmt in body {
+        try!(write!(f, " {};", stmt.item));
+    }
+    Ok(())
+}
+
+impl fmt::Display for ForStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("for "));
+
+        match self.header {
+            ForHeader::Condition(ref cond) => try!(write!(f, "{} ", cond)),
+            ForHeader::ForClause(ForClause { init: None, condition: None, post: None }) => {}
+            ForHeader::ForClause(ref clause) => {
+                if let Some(ref init) = clause.init {
+                    try!(write!(f, "{}", init));
+                }
+                try!(f.write_str("; "));
+                if let Some(ref cond) = clause.condition {
+                    try!(write!(f, "{}", cond));
+                }
+                try!(f.write_str("; "));
+                if let Some(ref post) = clause.post {
+                    try!(write!(f, "{} ", post));
+                }
+            }
+            ForHeader::RangeClause(ref clause) => {
+                match clause.iter_vars {
+                    IterVars::Exprs(ref exprs) if exprs.is_empty() => {}
+                    IterVars::Exprs(ref exprs) => {
+                        try!(write_list(f, exprs));
+                        try!(f.write_str(" = "));
+                    }
+                    IterVars::Idents(ref idents) => {
+                        try!(write_list(f, idents));
+                        try!(f.write_str(" := "));
+                    }
+                }
+                try!(write!(f, "range {} ", clause.expr.item));
+            }
+        }
+
+        write!(f, "{}", self.body)
+    }
+}
+
+// Declarations.
+
+impl fmt::Display for DeclStmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeclStmt::Const(ref decl) => write_spec_group(f, "const", &decl.specs),
+            DeclStmt::TypeDecl(ref decl) => write_spec_group(f, "type", &decl.specs),
+            DeclStmt::VarDecl(ref decl) => write_spec_group(f, "var", &decl.specs),
+        }
+    }
+}
+
+/// Write a declaration, grouping its specs in parentheses unless there is exactly one.
+fn write_spec_group<T>(f: &mut fmt::Formatter, keyword: &str, specs: &[Spanned<T>]) -> fmt::Result
+    where T: fmt::Display + fmt::Debug + Clone + PartialEq + Eq
+{
+    if specs.len() == 1 {
+        return write!(f, "{} {}", keyword, specs[0].item);
+    }
+
+    try!(write!(f, "{} (", keyword));
+    try!(write_joined(f, specs.iter().map(|s| &s.item), "; "));
+    f.write_str(")")
+}
+
+impl fmt::Display for ConstSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write_list(f, &self.idents));
+
+        if let Some(ref inner) = self.inner {
+            if let Some(ref typ) = inner.typ {
+                try!(write!(f, " {}", typ));
+            }
+            try!(f.write_str(" = "));
+            try!(write_list(f, &inner.exprs));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_alias {
+            write!(f, "{} = {}", self.ident.item, self.typ.item)
+        } else {
+            write!(f, "{} {}", self.ident.item, self.typ.item)
+        }
+    }
+}
+
+impl fmt::Display for VarSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write_list(f, &self.idents));
+
+        if let Some(ref typ) = self.typ {
+            try!(write!(f, " {}", typ));
+        }
+        if !self.exprs.is_empty() {
+            try!(f.write_str(" = "));
+            try!(write_list(f, &self.exprs));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TopLevelDecl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TopLevelDecl::Statement(ref decl) => write!(f, "{}", decl),
+            TopLevelDecl::Func(ref decl) => {
+                try!(write!(f, "func {}{}", decl.name.item, decl.signature));
+                write_body(f, decl.body.as_ref())
+            }
+            TopLevelDecl::Method(ref decl) => {
+                try!(write!(f,
+                            "func ({}) {}{}",
+                            decl.receiver,
+                            decl.name.item,
+                            decl.signature));
+                write_body(f, decl.body.as_ref())
+            }
+        }
+    }
+}
+
+fn write_body(f: &mut fmt::Formatter, body: Option<&Block>) -> fmt::Result {
+    match body {
+        Some(body) => write!(f, " {}", body),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for ImportSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ImportKind::Normal => {}
+            ImportKind::Alias(ref alias) => try!(write!(f, "{} ", alias)),
+            ImportKind::Glob => try!(f.write_str(". ")),
+            ImportKind::Blank => try!(f.write_str("_ ")),
+        }
+
+        write!(f, "{}", BasicLit::Str(self.path.item.clone()))
+    }
+}
+
+/// Formats a source file with one declaration per line.
+impl fmt::Display for SourceFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "package {}", self.package));
+
+        for decl in &self.import_decls {
+            try!(f.write_str("\n"));
+            try!(write_spec_group(f, "import", &decl.item.specs));
+            try!(f.write_str("\n"));
+        }
+
+        for decl in &self.top_level_decls {
+            try!(writeln!(f, "\n{}", decl.item));
+        }
+
+        Ok(())
+    }
+}
@@ -144,7 +144,7 @@ pub struct UnaryOperation {
 /// ```ignore
 /// unary_op   = "+" | "-" | "!" | "^" | "*" | "&" | "<-" .
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOperator {
     Plus,
     Minus,
@@ -261,8 +261,9 @@ pub struct SliceExpr {
 // to max - low. Only the first index may be omitted; it defaults to 0. After slicing the array a
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Slicing {
-    pub low: Spanned<Expr>,
-    pub high: Spanned<Expr>,
+    pub low: Option<Spanned<Expr>>,
+    pub high: Option<Spanned<Expr>>,
+    /// The capacity bound of a full slice expression, which requires `high` to be present.
     pub max: Option<Spanned<Expr>>,
 }
 
@@ -271,7 +272,8 @@ pub struct Slicing {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeAssertion {
     pub expr: Box<Spanned<PrimaryExpr>>,
-    pub typ: Spanned<String>,
+    /// The asserted type, or `None` for the `x.(type)` guard of a type switch.
+    pub typ: Option<Spanned<Type>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
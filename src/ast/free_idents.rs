@@ -0,0 +1,203 @@
+//! Finding the identifiers an expression refers to.
+
+use super::*;
+
+/// Return the identifiers an expression refers to, each once, in order of first appearance.
+///
+/// Type names count, wherever they appear: in conversions, composite literals, method
+/// expressions and type assertions, as do the lengths of array types. Selectors and the keys of
+/// struct literals name fields or methods rather than referring to anything in scope, so they are
+/// left out. Without type information, a key which is a lone identifier is taken to be a field
+/// name unless the literal is of an array, slice or map type. Similarly, a qualified identifier
+/// (e.g. `b.c`) can't be told apart from a selector, so only its package name (`b`) is included.
+///
+/// Function literals are not looked into, so the variables a closure captures are missing.
+pub fn free_idents(expr: &Expr) -> Vec<&str> {
+    let mut idents = Vec::new();
+    walk_expr(expr, &mut idents);
+    idents
+}
+
+fn add<'a>(ident: &'a str, idents: &mut Vec<&'a str>) {
+    if !idents.contains(&ident) {
+        idents.push(ident);
+    }
+}
+
+/// Add the package name of a qualified identifier, or the identifier itself.
+fn add_ident<'a>(ident: &'a MaybeQualifiedIdent, idents: &mut Vec<&'a str>) {
+    match ident.package {
+        Some(ref package) => add(package, idents),
+        None => add(&ident.name, idents),
+    }
+}
+
+fn walk_expr<'a>(expr: &'a Expr, idents: &mut Vec<&'a str>) {
+    match *expr {
+        Expr::Binary(ref binary) => {
+            walk_expr(&binary.lhs.item, idents);
+            walk_expr(&binary.rhs.item, idents);
+        }
+        Expr::Unary(ref unary) => walk_unary(unary, idents),
+    }
+}
+
+fn walk_unary<'a>(unary: &'a UnaryExpr, idents: &mut Vec<&'a str>) {
+    match *unary {
+        UnaryExpr::Primary(ref primary) => walk_primary(primary, idents),
+        UnaryExpr::UnaryOperation(ref op) => walk_unary(&op.operand.item, idents),
+    }
+}
+
+fn walk_primary<'a>(primary: &'a PrimaryExpr, idents: &mut Vec<&'a str>) {
+    match *primary {
+        PrimaryExpr::Operand(ref operand) => walk_operand(operand, idents),
+        PrimaryExpr::Conversion(ref conversion) => {
+            walk_type(&conversion.typ.item, idents);
+            walk_expr(&conversion.expr.item, idents);
+        }
+        PrimaryExpr::SelectorExpr(ref selector) => walk_primary(&selector.operand, idents),
+        PrimaryExpr::Indexing(ref index) => {
+            walk_primary(&index.operand.item, idents);
+            walk_expr(&index.index.item, idents);
+        }
+        PrimaryExpr::Slicing(ref slice) => {
+            walk_primary(&slice.operand.item, idents);
+            let bounds = [&slice.slicing.low, &slice.slicing.high, &slice.slicing.max];
+            for bound in bounds.iter().filter_map(|bound| bound.as_ref()) {
+                walk_expr(&bound.item, idents);
+            }
+        }
+        PrimaryExpr::TypeAssertion(ref assertion) => {
+            walk_primary(&assertion.expr.item, idents);
+            if let Some(ref typ) = assertion.typ {
+                walk_type(&typ.item, idents);
+            }
+        }
+        PrimaryExpr::FuncCall(ref call) => {
+            walk_primary(&call.callee.item, idents);
+            for arg in &call.args.expressions {
+                walk_expr(&arg.item, idents);
+            }
+        }
+    }
+}
+
+fn walk_operand<'a>(operand: &'a Operand, idents: &mut Vec<&'a str>) {
+    match *operand {
+        Operand::Ident(ref ident) => add_ident(ident, idents),
+        Operand::Lit(Literal::Composite(ref lit)) => {
+            let keys_may_be_fields = match lit.typ.item {
+                LiteralType::Struct(ref typ) => {
+                    walk_struct_type(typ, idents);
+                    true
+                }
+                LiteralType::Type(ref name) => {
+                    add_ident(name, idents);
+                    true
+                }
+                LiteralType::Array(ref typ) => {
+                    walk_expr(&typ.len, idents);
+                    walk_type(&typ.element_type, idents);
+                    false
+                }
+                LiteralType::Slice(ref typ) => {
+                    walk_type(&typ.element_type, idents);
+                    false
+                }
+                LiteralType::Map(ref typ) => {
+                    walk_type(&typ.key_type, idents);
+                    walk_type(&typ.element_type, idents);
+                    false
+                }
+            };
+            walk_literal_value(&lit.val, keys_may_be_fields, idents);
+        }
+        Operand::Lit(Literal::Basic(_)) |
+        Operand::Lit(Literal::Func(_)) => {}
+        Operand::MethodExpr(ref method) => walk_type(&method.receiver, idents),
+        Operand::Expr(ref expr) => walk_expr(expr, idents),
+    }
+}
+
+fn walk_literal_value<'a>(val: &'a LiteralValue,
+                          keys_may_be_fields: bool,
+                          idents: &mut Vec<&'a str>) {
+    for keyed in &val.elems {
+        match keyed.key.as_ref().map(|key| &key.item) {
+            Some(&Key::Expr(ref expr)) if !(keys_may_be_fields && is_lone_ident(expr)) => {
+                walk_expr(expr, idents)
+            }
+            // The type of an elided literal isn't known, so its keys may be field names.
+            Some(&Key::LiteralValue(ref val)) => walk_literal_value(val, true, idents),
+            Some(_) | None => {}
+        }
+
+        match keyed.elem.item {
+            Elem::Expr(ref expr) => walk_expr(expr, idents),
+            Elem::LiteralValue(ref val) => walk_literal_value(val, true, idents),
+        }
+    }
+}
+
+fn walk_type<'a>(typ: &'a Type, idents: &mut Vec<&'a str>) {
+    let lit = match *typ {
+        Type::Plain(ref name) => return add_ident(name, idents),
+        Type::Literal(ref lit) => lit,
+    };
+
+    match **lit {
+        TypeLiteral::Array(ref typ) => {
+            walk_expr(&typ.len, idents);
+            walk_type(&typ.element_type, idents);
+        }
+        TypeLiteral::Struct(ref typ) => walk_struct_type(typ, idents),
+        TypeLiteral::Pointer(PointerType(ref typ)) => walk_type(typ, idents),
+        TypeLiteral::Func(ref typ) => walk_signature(&typ.signature, idents),
+        TypeLiteral::Interface(ref typ) => {
+            for spec in &typ.specs {
+                match spec.method {
+                    InnerMethodSpec::Signature(ref signature) => walk_signature(signature, idents),
+                    InnerMethodSpec::InterfaceName(ref name) => add_ident(name, idents),
+                }
+            }
+            for term in typ.constraints.iter().flat_map(|constraint| &constraint.terms) {
+                walk_type(&term.typ, idents);
+            }
+        }
+        TypeLiteral::Slice(ref typ) => walk_type(&typ.element_type, idents),
+        TypeLiteral::Map(ref typ) => {
+            walk_type(&typ.key_type, idents);
+            walk_type(&typ.element_type, idents);
+        }
+        TypeLiteral::Chan(ref typ) => walk_type(&typ.element_type, idents),
+    }
+}
+
+fn walk_struct_type<'a>(typ: &'a StructType, idents: &mut Vec<&'a str>) {
+    for decl in &typ.field_decls {
+        match decl.inner {
+            InnerFieldDecl::Named { ref typ, .. } => walk_type(typ, idents),
+            InnerFieldDecl::Anonymous { ref type_name, .. } => add_ident(type_name, idents),
+        }
+    }
+}
+
+fn walk_signature<'a>(signature: &'a FuncSignature, idents: &mut Vec<&'a str>) {
+    let decls = signature.parameters.decls.iter().chain(&signature.result.decls);
+    for decl in decls {
+        walk_type(&decl.typ, idents);
+    }
+}
+
+fn is_lone_ident(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Unary(UnaryExpr::Primary(ref primary)) => {
+            match **primary {
+                PrimaryExpr::Operand(Operand::Ident(ref ident)) => ident.package.is_none(),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
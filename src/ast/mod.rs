@@ -9,13 +9,23 @@
 mod types;
 mod statements;
 mod expressions;
+mod display;
+mod free_idents;
+
+#[cfg(test)]
+pub mod diff;
+
+#[cfg(test)]
+mod test;
 
 use num::bigint::BigInt;
 use num::BigRational;
+use num::ToPrimitive;
 use token::Spanned;
 pub use self::types::*;
 pub use self::statements::*;
 pub use self::expressions::*;
+pub use self::free_idents::free_idents;
 
 
 // XXX: We may want to intern strings later on.
@@ -38,7 +48,34 @@ pub struct SourceFile {
     /// All import declarations in this file.
     pub import_decls: Vec<Spanned<ImportDecl>>,
     /// All top-level declarations in this file.
-    pub top_level_decls: Vec<TopLevelDecl>,
+    pub top_level_decls: Vec<Spanned<TopLevelDecl>>,
+}
+
+/// The source files making up a single package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// The package name shared by all files.
+    pub name: Ident,
+    /// The parsed files, in the order they were given.
+    pub files: Vec<SourceFile>,
+}
+
+impl SourceFile {
+    /// The paths of all packages imported by this file, in order.
+    ///
+    /// Import paths are string literals, so they are not guaranteed to be valid UTF-8.
+    pub fn imports<'a>(&'a self) -> Box<Iterator<Item = &'a [u8]> + 'a> {
+        Box::new(self.imports_with_kind().map(|(_, path)| path))
+    }
+
+    /// Like `imports`, but also yields how each package is imported.
+    pub fn imports_with_kind<'a>(&'a self)
+                                 -> Box<Iterator<Item = (&'a ImportKind, &'a [u8])> + 'a> {
+        Box::new(self.import_decls
+            .iter()
+            .flat_map(|decl| decl.item.specs.iter())
+            .map(|spec| (&spec.item.kind, &spec.item.path.item[..])))
+    }
 }
 
 /// An import declaration.
@@ -96,6 +133,9 @@ pub enum ImportKind {
     /// Glob import: all the package's exported identifiers will be declared in the importing
     /// source file.
     Glob,
+    /// Blank import (`import _ "lib/math"`): the package is only imported for its side effects
+    /// (i.e. its initialization).
+    Blank,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -142,6 +182,24 @@ impl Parameters {
         Parameters { decls: Vec::new() }
     }
 
+    /// Return one entry per parameter, expanding grouped names: `(a, b int)` yields `a` and `b`,
+    /// both of type `int`. Unnamed parameters have no name.
+    pub fn expand(&self) -> Vec<(Option<&str>, &Type)> {
+        let mut res = Vec::new();
+
+        for decl in &self.decls {
+            if decl.identifiers.is_empty() {
+                res.push((None, &decl.typ));
+            }
+
+            for ident in &decl.identifiers {
+                res.push((Some(&ident[..]), &decl.typ));
+            }
+        }
+
+        res
+    }
+
     /// Create a parameter list containing a single, unnamed type.
     pub fn from_single_type(t: Type) -> Parameters {
         Parameters {
@@ -215,7 +273,7 @@ pub struct MaybeQualifiedIdent {
 /// Example: `const Pi float64 = 3.14159265358979323846`
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConstDecl {
-    pub specs: Vec<ConstSpec>,
+    pub specs: Vec<Spanned<ConstSpec>>,
 }
 
 
@@ -229,13 +287,15 @@ pub struct ConstDecl {
 pub struct ConstSpec {
     pub idents: Vec<Spanned<Ident>>,
     pub inner: Option<ConstSpecInner>,
+    /// The value of `iota` in this spec: its index within the enclosing declaration.
+    pub iota: u32,
 }
 
 // XXX: naming
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConstSpecInner {
     pub typ: Option<Type>,
-    pub exprs: Vec<Expr>,
+    pub exprs: Vec<Spanned<Expr>>,
 }
 
 
@@ -258,13 +318,16 @@ pub struct MethodDecl {
 
 /// A type declaration binds an identifier, the type name, to a new type that has the same
 /// underlying type as an existing type, and operations defined for the existing type are also
-/// defined for the new type.
+/// defined for the new type. An alias declaration instead binds the identifier to the existing
+/// type itself.
 ///
 /// ## Grammar
 ///
 /// ```ignore
 /// TypeDecl     = "type" ( TypeSpec | "(" { TypeSpec ";" } ")" ) .
-/// TypeSpec     = identifier Type .
+/// TypeSpec     = AliasDecl | TypeDef .
+/// AliasDecl    = identifier "=" Type .
+/// TypeDef      = identifier Type .
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeDecl {
@@ -276,6 +339,8 @@ pub struct TypeDecl {
 pub struct TypeSpec {
     pub ident: Spanned<Ident>,
     pub typ: Spanned<Type>,
+    /// Whether this is an alias (`type A = B`) rather than a new type (`type A B`).
+    pub is_alias: bool,
 }
 
 /// A variable declaration creates one or more variables, binds corresponding identifiers to them,
@@ -344,7 +409,7 @@ pub struct ShortVarDecl {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Block(pub Vec<Statement>);
+pub struct Block(pub Vec<Spanned<Statement>>);
 
 
 // XXX/FIXME: review and fix this.
@@ -357,6 +422,38 @@ pub enum BasicLit {
     Str(Vec<u8>),
 }
 
+impl BasicLit {
+    /// The code point of a rune literal, or `None` for any other kind of literal.
+    pub fn as_char(&self) -> Option<char> {
+        match *self {
+            BasicLit::Rune(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer literal, or `None` for any other kind of literal.
+    ///
+    /// Integer literals are untyped constants of arbitrary precision, so this never overflows.
+    pub fn as_big(&self) -> Option<&BigInt> {
+        match *self {
+            BasicLit::Int(ref i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer literal, or `None` for any other kind of literal or if the value
+    /// does not fit in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_big().and_then(|i| i.to_i64())
+    }
+
+    /// The value of an integer literal, or `None` for any other kind of literal or if the value
+    /// does not fit in a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_big().and_then(|i| i.to_u64())
+    }
+}
+
 
 /// Composite literals construct values for structs, arrays, slices, and maps and create a new
 /// value each time they are evaluated. They consist of the type of the literal followed by a
@@ -441,4 +538,6 @@ pub struct FuncLit {
 pub struct Arguments {
     pub typ: Option<Spanned<Type>>,
     pub expressions: Vec<Spanned<Expr>>,
+    /// Whether the last argument is followed by `...`, passing a slice as the variadic parameter.
+    pub variadic: bool,
 }
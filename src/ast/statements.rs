@@ -1,4 +1,5 @@
-use super::{Block, Expr, ShortVarDecl, ConstDecl, TypeDecl, VarDecl, BinaryOperation};
+use super::{Block, Expr, PrimaryExpr, Type, ShortVarDecl, ConstDecl, TypeDecl, VarDecl,
+            BinaryOperation};
 use token::Spanned;
 
 
@@ -72,8 +73,15 @@ pub enum SimpleStmt {
     ShortVarDecl(ShortVarDecl),
 }
 
+/// A statement preceded by a label, which may be the target of a `goto`, `break` or `continue`
+/// statement.
+///
+/// A label may also be the last thing in a block, in which case it labels an empty statement.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct LabeledStmt;
+pub struct LabeledStmt {
+    pub label: Spanned<String>,
+    pub statement: Box<Spanned<Statement>>,
+}
 
 /// A "go" statement starts the execution of a function call as an independent concurrent thread of
 /// control, or goroutine, within the same address space.
@@ -95,8 +103,8 @@ pub struct DeferStmt {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ReturnStmt {
-    /// The expression being returned.
-    pub expr: Spanned<Expr>,
+    /// The expressions being returned. Empty for a bare `return`.
+    pub exprs: Vec<Spanned<Expr>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -145,8 +153,79 @@ pub enum Else {
 }
 
 
+/// "Switch" statements provide multi-way execution. They come in two forms: expression switches
+/// and type switches.
+///
+/// ## Grammar
+///
+/// ```ignore
+/// SwitchStmt = ExprSwitchStmt | TypeSwitchStmt .
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwitchStmt {
+    Expr(ExprSwitchStmt),
+    Type(TypeSwitchStmt),
+}
+
+/// An expression switch compares the switch expression to the expressions of each case.
+///
+/// ## Grammar
+///
+/// ```ignore
+/// ExprSwitchStmt = "switch" [ SimpleStmt ";" ] [ Expression ] "{" { ExprCaseClause } "}" .
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SwitchStmt;
+pub struct ExprSwitchStmt {
+    pub init: Option<SimpleStmt>,
+    /// The switch expression. A missing expression is equivalent to `true`.
+    pub expr: Option<Spanned<Expr>>,
+    pub clauses: Vec<ExprCaseClause>,
+}
+
+/// ## Grammar
+///
+/// ```ignore
+/// ExprCaseClause = ExprSwitchCase ":" StatementList .
+/// ExprSwitchCase = "case" ExpressionList | "default" .
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprCaseClause {
+    /// The expressions of the case, or `None` for the default case.
+    pub exprs: Option<Vec<Spanned<Expr>>>,
+    pub body: Vec<Spanned<Statement>>,
+}
+
+/// A type switch compares the dynamic type of an interface value to the types of each case.
+///
+/// ## Grammar
+///
+/// ```ignore
+/// TypeSwitchStmt  = "switch" [ SimpleStmt ";" ] TypeSwitchGuard "{" { TypeCaseClause } "}" .
+/// TypeSwitchGuard = [ identifier ":=" ] PrimaryExpr "." "(" "type" ")" .
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeSwitchStmt {
+    pub init: Option<SimpleStmt>,
+    /// The identifier bound to the value in each clause, e.g. `v` in `switch v := x.(type)`.
+    pub binding: Option<Spanned<String>>,
+    /// The expression whose type is switched on, e.g. `x` in `switch x.(type)`.
+    pub expr: Spanned<PrimaryExpr>,
+    pub clauses: Vec<TypeCaseClause>,
+}
+
+/// ## Grammar
+///
+/// ```ignore
+/// TypeCaseClause  = TypeSwitchCase ":" StatementList .
+/// TypeSwitchCase  = "case" TypeList | "default" .
+/// TypeList        = Type { "," Type } .
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeCaseClause {
+    /// The types of the case, or `None` for the default case.
+    pub types: Option<Vec<Spanned<Type>>>,
+    pub body: Vec<Spanned<Statement>>,
+}
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SelectStmt;
 
@@ -0,0 +1,133 @@
+use super::*;
+use super::diff::{first_difference, assert_ast_eq};
+use token::{Span, Spanned};
+
+fn source_file(package: &str, import_paths: &[&str]) -> SourceFile {
+    let specs = import_paths.iter()
+        .map(|path| {
+            Spanned::new(Span { start: 0, end: 0 },
+                         ImportSpec {
+                             kind: ImportKind::Normal,
+                             path: Spanned::new(Span { start: 0, end: 0 }, path.bytes().collect()),
+                         })
+        })
+        .collect();
+
+    SourceFile {
+        package: package.into(),
+        import_decls: vec![Spanned::new(Span { start: 0, end: 0 }, ImportDecl { specs: specs })],
+        top_level_decls: vec![],
+    }
+}
+
+#[test]
+fn diff_identical() {
+    let a = source_file("main", &["fmt"]);
+    assert_eq!(first_difference(&a, &a.clone()), None);
+    assert_ast_eq(&a, &a.clone());
+}
+
+#[test]
+fn diff_reports_field_path() {
+    let a = source_file("main", &["fmt"]);
+    let b = source_file("foo", &["fmt"]);
+
+    let diff = first_difference(&a, &b).unwrap();
+    assert_eq!(diff.path, "package");
+    assert_eq!(diff.expected, "package: \"main\",");
+    assert_eq!(diff.got, "package: \"foo\",");
+}
+
+#[test]
+fn diff_reports_nested_path() {
+    let a = source_file("main", &["fmt", "io"]);
+    let b = source_file("main", &["fmt", "os"]);
+
+    let diff = first_difference(&a, &b).unwrap();
+    assert_eq!(diff.path, "import_decls[0].item.specs[1].item.path.item[0]");
+}
+
+#[test]
+fn diff_reports_missing_element() {
+    let a = source_file("main", &["fmt", "io"]);
+    let b = source_file("main", &["fmt"]);
+
+    let diff = first_difference(&a, &b).unwrap();
+    assert_eq!(diff.path, "import_decls[0].item.specs[1]");
+}
+
+#[test]
+#[should_panic(expected = "first difference at `package`")]
+fn assert_ast_eq_panics_with_path() {
+    assert_ast_eq(&source_file("main", &[]), &source_file("foo", &[]));
+}
+
+#[test]
+fn binary_operation_from_token_kind() {
+    use token::TokenKind;
+
+    assert_eq!(BinaryOperation::from_token_kind(TokenKind::OrOr),
+               Some(BinaryOperation::LogOr));
+    assert_eq!(BinaryOperation::from_token_kind(TokenKind::BitClear),
+               Some(BinaryOperation::BitClear));
+    assert_eq!(BinaryOperation::from_token_kind(TokenKind::LessThanOrEqual),
+               Some(BinaryOperation::LessThanOrEqual));
+
+    // Not binary operators, even though some are operators of another kind.
+    for &kind in &[TokenKind::Ident, TokenKind::Not, TokenKind::Arrow, TokenKind::PlusAssign] {
+        assert_eq!(BinaryOperation::from_token_kind(kind), None, "{:?}", kind);
+    }
+
+    assert_eq!(BinaryOperation::from_token_kind_assign_op(TokenKind::PlusAssign),
+               Some(BinaryOperation::Add));
+    assert_eq!(BinaryOperation::from_token_kind_assign_op(TokenKind::Plus), None);
+}
+
+/// Parse `src` as the value of a variable declaration.
+fn parse_expr(src: &str) -> Expr {
+    let file = ::parse(&format!("package p\n\nvar _ = {}\n", src)).unwrap();
+    match file.top_level_decls.into_iter().next().unwrap().item {
+        TopLevelDecl::Statement(DeclStmt::VarDecl(mut decl)) => {
+            decl.specs.remove(0).item.exprs.remove(0).item
+        }
+        decl => panic!("expected a variable declaration, got {:?}", decl),
+    }
+}
+
+fn plain_type(name: &str) -> Type {
+    Type::Plain(MaybeQualifiedIdent {
+        package: None,
+        name: name.into(),
+    })
+}
+
+#[test]
+fn free_idents_of_exprs() {
+    let idents = |src: &str| {
+        free_idents(&parse_expr(src)).into_iter().map(String::from).collect::<Vec<_>>()
+    };
+
+    assert_eq!(idents("a + b.c * d"), ["a", "b", "d"]);
+    assert_eq!(idents("f(x).y[i:j] - x"), ["f", "x", "i", "j"]);
+    assert_eq!(idents("-*p.q.r + <-ch"), ["p", "ch"]);
+    assert_eq!(idents("Point{X: x, Y: y}"), ["Point", "x", "y"]);
+    assert_eq!(idents("map[string]int{k: v, \"lit\": 1}"), ["string", "int", "k", "v"]);
+    assert_eq!(idents("[]Point{{X: a}, {b, c}}"), ["Point", "a", "b", "c"]);
+    assert!(idents("1 + 2").is_empty());
+
+    // Type names and array lengths count, wherever the type appears.
+    assert_eq!(idents("T(x) + U{x}"), ["T", "x", "U"]);
+    assert_eq!(idents("[]byte(s)"), ["byte", "s"]);
+    assert_eq!(idents("[n]int{}"), ["n", "int"]);
+    assert_eq!(idents("v.(io.Reader) == nil"), ["v", "io", "nil"]);
+    assert_eq!(idents("struct{ x T }{y}"), ["T", "y"]);
+
+    // The parser doesn't produce method expressions yet.
+    let receiver = Type::Literal(Box::new(TypeLiteral::Pointer(PointerType(plain_type("T")))));
+    let operand = Operand::MethodExpr(MethodExpr {
+        receiver: receiver,
+        name: "m".into(),
+    });
+    let method = Expr::Unary(UnaryExpr::Primary(Box::new(PrimaryExpr::Operand(operand))));
+    assert_eq!(free_idents(&method), ["T"]);
+}
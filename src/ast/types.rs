@@ -85,14 +85,50 @@ pub struct FuncType {
 
 /// An interface type specifies a method set called its interface.
 ///
+/// The predeclared identifier `any` is an alias for the empty interface, `interface{}`. It is
+/// parsed as a plain type name like any other, as it may be shadowed.
+///
 /// ## Grammar
 ///
 /// ```ignore
-/// InterfaceType      = "interface" "{" { MethodSpec ";" } "}" .
+/// InterfaceType      = "interface" "{" { InterfaceElem ";" } "}" .
+/// InterfaceElem      = MethodSpec | TypeConstraint .
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InterfaceType {
     pub specs: Vec<MethodSpec>,
+    /// Type constraints restricting the type set of the interface, e.g. `~int | ~string`.
+    /// Embedded interfaces are stored in `specs`, even though they are technically constraints.
+    pub constraints: Vec<TypeConstraint>,
+}
+
+/// A union of type terms inside of an interface, which may only be used as a constraint on type
+/// parameters.
+///
+/// ## Grammar
+///
+/// ```ignore
+/// TypeConstraint     = TypeTerm { "|" TypeTerm } .
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeConstraint {
+    pub terms: Vec<TypeTerm>,
+}
+
+/// A single term of a type constraint.
+///
+/// ## Grammar
+///
+/// ```ignore
+/// TypeTerm           = Type | UnderlyingType .
+/// UnderlyingType     = "~" Type .
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeTerm {
+    /// Whether the term is prefixed with `~`, which means that it also includes all types whose
+    /// underlying type is `typ`.
+    pub underlying: bool,
+    pub typ: Type,
 }
 
 /// An interface method spec.
@@ -0,0 +1,51 @@
+//! The crate-level error type, which covers every stage of the pipeline.
+
+use lexer;
+use parser;
+use token::Span;
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+quick_error! {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Error {
+        /// The source could not be tokenized.
+        Lex(err: lexer::Error) {
+            from()
+            description("lexical error")
+            display("lexical error at {}", err)
+            cause(err)
+        }
+        /// The tokens could not be parsed.
+        Parse(err: parser::Error) {
+            from()
+            description("parse error")
+            display("parse error at {}", err)
+            cause(err)
+        }
+        /// A file of a package declared a different package name than the others.
+        PackageMismatch(expected: String, found: String) {
+            description("mismatched package name")
+            display("found package {}, expected {}", found, expected)
+        }
+        /// The lexer or parser panicked. Only produced by `try_parse`.
+        Panic(message: String, span: Span) {
+            description("internal error")
+            display("internal error at {}: {}", span, message)
+        }
+    }
+}
+
+impl Error {
+    /// The span of the source which caused the error.
+    ///
+    /// Errors about a file as a whole, such as a mismatched package name, point to its start.
+    pub fn span(&self) -> Span {
+        match *self {
+            Error::Lex(ref err) => err.span,
+            Error::Parse(ref err) => err.span,
+            Error::PackageMismatch(..) => Span { start: 0, end: 0 },
+            Error::Panic(_, span) => span,
+        }
+    }
+}
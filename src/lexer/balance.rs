@@ -0,0 +1,82 @@
+//! Cheap delimiter balance checking on a token stream.
+//!
+//! Matching delimiters is really the parser's job, but editors and other tools often want quick
+//! feedback about unbalanced brackets without parsing the whole file.
+
+use std::fmt;
+use token::{TokenAndSpan, TokenKind};
+
+/// The first delimiter which breaks the balance of a token stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnbalancedDelim {
+    /// A closing delimiter which doesn't match the innermost open delimiter.
+    Mismatched {
+        /// Index of the closing delimiter in the token stream.
+        index: usize,
+        found: TokenKind,
+        /// The closing delimiter that was expected, or `None` if nothing was open.
+        expected: Option<TokenKind>,
+    },
+    /// An opening delimiter which is never closed.
+    Unclosed {
+        /// Index of the opening delimiter in the token stream.
+        index: usize,
+        opener: TokenKind,
+    },
+}
+
+impl fmt::Display for UnbalancedDelim {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnbalancedDelim::Mismatched { index, found, expected: Some(expected) } => {
+                write!(f,
+                       "token {}: expected \"{}\", found \"{}\"",
+                       index,
+                       expected,
+                       found)
+            }
+            UnbalancedDelim::Mismatched { index, found, expected: None } => {
+                write!(f, "token {}: unexpected \"{}\"", index, found)
+            }
+            UnbalancedDelim::Unclosed { index, opener } => {
+                write!(f, "token {}: unclosed \"{}\"", index, opener)
+            }
+        }
+    }
+}
+
+/// Check that all parentheses, brackets and braces in `tokens` are balanced.
+///
+/// If several delimiters are left unclosed, the innermost one is reported.
+pub fn check_balanced(tokens: &[TokenAndSpan]) -> Result<(), UnbalancedDelim> {
+    // The indices of the currently open delimiters.
+    let mut stack: Vec<usize> = Vec::new();
+
+    for (index, tok) in tokens.iter().enumerate() {
+        let kind = tok.token.kind;
+
+        if kind.closing_delim().is_some() {
+            stack.push(index);
+        } else if kind.is_closing_delim() {
+            let expected = stack.pop().and_then(|i| tokens[i].token.kind.closing_delim());
+
+            if expected != Some(kind) {
+                return Err(UnbalancedDelim::Mismatched {
+                    index: index,
+                    found: kind,
+                    expected: expected,
+                });
+            }
+        }
+    }
+
+    match stack.pop() {
+        Some(index) => {
+            Err(UnbalancedDelim::Unclosed {
+                index: index,
+                opener: tokens[index].token.kind,
+            })
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,63 @@
+use std::error;
+use std::fmt;
+use token::{Span, TokenKind};
+
+pub type LResult<T> = ::std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub span: Span,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "lexical error"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The source contains a NUL byte, which Go forbids anywhere in a source file.
+    NulCharacter,
+    /// A lexer was given an offset which is not a character boundary of the source, or is past
+    /// its end.
+    InvalidOffset,
+    /// A character which can't start any token, outside of a comment or literal (e.g. `@`).
+    IllegalCharacter(char),
+    /// A rune, string or raw string literal (given by the kind of its token) which is still open
+    /// at the end of the source.
+    UnterminatedLiteral(TokenKind),
+    /// The source read by `read_source` is not valid UTF-8.
+    InvalidUtf8,
+    /// The source could not be read by `read_source`. Holds the description of the I/O error.
+    Io(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::NulCharacter => write!(f, "illegal NUL character"),
+            ErrorKind::InvalidOffset => write!(f, "offset is not on a character boundary"),
+            ErrorKind::IllegalCharacter(c) => {
+                write!(f, "illegal character U+{:04X} '{}'", c as u32, c)
+            }
+            ErrorKind::UnterminatedLiteral(kind) => {
+                let name = match kind {
+                    TokenKind::Rune => "rune",
+                    TokenKind::StrRaw => "raw string",
+                    _ => "string",
+                };
+                write!(f, "{} literal not terminated", name)
+            }
+            ErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ErrorKind::Io(ref err) => write!(f, "failed to read source: {}", err),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! A minimal formatter working on tokens alone.
+//!
+//! Without an AST, the layout has to be guessed from the tokens: statements go on lines of their
+//! own, blocks are indented with tabs, and operators get the usual spacing. Where the tokens are
+//! ambiguous (e.g. whether a `*` after a name is a multiplication or starts a pointer type), the
+//! spacing of the original source is kept. It is nowhere near gofmt, but readable.
+
+use token::{TokenAndSpan, TokenKind};
+use token::TokenKind::*;
+
+/// What an open delimiter means for the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delim {
+    /// The braces of a block or of a struct or interface type, or the parentheses of a grouped
+    /// declaration. Their contents go on separate, indented lines.
+    Block,
+    /// Any other delimiter, whose contents stay on one line.
+    Inline(TokenKind),
+}
+
+/// Format a token stream as source code.
+///
+/// Comments are lost, as the lexer doesn't produce tokens for them, and composite literals are
+/// put on a single line.
+pub fn format_tokens(tokens: &[TokenAndSpan]) -> String {
+    let mut f = Formatter {
+        tokens: tokens,
+        out: String::new(),
+        delims: Vec::new(),
+        headers: Vec::new(),
+        funcs: Vec::new(),
+        cases: Vec::new(),
+        newline: false,
+        blank_line: false,
+    };
+
+    for i in 0..tokens.len() {
+        f.token(i);
+    }
+
+    if !f.out.is_empty() {
+        f.out.push('\n');
+    }
+    f.out
+}
+
+struct Formatter<'a> {
+    tokens: &'a [TokenAndSpan],
+    out: String,
+    delims: Vec<Delim>,
+    /// The depths (numbers of open delimiters) at which the headers of the `if`, `for` and
+    /// `switch` statements still waiting for their body start.
+    headers: Vec<usize>,
+    /// The depths at which the `func` keywords still waiting for their body appear.
+    funcs: Vec<usize>,
+    /// The depths at which the `case` and `default` clauses still waiting for their colon start.
+    cases: Vec<usize>,
+    /// Whether the next token starts a new line.
+    newline: bool,
+    /// Whether to leave an empty line before the next one, between top-level declarations.
+    blank_line: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn kind(&self, i: usize) -> Option<TokenKind> {
+        self.tokens.get(i).map(|t| t.token.kind)
+    }
+
+    fn token(&mut self, i: usize) {
+        let kind = self.tokens[i].token.kind;
+        let depth = self.delims.len();
+
+        match kind {
+            Semicolon => return self.semicolon(),
+            Eof => return,
+            _ => {}
+        }
+
+        if kind.is_closing_delim() {
+            let opener = self.delims.pop();
+            let depth = self.delims.len();
+            self.headers.retain(|&d| d <= depth);
+            self.funcs.retain(|&d| d <= depth);
+            self.cases.retain(|&d| d <= depth);
+
+            // Only an empty block stays on one line.
+            let prev = self.kind(i.wrapping_sub(1));
+            let empty = prev.and_then(|k| k.closing_delim()) == Some(kind);
+            if opener == Some(Delim::Block) && !empty {
+                self.newline = true;
+            }
+        }
+
+        let opens_block = match kind {
+            LBrace => self.brace_opens_block(i),
+            LParen => {
+                match self.kind(i.wrapping_sub(1)) {
+                    Some(Import) | Some(Var) | Some(Const) | Some(Type) => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+
+        // Labels, cases and closing braces are outdented from the statements they belong to.
+        let is_label = kind == Ident && self.kind(i + 1) == Some(Colon) &&
+                       self.delims.last() == Some(&Delim::Block) &&
+                       (self.newline || i == 0);
+        if self.newline {
+            let outdent = is_label || kind == Case || kind == Default;
+            self.start_line(outdent);
+        } else if i > 0 && !self.out.is_empty() && self.space_before(i, opens_block) {
+            self.out.push(' ');
+        }
+        self.write_token(i);
+
+        match kind {
+            LParen | LBracket | LBrace => {
+                if opens_block {
+                    self.delims.push(Delim::Block);
+                    self.newline = kind.closing_delim() != self.kind(i + 1);
+                } else {
+                    self.delims.push(Delim::Inline(kind));
+                }
+            }
+            If | For | Switch => self.headers.push(depth),
+            Func => self.funcs.push(depth),
+            Case | Default => self.cases.push(depth),
+            Colon => {
+                if self.cases.last() == Some(&depth) {
+                    self.cases.pop();
+                    self.newline = true;
+                } else if i > 0 && self.kind(i - 1) == Some(Ident) &&
+                          self.delims.last() == Some(&Delim::Block) &&
+                          (i == 1 || self.kind(i - 2) == Some(Semicolon) ||
+                           self.kind(i - 2) == Some(LBrace)) {
+                    // The colon after a label.
+                    self.newline = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn semicolon(&mut self) {
+        let depth = self.delims.len();
+        // A `func` which is still waiting for its body at the end of a statement was a type.
+        while self.funcs.last() == Some(&depth) {
+            self.funcs.pop();
+        }
+
+        match self.delims.last() {
+            _ if self.headers.last() == Some(&depth) => self.out.push(';'),
+            Some(&Delim::Inline(_)) => self.out.push_str("; "),
+            Some(&Delim::Block) => self.newline = true,
+            None => {
+                self.newline = true;
+                self.blank_line = !self.out.is_empty();
+            }
+        }
+    }
+
+    /// Whether the `{` at `i` opens a block, rather than a composite literal.
+    fn brace_opens_block(&mut self, i: usize) -> bool {
+        let depth = self.delims.len();
+
+        match self.kind(i.wrapping_sub(1)) {
+            Some(Struct) | Some(Interface) | Some(Else) | Some(Select) => return true,
+            _ => {}
+        }
+        if self.funcs.last() == Some(&depth) {
+            self.funcs.pop();
+            return true;
+        }
+        if self.headers.last() == Some(&depth) {
+            self.headers.pop();
+            return true;
+        }
+
+        // A block statement.
+        self.newline || i == 0
+    }
+
+    fn start_line(&mut self, outdent: bool) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+            if self.blank_line {
+                self.out.push('\n');
+            }
+        }
+
+        let mut indent = self.delims.iter().filter(|&&d| d == Delim::Block).count();
+        if outdent && indent > 0 {
+            indent -= 1;
+        }
+        for _ in 0..indent {
+            self.out.push('\t');
+        }
+
+        self.newline = false;
+        self.blank_line = false;
+    }
+
+    fn write_token(&mut self, i: usize) {
+        let token = &self.tokens[i].token;
+        let value = token.value.as_ref().map(|v| &v[..]).unwrap_or("");
+
+        match token.kind {
+            Str => self.out.push_str(&format!("\"{}\"", value)),
+            StrRaw => self.out.push_str(&format!("`{}`", value)),
+            Rune => self.out.push_str(&format!("'{}'", value)),
+            kind => self.out.push_str(kind.spelling().unwrap_or(value)),
+        }
+    }
+
+    /// Whether the token at `i` is a binary operator, i.e. an operator which follows an operand.
+    ///
+    /// The arrow of a send statement or of a send-only channel type counts as well.
+    fn is_binary_op(&self, i: usize) -> bool {
+        let kind = self.tokens[i].token.kind;
+        if kind.binary_precedence().is_none() && kind != Arrow {
+            return false;
+        }
+
+        match self.kind(i.wrapping_sub(1)) {
+            Some(Chan) => kind == Arrow,
+            Some(prev) => ends_operand(prev),
+            None => false,
+        }
+    }
+
+    fn space_before(&self, i: usize, opens_block: bool) -> bool {
+        let prev = &self.tokens[i - 1];
+        let cur = &self.tokens[i];
+        let (p, t) = (prev.token.kind, cur.token.kind);
+        let gap = prev.span.end < cur.span.start;
+
+        match t {
+            RParen | RBracket | RBrace | Comma | Semicolon | Dot | Colon | Increment |
+            Decrement => return false,
+            _ => {}
+        }
+        match p {
+            LParen | LBracket | LBrace | Dot | Not | Tilde | Ellipsis => return false,
+            Comma | Semicolon => return true,
+            // No space in a slice expression, e.g. `a[i:j]`.
+            Colon => return self.delims.last() != Some(&Delim::Inline(LBracket)),
+            _ => {}
+        }
+
+        if p == Chan && t == Arrow {
+            return false;
+        }
+        if p.is_unary_op() && !self.is_binary_op(i - 1) {
+            return false;
+        }
+        // Multiplication or a pointer type (e.g. `p *T`).
+        if p == Star && self.is_binary_op(i - 1) || t == Star && self.is_binary_op(i) {
+            return gap;
+        }
+        if self.is_binary_op(i - 1) || self.is_binary_op(i) || is_assign(p) || is_assign(t) {
+            return true;
+        }
+
+        match t {
+            LParen => {
+                match p {
+                    // A method receiver.
+                    Func => {
+                        self.delims.is_empty() && (i < 2 || self.kind(i - 2) == Some(Semicolon))
+                    }
+                    // A call of a call result, or the results of a function (`f() (int, error)`).
+                    RParen => gap,
+                    Ident | RBracket | RBrace => false,
+                    p => !p.is_literal(),
+                }
+            }
+            LBracket => {
+                match p {
+                    Map => false,
+                    // An index, or the type of a declaration (`xs []int`).
+                    p if ends_operand(p) => gap,
+                    _ => true,
+                }
+            }
+            LBrace => {
+                opens_block &&
+                !((p == Struct || p == Interface) && self.kind(i + 1) == Some(RBrace))
+            }
+            Ellipsis if ends_operand(p) => gap,
+            _ => p != RBracket,
+        }
+    }
+}
+
+/// Whether a token of this kind can end an operand, so that an operator after it is binary.
+fn ends_operand(kind: TokenKind) -> bool {
+    match kind {
+        Ident | RParen | RBracket | RBrace => true,
+        kind => kind.is_literal(),
+    }
+}
+
+fn is_assign(kind: TokenKind) -> bool {
+    kind.is_assign_op() || kind == ColonAssign
+}
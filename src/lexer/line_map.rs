@@ -0,0 +1,135 @@
+//! Mapping byte offsets to lines, honoring `//line` directives.
+//!
+//! Generated code uses `//line file:line` comments to point diagnostics back at the source it was
+//! generated from (e.g. a `.y` file for `goyacc` output). A directive must start at the beginning
+//! of a line, and gives the file name and line number of the line which follows it.
+
+use std::iter;
+use super::{Lexer, LexerConfig, LResult, TokenKind};
+
+const DIRECTIVE_PREFIX: &'static str = "//line ";
+
+/// The file and line a byte offset maps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLine<'a> {
+    pub file: &'a str,
+    /// 1-indexed line.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    /// 0-indexed row of the line following the directive.
+    row: usize,
+    file: String,
+    line: usize,
+}
+
+/// Maps byte offsets in a source file to the file and line they should be reported at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMap {
+    file: String,
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+    /// Directives, in source order.
+    directives: Vec<Directive>,
+}
+
+impl LineMap {
+    /// Build a line map for `src`, the contents of the file named `file`.
+    ///
+    /// Only comments are searched for directives, so a `//line` inside a raw string literal is not
+    /// mistaken for one. Malformed directives are ignored. Returns an error if the source can't be
+    /// lexed at all.
+    pub fn new(file: &str, src: &str) -> LResult<LineMap> {
+        let mut line_starts = vec![0];
+        line_starts.extend(src.match_indices('\n').map(|(i, _)| i + 1));
+
+        // Comments can only appear in the gaps between tokens. Automatically inserted semicolons
+        // span the whitespace and comments they replace, so they don't count.
+        let config = LexerConfig { lenient: true, ..LexerConfig::default() };
+        let lexer = try!(Lexer::with_config(src, config));
+        let spans = lexer.map(|t| (t.token.kind, t.span.start as usize, t.span.end as usize))
+            .filter(|&(kind, start, end)| kind != TokenKind::Semicolon || &src[start..end] == ";")
+            .map(|(_, start, end)| (start, end))
+            .chain(iter::once((src.len(), src.len())));
+
+        let mut directives = Vec::new();
+        let mut gap_start = 0;
+
+        for (start, end) in spans {
+            let mut row = match line_starts.binary_search(&gap_start) {
+                Ok(row) | Err(row) => row,
+            };
+
+            while row + 1 < line_starts.len() && line_starts[row] < start {
+                let line = &src[line_starts[row]..line_starts[row + 1] - 1];
+                if let Some((file, line)) = parse_directive(line) {
+                    directives.push(Directive {
+                        row: row + 1,
+                        file: file.to_owned(),
+                        line: line,
+                    });
+                }
+                row += 1;
+            }
+
+            if end > gap_start {
+                gap_start = end;
+            }
+        }
+
+        Ok(LineMap {
+            file: file.to_owned(),
+            line_starts: line_starts,
+            directives: directives,
+        })
+    }
+
+    /// Return the file and line of the character at the given byte offset.
+    ///
+    /// Before the first directive, this is the file the map was built for and the actual line.
+    pub fn lookup(&self, offset: usize) -> FileLine {
+        let row = match self.line_starts.binary_search(&offset) {
+            Ok(row) => row,
+            Err(row) => row - 1,
+        };
+        let in_effect = match self.directives.binary_search_by(|d| d.row.cmp(&row)) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+
+        match self.directives[..in_effect].last() {
+            Some(directive) => {
+                FileLine {
+                    file: &directive.file,
+                    line: directive.line + row - directive.row,
+                }
+            }
+            None => {
+                FileLine {
+                    file: &self.file,
+                    line: row + 1,
+                }
+            }
+        }
+    }
+}
+
+/// Parse a line of the form `//line file:line`.
+fn parse_directive(line: &str) -> Option<(&str, usize)> {
+    if !line.starts_with(DIRECTIVE_PREFIX) {
+        return None;
+    }
+
+    let rest = line[DIRECTIVE_PREFIX.len()..].trim_right();
+    let colon = match rest.rfind(':') {
+        Some(colon) if colon > 0 => colon,
+        _ => return None,
+    };
+
+    match rest[colon + 1..].parse() {
+        Ok(line) if line > 0 => Some((&rest[..colon], line)),
+        _ => None,
+    }
+}
@@ -17,35 +17,168 @@
 //! comparisons, which are going to be very frequent. Probably reduces allocations, too - and we're
 //! allocating a _lot_. We'd have to benchmark to be sure.
 
+use std::io::Read;
 use std::iter::Iterator;
 pub use token::*;
+use Position;
 
 #[cfg(test)]
 mod test;
 
+mod error;
+pub use self::error::{LResult, Error, ErrorKind};
+
+mod balance;
+pub use self::balance::{check_balanced, UnbalancedDelim};
+
+mod line_map;
+pub use self::line_map::{LineMap, FileLine};
+
+mod format;
+pub use self::format::format_tokens;
+
+mod semantic;
+pub use self::semantic::{semantic_tokens, SemanticToken};
+
+mod unicode;
+
+/// The UTF-8 byte order mark. Go ignores it if it is the first character of a source file.
+const BOM: char = '\u{FEFF}';
+
+/// Options controlling the behavior of a `Lexer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexerConfig {
+    /// Number of columns between tab stops when computing positions.
+    ///
+    /// Defaults to 1, like Go's own tooling.
+    pub tab_width: usize,
+    /// Whether to keep going after a character that can't start any token, emitting it as a
+    /// `TokenKind::Error` token, rather than panicking. A literal left open at the end of the
+    /// source becomes an `Error` token as well. Useful for editors, which have to cope with
+    /// half-written files. `tokenize` always lexes leniently, and reports such a token as an
+    /// error.
+    ///
+    /// Defaults to false.
+    pub lenient: bool,
+}
+
+impl Default for LexerConfig {
+    fn default() -> LexerConfig {
+        LexerConfig {
+            tab_width: 1,
+            lenient: false,
+        }
+    }
+}
+
+/// A `Lexer` is an iterator over the tokens of a source string.
+///
+/// Cloning a lexer is cheap, as it only borrows the source string. A clone can be advanced to look
+/// ahead, or kept around to backtrack to its position later, without affecting the original.
+/// For a single token of lookahead, `Iterator::peekable` is usually enough.
+#[derive(Debug, Clone)]
 pub struct Lexer<'src> {
     /// Byte offset from the start of the source string.
     offset: usize,
+    /// Byte offset of the start of the token being scanned.
+    token_start: usize,
     /// The source string.
     src: &'src str,
     /// The last character to be read.
     current_char: Option<char>,
     /// The kind of token we read last. Used for automatic semicolon insertion.
     last_token_kind: Option<TokenKind>,
+    config: LexerConfig,
 }
 
 impl<'src> Lexer<'src> {
     /// Create a new Lexer from the given source string.
-    pub fn new(s: &str) -> Lexer {
+    ///
+    /// The whole source must be in memory; use `read_source` to load it from a file or pipe.
+    /// A leading byte order mark is skipped, but spans are still relative to the start of `s`.
+    /// Returns an error if the source contains a NUL byte.
+    pub fn new(s: &str) -> LResult<Lexer> {
+        Lexer::with_config(s, LexerConfig::default())
+    }
+
+    /// Create a new Lexer from the given source string, with custom options.
+    pub fn with_config(s: &str, config: LexerConfig) -> LResult<Lexer> {
+        if let Some(nul_offset) = s.bytes().position(|b| b == 0) {
+            return Err(Error {
+                span: Span {
+                    start: nul_offset as u32,
+                    end: nul_offset as u32 + 1,
+                },
+                kind: ErrorKind::NulCharacter,
+            });
+        }
+
+        let offset = if s.starts_with(BOM) {
+            BOM.len_utf8()
+        } else {
+            0
+        };
+
         // Initialize the lexer with the first character of the source string.
-        let first_char = s.chars().next();
+        let first_char = s[offset..].chars().next();
 
-        Lexer {
+        Ok(Lexer {
             src: s,
-            offset: 0,
+            offset: offset,
+            token_start: offset,
             current_char: first_char,
             last_token_kind: None,
+            config: config,
+        })
+    }
+
+    /// Create a new Lexer which starts scanning `s` at the given byte offset.
+    ///
+    /// Spans are still relative to the start of `s`, which makes it possible to re-lex a region of
+    /// a larger file. Returns an error if `offset` is not on a character boundary.
+    pub fn new_at(s: &str, offset: usize) -> LResult<Lexer> {
+        if !s.is_char_boundary(offset) {
+            return Err(Error {
+                span: Span {
+                    start: offset as u32,
+                    end: offset as u32,
+                },
+                kind: ErrorKind::InvalidOffset,
+            });
+        }
+
+        let mut lexer = try!(Lexer::new(s));
+        if offset > lexer.offset {
+            lexer.offset = offset;
+            lexer.token_start = offset;
+            lexer.current_char = s[offset..].chars().next();
         }
+
+        Ok(lexer)
+    }
+
+    /// The byte offset in the source string at which the lexer will resume scanning.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The part of the source string which has not been scanned yet.
+    pub fn remaining(&self) -> &'src str {
+        &self.src[self.offset..]
+    }
+
+    /// Return the row and column of the character at the given byte offset, taking the configured
+    /// tab width into account.
+    ///
+    /// Returns an error if `offset` is past the end of the source or not on a character boundary.
+    pub fn position(&self, offset: u32) -> LResult<Position> {
+        Position::from_offset(self.src, offset as usize, self.config.tab_width).ok_or(Error {
+            span: Span {
+                start: offset,
+                end: offset,
+            },
+            kind: ErrorKind::InvalidOffset,
+        })
     }
 
     /// 'eat' one character.
@@ -87,22 +220,8 @@ impl<'src> Lexer<'src> {
 
         // If we have a hexadecimal, treat it specially.
         if self.current_char == Some('0') &&
-           (self.next_char() == Some('x') || self.next_char() == Some('x')) {
-            self.bump();
-            self.bump();
-
-            while let Some(c) = self.current_char {
-                if c.is_digit(16) {
-                    self.bump();
-                } else {
-                    break;
-                }
-            }
-
-            return Token {
-                value: Some(self.src[start..self.offset].into()),
-                kind: TokenKind::Hex,
-            };
+           (self.next_char() == Some('x') || self.next_char() == Some('X')) {
+            return self.scan_hex_number(start);
         }
 
         let has_leading_zero = self.current_char == Some('0');
@@ -150,6 +269,60 @@ impl<'src> Lexer<'src> {
         }
     }
 
+    /// Scan a hexadecimal integer or float literal, starting at its `0x` prefix.
+    fn scan_hex_number(&mut self, start: usize) -> Token {
+        // hex_lit         = "0" ( "x" | "X" ) hex_digit { hex_digit } .
+        // hex_float_lit   = "0" ( "x" | "X" ) hex_mantissa hex_exponent .
+        // hex_mantissa    = hex_digits [ "." [ hex_digits ] ] | "." hex_digits .
+        // hex_exponent    = ( "p" | "P" ) [ "+" | "-" ] decimal_digits .
+        //
+        // A mantissa without an exponent is scanned as a float too, so that the parser can report
+        // the missing exponent.
+
+        self.bump();
+        self.bump();
+
+        let mut had_dot = false;
+        let mut had_p = false;
+
+        while let Some(c) = self.current_char {
+            if c.is_digit(16) {
+                self.bump();
+            } else if !had_dot && c == '.' {
+                self.bump();
+                had_dot = true;
+            } else {
+                break;
+            }
+        }
+
+        if self.current_char == Some('p') || self.current_char == Some('P') {
+            self.bump();
+            had_p = true;
+
+            if self.current_char == Some('+') || self.current_char == Some('-') {
+                self.bump();
+            }
+            while self.current_char.map_or(false, |c| c.is_digit(10)) {
+                self.bump();
+            }
+        }
+
+        let kind = if !had_dot && !had_p {
+            TokenKind::Hex
+        } else if self.current_char == Some('i') {
+            self.bump();
+            TokenKind::Imaginary
+        } else {
+            TokenKind::Float
+        };
+
+        Token {
+            value: Some(self.src[start..self.offset].into()),
+            kind: kind,
+        }
+    }
+
     /// Skip whitespace and comments, returning whether at least one newline was encountered.
     fn skip_whitespace_and_comments(&mut self) -> bool {
         let mut contains_newline = false;
@@ -223,38 +396,13 @@ impl<'src> Lexer<'src> {
 
     fn scan_ident_or_keyword(&mut self) -> Token {
         let ident = self.scan_ident();
+        trace!("scan_ident_or_keyword: {:?}", ident);
         let mut value = None;
 
-        use token::TokenKind::*;
-
-        let kind = match &*ident {
-            "break" => Break,
-            "case" => Case,
-            "chan" => Chan,
-            "const" => Const,
-            "continue" => Continue,
-            "default" => Default,
-            "defer" => Defer,
-            "else" => Else,
-            "fallthrough" => Fallthrough,
-            "for" => For,
-            "func" => Func,
-            "go" => Go,
-            "goto" => Goto,
-            "if" => If,
-            "import" => Import,
-            "interface" => Interface,
-            "map" => Map,
-            "package" => Package,
-            "range" => Range,
-            "return" => Return,
-            "select" => Select,
-            "struct" => Struct,
-            "switch" => Switch,
-            "type" => Type,
-            "var" => Var,
+        let kind = match TokenKind::from_keyword(ident) {
+            Some(kind) => kind,
             // XXX(perf): unnecessary alloc.
-            _ => {
+            None => {
                 value = Some(ident.into());
                 TokenKind::Ident
             }
@@ -284,6 +432,9 @@ impl<'src> Lexer<'src> {
             });
         }
 
+        // The inserted semicolon above spans the whitespace; actual tokens don't.
+        self.token_start = self.offset;
+
         // Check for EOF after whitespace handling.
         let c = match self.current_char {
             Some(c) => c,
@@ -521,6 +672,10 @@ impl<'src> Lexer<'src> {
                     _ => TokenKind::Caret,
                 }
             }
+            '~' => {
+                self.bump();
+                TokenKind::Tilde
+            }
             '%' => {
                 self.bump();
 
@@ -539,7 +694,14 @@ impl<'src> Lexer<'src> {
             '"' => return Some(self.scan_interpreted_str_lit()),
             '`' => return Some(self.scan_raw_str_lit()),
             '\'' => return Some(self.scan_rune_lit()),
-            c => panic!("unexpected start of token: '{}'", c),
+            c if self.config.lenient => {
+                self.bump();
+                return Some(Token {
+                    kind: TokenKind::Error,
+                    value: Some(c.to_string()),
+                });
+            }
+            c => panic!("{}", ErrorKind::IllegalCharacter(c)),
         };
 
         Some(Token {
@@ -559,7 +721,9 @@ impl<'src> Lexer<'src> {
             // following character.
             if c == '\\' {
                 self.bump();
-                self.bump();
+                if self.current_char.is_some() {
+                    self.bump();
+                }
             } else if c == '\'' {
                 break;
             } else {
@@ -567,6 +731,10 @@ impl<'src> Lexer<'src> {
             }
         }
 
+        if self.current_char.is_none() {
+            return self.unterminated_lit(TokenKind::Rune);
+        }
+
         let s = &self.src[start..self.offset];
 
         // Skip the quote _after_ slicing so that it isn't included
@@ -588,7 +756,9 @@ impl<'src> Lexer<'src> {
             // following character.
             if c == '\\' {
                 self.bump();
-                self.bump();
+                if self.current_char.is_some() {
+                    self.bump();
+                }
             } else if c == '"' {
                 break;
             } else {
@@ -596,6 +766,10 @@ impl<'src> Lexer<'src> {
             }
         }
 
+        if self.current_char.is_none() {
+            return self.unterminated_lit(TokenKind::Str);
+        }
+
         let s = &self.src[start..self.offset];
 
         // Skip the quote _after_ slicing so that it isn't included
@@ -624,6 +798,10 @@ impl<'src> Lexer<'src> {
             }
         }
 
+        if self.current_char.is_none() {
+            return self.unterminated_lit(TokenKind::StrRaw);
+        }
+
         let s = &self.src[start..self.offset];
 
         // Skip the backtick _after_ slicing so that it isn't included
@@ -636,47 +814,196 @@ impl<'src> Lexer<'src> {
             kind: TokenKind::StrRaw,
         }
     }
+
+    /// Handle a literal of the given kind which is still open at the end of the source.
+    ///
+    /// In lenient mode, the whole literal, opening quote included, becomes an `Error` token.
+    fn unterminated_lit(&mut self, kind: TokenKind) -> Token {
+        if !self.config.lenient {
+            panic!("{}", ErrorKind::UnterminatedLiteral(kind));
+        }
+
+        Token {
+            kind: TokenKind::Error,
+            value: Some(self.src[self.token_start..self.offset].into()),
+        }
+    }
 }
 
 impl<'src> Iterator for Lexer<'src> {
     type Item = TokenAndSpan;
 
     fn next(&mut self) -> Option<TokenAndSpan> {
-        let start = self.offset as u32;
+        self.token_start = self.offset;
         let t = self.next_token_inner();
         self.last_token_kind = t.as_ref().map(|t| t.kind);
 
         t.map(|t| {
+            let span = Span {
+                start: self.token_start as u32,
+                end: self.offset as u32,
+            };
+            trace!("lexed {} at {}", t, span);
+
             TokenAndSpan {
                 token: t,
-                span: Span {
-                    start: start,
-                    end: self.offset as u32,
-                },
+                span: span,
             }
         })
     }
 }
 
 /// Convenience function to collect all the tokens from a string.
-pub fn tokenize(s: &str) -> Vec<TokenAndSpan> {
-    let lexer = Lexer::new(s);
-    lexer.collect()
+///
+/// Returns an error at the first character that can't start a token, or at a literal which is
+/// still open at the end of the source.
+pub fn tokenize(s: &str) -> LResult<Vec<TokenAndSpan>> {
+    let config = LexerConfig { lenient: true, ..LexerConfig::default() };
+    let lexer = try!(Lexer::with_config(s, config));
+    let mut tokens = Vec::new();
+
+    for t in lexer {
+        if t.token.kind == TokenKind::Error {
+            let c = t.token.value.as_ref().and_then(|v| v.chars().next()).unwrap();
+            // Characters which start a literal are only turned into errors when the literal is
+            // left open.
+            let kind = match c {
+                '\'' => ErrorKind::UnterminatedLiteral(TokenKind::Rune),
+                '"' => ErrorKind::UnterminatedLiteral(TokenKind::Str),
+                '`' => ErrorKind::UnterminatedLiteral(TokenKind::StrRaw),
+                c => ErrorKind::IllegalCharacter(c),
+            };
+            return Err(Error {
+                span: t.span,
+                kind: kind,
+            });
+        }
+        tokens.push(t);
+    }
+
+    Ok(tokens)
+}
+
+/// Collect all the tokens from a string like `tokenize`, followed by an `Eof` token with an empty
+/// span at the end of the source.
+///
+/// The parser reports a missing token the same way whether or not the stream ends with `Eof`.
+pub fn lex_all(s: &str) -> LResult<Vec<TokenAndSpan>> {
+    let mut tokens = try!(tokenize(s));
+    let end = s.len() as u32;
+    tokens.push(TokenAndSpan {
+        token: Token {
+            kind: TokenKind::Eof,
+            value: None,
+        },
+        span: Span {
+            start: end,
+            end: end,
+        },
+    });
+    Ok(tokens)
+}
+
+/// Collect the tokens from a string in a canonical form, without their spans, so that two sources
+/// which only differ in formatting give the same tokens.
+///
+/// On top of automatic semicolon insertion, a semicolon is added at the end of the source where a
+/// newline would have inserted one, and semicolons directly before a closing ")" or "}" are
+/// dropped, as they are optional there.
+pub fn canonical_tokens(s: &str) -> LResult<Vec<Token>> {
+    let mut tokens: Vec<Token> = Vec::new();
+
+    for t in try!(tokenize(s)) {
+        let kind = t.token.kind;
+        if (kind == TokenKind::RParen || kind == TokenKind::RBrace) &&
+           tokens.last().map(|t| t.kind) == Some(TokenKind::Semicolon) {
+            tokens.pop();
+        }
+        tokens.push(t.token);
+    }
+
+    if may_terminate_statement(tokens.last().map(|t| t.kind)) {
+        tokens.push(Token {
+            kind: TokenKind::Semicolon,
+            value: None,
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// Read a whole source file from `reader` into a string, ready to be lexed.
+///
+/// Invalid UTF-8 is reported with a span covering the first offending byte. An I/O error is
+/// reported with an empty span at the start of the source.
+pub fn read_source<R: Read>(mut reader: R) -> LResult<String> {
+    let mut bytes = Vec::new();
+    if let Err(err) = reader.read_to_end(&mut bytes) {
+        return Err(Error {
+            span: Span { start: 0, end: 0 },
+            kind: ErrorKind::Io(err.to_string()),
+        });
+    }
+
+    String::from_utf8(bytes).map_err(|err| {
+        let offset = err.utf8_error().valid_up_to() as u32;
+        Error {
+            span: Span {
+                start: offset,
+                end: offset + 1,
+            },
+            kind: ErrorKind::InvalidUtf8,
+        }
+    })
+}
+
+/// Convenience function to collect all the tokens from a reader. See `read_source`.
+pub fn tokenize_reader<R: Read>(reader: R) -> LResult<Vec<TokenAndSpan>> {
+    tokenize(&try!(read_source(reader)))
+}
+
+/// Describe the tokens of a string, one per line, with their kind, span and source text.
+///
+/// Meant for debugging the lexer and for snapshot tests. Illegal characters show up as `Error`
+/// tokens rather than making the whole dump fail. Automatically inserted semicolons span the
+/// whitespace they replace.
+///
+/// ```
+/// use rgo::lexer::debug_tokens;
+///
+/// assert_eq!(debug_tokens("package main\n").unwrap(),
+///            "Package @0..7 \"package\"\n\
+///             Ident(main) @8..12 \"main\"\n\
+///             Semicolon @12..13 \"\\n\"\n");
+/// ```
+pub fn debug_tokens(s: &str) -> LResult<String> {
+    let config = LexerConfig { lenient: true, ..LexerConfig::default() };
+    let lexer = try!(Lexer::with_config(s, config));
+    let mut out = String::new();
+
+    for t in lexer {
+        let text = &s[t.span.start as usize..t.span.end as usize];
+        out.push_str(&format!("{} @{} {:?}\n", t.token, t.span, text));
+    }
+
+    Ok(out)
 }
 
 
 // =====
 // Utility functions.
 //
-// XXX(perf): expensive checks on Unicode chars (is_alphabetic(), is_numeric()) in these functions.
+// XXX(perf): non-ASCII chars are looked up in tables by binary search in these functions.
 // =====
 
+// identifier = letter { letter | unicode_digit } .
+// letter     = unicode_letter | "_" .
 fn can_start_identifier(c: char) -> bool {
-    c.is_alphabetic() || c == '_'
+    unicode::is_letter(c) || c == '_'
 }
 
 fn can_continue_identifier(c: char) -> bool {
-    c.is_alphabetic() || c.is_numeric() || c == '_'
+    unicode::is_letter(c) || unicode::is_digit(c) || c == '_'
 }
 
 fn char_at(s: &str, byte: usize) -> char {
@@ -0,0 +1,54 @@
+//! Token positions in the row/column form editors work with, e.g. for semantic highlighting.
+
+use Position;
+use super::{Lexer, LexerConfig, LResult, TokenKind};
+
+/// A token, located by row and column rather than by byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub kind: TokenKind,
+    /// The position of the first character of the token.
+    pub start: Position,
+    /// The position just past the last character of the token. It is on a later row than `start`
+    /// for raw strings spanning several lines.
+    pub end: Position,
+}
+
+/// Locate every token of `src` by row and column.
+///
+/// Columns count characters, with a tab counting as one, and both rows and columns are 1-indexed;
+/// an editor which counts UTF-16 code units from 0 has to convert them. Automatically inserted
+/// semicolons are left out, as they don't appear in the source, and illegal characters show up
+/// as `Error` tokens. Returns an error only if the source can't be lexed at all.
+///
+/// `TokenKind::highlight_class` gives the class to highlight each token with.
+pub fn semantic_tokens(src: &str) -> LResult<Vec<SemanticToken>> {
+    let config = LexerConfig { lenient: true, ..LexerConfig::default() };
+    let lexer = try!(Lexer::with_config(src, config));
+    let tab_width = config.tab_width;
+
+    // Positions are computed in a single pass, carrying the position of `offset` along.
+    let mut pos = Position::start();
+    let mut offset = 0;
+    let mut tokens = Vec::new();
+
+    for t in lexer {
+        let (start, end) = (t.span.start as usize, t.span.end as usize);
+        if t.token.kind == TokenKind::Semicolon && &src[start..end] != ";" {
+            continue;
+        }
+
+        pos.advance(&src[offset..start], tab_width);
+        let token_start = pos.clone();
+        pos.advance(&src[start..end], tab_width);
+        offset = end;
+
+        tokens.push(SemanticToken {
+            kind: t.token.kind,
+            start: token_start,
+            end: pos.clone(),
+        });
+    }
+
+    Ok(tokens)
+}
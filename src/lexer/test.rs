@@ -1,10 +1,14 @@
-use super::{Token, TokenKind, tokenize};
+use super::{Lexer, LexerConfig, Token, TokenKind, ErrorKind, Span, UnbalancedDelim, tokenize,
+            tokenize_reader, canonical_tokens, check_balanced, HighlightClass, can_start_identifier,
+            can_continue_identifier, LineMap, FileLine, lex_all, format_tokens, semantic_tokens,
+            SemanticToken};
+use Position;
 use token::TokenKind::*;
 
 // XXX: use the full TokenKind::* path, or `use TokenKind::*`?
 
 fn assert_tokens(code: &str, expect: &[(TokenKind, Option<&str>)]) {
-    let got = tokenize(code);
+    let got = tokenize(code).unwrap();
 
     // If the assertion fails, having a log message will be very useful.
     println!("got:\n{:#?}", got);
@@ -83,6 +87,7 @@ fn tokenize_simple() {
                      ("!=", TokenKind::NotEqual),
                      ("^", TokenKind::Caret),
                      ("^=", TokenKind::CaretAssign),
+                     ("~", TokenKind::Tilde),
                      ("%", TokenKind::Percent),
                      ("%=", TokenKind::PercentAssign),
                      ("&", TokenKind::And),
@@ -125,6 +130,36 @@ fn tokenize_simple() {
     }
 }
 
+#[test]
+fn tokenize_every_operator() {
+    let src = "+ - * / % & | ^ << >> &^ && || <- ++ -- == < > = ! != <= >= := ... . , ; : \
+               ( ) [ ] { } += -= *= /= %= &= |= ^= <<= >>= &^= ~";
+    let expect = [Plus, Minus, Star, Slash, Percent, And, Or, Caret, Lshift, Rshift, BitClear,
+                  AndAnd, OrOr, Arrow, Increment, Decrement, Equals, LessThan, GreaterThan,
+                  Assign, Not, NotEqual, LessThanOrEqual, GreaterThanOrEqual, ColonAssign,
+                  Ellipsis, Dot, Comma, Semicolon, Colon, LParen, RParen, LBracket, RBracket,
+                  LBrace, RBrace, PlusAssign, MinusAssign, StarAssign, SlashAssign,
+                  PercentAssign, AndAssign, OrAssign, CaretAssign, LshiftAssign, RshiftAssign,
+                  BitClearAssign, Tilde];
+
+    let expect: Vec<_> = expect.iter().map(|&kind| (kind, None)).collect();
+    assert_tokens(src, &expect);
+
+    // Every operator in the source was checked.
+    assert_eq!(expect.len(), src.split_whitespace().count());
+}
+
+#[test]
+fn tokenize_tilde_type_term() {
+    assert_tokens("~int", &[(Tilde, None), (Ident, Some("int"))]);
+    assert_tokens("~int | ~string",
+                  &[(Tilde, None),
+                    (Ident, Some("int")),
+                    (Or, None),
+                    (Tilde, None),
+                    (Ident, Some("string"))]);
+}
+
 #[test]
 fn tokenize_comments() {
     assert_tokens("// Hello, this is a comment", &[]);
@@ -176,6 +211,32 @@ fn tokenize_keywords() {
     }
 }
 
+#[test]
+fn tokenize_predeclared_identifiers() {
+    // Predeclared identifiers can be shadowed like any other name, so they are not keywords.
+    let predeclared = ["any", "append", "bool", "byte", "cap", "clear", "close", "complex",
+                       "error", "false", "int", "iota", "len", "make", "max", "min", "new", "nil",
+                       "panic", "print", "recover", "rune", "string", "true", "uintptr"];
+
+    for &s in &predeclared {
+        assert_token(s, TokenKind::Ident, Some(s));
+        assert!(!TokenKind::is_keyword_str(s), "{:?}", s);
+    }
+}
+
+#[test]
+fn keyword_lookup() {
+    assert_eq!(TokenKind::from_keyword("fallthrough"), Some(TokenKind::Fallthrough));
+    assert_eq!(TokenKind::from_keyword("struct"), Some(TokenKind::Struct));
+    assert!(TokenKind::is_keyword_str("chan"));
+
+    // Same length and first letter as a keyword, or a prefix of one.
+    for s in &["", "c", "cast", "swatch", "fo", "gox", "Type", "interfaces", "fallthrougg"] {
+        assert_eq!(TokenKind::from_keyword(s), None, "{:?}", s);
+        assert!(!TokenKind::is_keyword_str(s));
+    }
+}
+
 #[test]
 fn tokenize_mixed_whitespace() {
     assert_tokens(" \t
@@ -184,6 +245,24 @@ fn tokenize_mixed_whitespace() {
                   &[]);
 }
 
+#[test]
+fn tokenize_newlines_only_as_semicolons() {
+    // Runs of whitespace never produce a token of their own: a newline either becomes an
+    // automatically inserted semicolon, or disappears entirely.
+    assert_tokens("x := 1\n\n\ty++\n",
+                  &[(TokenKind::Ident, Some("x")),
+                    (TokenKind::ColonAssign, None),
+                    (TokenKind::Decimal, Some("1")),
+                    (TokenKind::Semicolon, None),
+                    (TokenKind::Ident, Some("y")),
+                    (TokenKind::Increment, None),
+                    (TokenKind::Semicolon, None)]);
+    assert_tokens("a +\n\tb",
+                  &[(TokenKind::Ident, Some("a")),
+                    (TokenKind::Plus, None),
+                    (TokenKind::Ident, Some("b"))]);
+}
+
 #[test]
 fn tokenize_package_declaration() {
     assert_tokens("package main",
@@ -300,3 +379,541 @@ func main() {
 
     assert_tokens(src, &expected);
 }
+
+// =====
+// Source encoding
+// =====
+
+#[test]
+fn tokenize_bom_prefixed() {
+    let src = "\u{FEFF}package main";
+
+    assert_tokens(src, &[(Package, None), (Ident, Some("main"))]);
+
+    // Spans are still relative to the start of the source, BOM included.
+    let tokens = tokenize(src).unwrap();
+    assert_eq!(tokens[0].span, Span { start: 3, end: 10 });
+}
+
+#[test]
+fn tokenize_from_reader() {
+    use std::io::{self, Cursor, Read};
+
+    let src = "package main\n";
+    let tokens = tokenize_reader(Cursor::new(src.as_bytes().to_vec())).unwrap();
+    assert_eq!(tokens, tokenize(src).unwrap());
+
+    let err = tokenize_reader(Cursor::new(b"var s = \"\xff\"\n".to_vec())).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidUtf8);
+    assert_eq!(err.span, Span { start: 9, end: 10 });
+
+    struct Broken;
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "broken pipe"))
+        }
+    }
+    let err = tokenize_reader(Broken).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Io("broken pipe".into()));
+}
+
+#[test]
+fn canonical_tokens_ignore_formatting() {
+    let canonical = |src| canonical_tokens(src).unwrap();
+
+    assert_eq!(canonical("func f(){}"), canonical("func f() {\n}\n"));
+    assert_eq!(canonical("func f() { return }"),
+               canonical("func f() {\n\t// Nothing to do.\n\treturn\n}"));
+    assert_eq!(canonical("package main; import \"fmt\""),
+               canonical("package main\n\nimport \"fmt\"\n"));
+    assert_eq!(canonical("f(a, b)"), canonical("f(a,\n\tb)"));
+
+    assert!(canonical("x := 1") != canonical("x := 2"));
+    assert_eq!(canonical("x++"),
+               [Token { kind: Ident, value: Some("x".into()) },
+                Token { kind: Increment, value: None },
+                Token { kind: Semicolon, value: None }]);
+}
+
+#[test]
+fn tokenize_illegal_characters() {
+    for &(src, c, msg) in &[("a @ b", '@', "illegal character U+0040 '@'"),
+                             ("a # b", '#', "illegal character U+0023 '#'"),
+                             ("a $ b", '$', "illegal character U+0024 '$'")] {
+        let err = tokenize(src).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IllegalCharacter(c));
+        assert_eq!(err.span, Span { start: 2, end: 3 });
+        assert_eq!(err.kind.to_string(), msg);
+    }
+
+    // They're fine inside literals and comments.
+    assert_tokens("\"@#$\" // @#$", &[(Str, Some("@#$"))]);
+}
+
+#[test]
+fn tokenize_adjacent_strs() {
+    // Unlike C, Go doesn't join adjacent string literals.
+    assert_tokens(r#""a" "b" `c`"#,
+                  &[(Str, Some("a")), (Str, Some("b")), (StrRaw, Some("c"))]);
+}
+
+#[test]
+fn tokenize_nul() {
+    let err = tokenize("package\0main").unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::NulCharacter);
+    assert_eq!(err.span, Span { start: 7, end: 8 });
+}
+
+#[test]
+fn display_nul_error() {
+    let err = ::Error::from(tokenize("package\0main").unwrap_err());
+
+    assert_eq!(err.span(), Span { start: 7, end: 8 });
+    assert_eq!(format!("{}", err), "lexical error at 7..8: illegal NUL character");
+}
+
+// =====
+// Positions
+// =====
+
+#[test]
+fn token_span_excludes_leading_whitespace() {
+    let tokens = tokenize("  foo").unwrap();
+    assert_eq!(tokens[0].span, Span { start: 2, end: 5 });
+}
+
+/// Lex `src` and return the position of the first `foo` identifier.
+fn position_of_foo(src: &str, config: LexerConfig) -> Position {
+    let mut lexer = Lexer::with_config(src, config).unwrap();
+    let tokens: Vec<_> = lexer.by_ref().collect();
+    let foo = tokens.iter().find(|t| t.token.value == Some("foo".into())).unwrap();
+    lexer.position(foo.span.start).unwrap()
+}
+
+#[test]
+fn position_with_tabs() {
+    let src = "package main\n\t\tfoo := 1";
+
+    assert_eq!(position_of_foo(src, LexerConfig::default()),
+               Position { row: 2, column: 3 });
+    assert_eq!(position_of_foo(src, LexerConfig { tab_width: 4, ..LexerConfig::default() }),
+               Position { row: 2, column: 9 });
+
+    // A tab advances to the next tab stop, rather than by a fixed width.
+    let config = LexerConfig { tab_width: 4, ..LexerConfig::default() };
+    assert_eq!(position_of_foo("a\tfoo", config), Position { row: 1, column: 5 });
+    assert_eq!(position_of_foo("abcd\tfoo", config), Position { row: 1, column: 9 });
+}
+
+#[test]
+fn position_rejects_invalid_offsets() {
+    let lexer = Lexer::new("x := \"日本\"").unwrap();
+
+    for &offset in &[7, 14, 100] {
+        let err = lexer.position(offset).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidOffset);
+        assert_eq!(err.span, Span { start: offset, end: offset });
+    }
+    assert_eq!(lexer.position(13), Ok(Position { row: 1, column: 10 }));
+}
+
+// =====
+// Partial lexing
+// =====
+
+#[test]
+fn new_at_reports_absolute_spans() {
+    let src = "package main\n\nvar x = 1\n";
+    let start = src.find("var").unwrap();
+
+    let mut lexer = Lexer::new_at(src, start).unwrap();
+    assert_eq!(lexer.offset(), start);
+    assert_eq!(lexer.remaining(), "var x = 1\n");
+
+    let tokens: Vec<_> = lexer.by_ref().collect();
+    let kinds: Vec<_> = tokens.iter().map(|t| t.token.kind).collect();
+    assert_eq!(kinds,
+               vec![TokenKind::Var,
+                    TokenKind::Ident,
+                    TokenKind::Assign,
+                    TokenKind::Decimal,
+                    TokenKind::Semicolon]);
+    assert_eq!(tokens[0].span, Span { start: 14, end: 17 });
+    assert_eq!(tokens[3].span, Span { start: 22, end: 23 });
+    assert_eq!(lexer.remaining(), "");
+}
+
+#[test]
+fn new_at_rejects_non_char_boundary() {
+    let err = Lexer::new_at("x := \"日本\"", 7).err().unwrap();
+
+    assert_eq!(err.kind, ErrorKind::InvalidOffset);
+    assert_eq!(err.span, Span { start: 7, end: 7 });
+}
+
+#[test]
+fn cloned_lexer_advances_independently() {
+    let mut lexer = Lexer::new("a + b").unwrap();
+    assert_eq!(lexer.next().unwrap().token.kind, TokenKind::Ident);
+
+    let mut lookahead = lexer.clone();
+    let rest: Vec<_> = lookahead.by_ref().map(|t| t.token.kind).collect();
+    assert_eq!(rest, vec![TokenKind::Plus, TokenKind::Ident]);
+    assert_eq!(lookahead.offset(), 5);
+
+    // The original lexer is right where it was.
+    assert_eq!(lexer.offset(), 1);
+    let next = lexer.next().unwrap();
+    assert_eq!(next.token.kind, TokenKind::Plus);
+    assert_eq!(next.span, Span { start: 2, end: 3 });
+}
+
+// =====
+// Operator classification
+// =====
+
+#[test]
+fn highlight_classes() {
+    let classes: Vec<_> = tokenize("func f(s string) { x := \"hi\" + s[0] + 'c'; return 1.5 }")
+        .unwrap()
+        .iter()
+        .map(|t| (t.token.value.clone().unwrap_or_else(|| t.token.kind.to_string()),
+                  t.token.highlight_class()))
+        .collect();
+
+    let get = |text: &str| {
+        classes.iter().find(|&&(ref t, _)| t == text).map(|&(_, class)| class)
+    };
+    assert_eq!(get("Func"), Some(HighlightClass::Keyword));
+    assert_eq!(get("Return"), Some(HighlightClass::Keyword));
+    assert_eq!(get("string"), Some(HighlightClass::Identifier));
+    assert_eq!(get("hi"), Some(HighlightClass::String));
+    assert_eq!(get("c"), Some(HighlightClass::Char));
+    assert_eq!(get("1.5"), Some(HighlightClass::Number));
+    assert_eq!(get("Plus"), Some(HighlightClass::Operator));
+    assert_eq!(get("ColonAssign"), Some(HighlightClass::Operator));
+    assert_eq!(get("LBrace"), Some(HighlightClass::Punctuation));
+    assert_eq!(get("Semicolon"), Some(HighlightClass::Punctuation));
+
+    assert_eq!(TokenKind::Eof.highlight_class(), HighlightClass::None);
+}
+
+#[test]
+fn binary_precedence() {
+    assert!(Star.binary_precedence() > Plus.binary_precedence());
+    assert!(Plus.binary_precedence() > Equals.binary_precedence());
+    assert!(Equals.binary_precedence() > AndAnd.binary_precedence());
+
+    let binary_ops = [Star, Slash, Percent, Lshift, Rshift, And, BitClear, Plus, Minus, Or, Caret,
+                      Equals, NotEqual, LessThan, LessThanOrEqual, GreaterThan,
+                      GreaterThanOrEqual, AndAnd, OrOr];
+    let lowest = binary_ops.iter().filter_map(|op| op.binary_precedence()).min();
+    assert_eq!(lowest, OrOr.binary_precedence());
+    assert_eq!(OrOr.binary_precedence(), Some(1));
+
+    assert_eq!(Ident.binary_precedence(), None);
+    assert_eq!(Not.binary_precedence(), None);
+    assert_eq!(Assign.binary_precedence(), None);
+}
+
+#[test]
+fn unary_ops() {
+    for op in &[Plus, Minus, Not, Caret, Star, And, Arrow] {
+        assert!(op.is_unary_op(), "{} should be a unary operator", op);
+    }
+
+    assert!(!Slash.is_unary_op());
+    assert!(!OrOr.is_unary_op());
+}
+
+// =====
+// Delimiter balance
+// =====
+
+fn balance(src: &str) -> Result<(), UnbalancedDelim> {
+    check_balanced(&tokenize(src).unwrap())
+}
+
+#[test]
+fn balanced_delims() {
+    assert_eq!(balance(""), Ok(()));
+    assert_eq!(balance("func f(a []int) { g(a[0]) }"), Ok(()));
+}
+
+#[test]
+fn mismatched_delims() {
+    assert_eq!(balance("([)]"),
+               Err(UnbalancedDelim::Mismatched {
+                   index: 2,
+                   found: RParen,
+                   expected: Some(RBracket),
+               }));
+    assert_eq!(balance("a)"),
+               Err(UnbalancedDelim::Mismatched {
+                   index: 1,
+                   found: RParen,
+                   expected: None,
+               }));
+}
+
+#[test]
+fn unclosed_delims() {
+    assert_eq!(balance("(("),
+               Err(UnbalancedDelim::Unclosed {
+                   index: 1,
+                   opener: LParen,
+               }));
+    assert_eq!(format!("{}", balance("((").unwrap_err()),
+               "token 1: unclosed \"LParen\"");
+}
+
+#[test]
+fn identifiers_use_go_letter_classes() {
+    assert_tokens("héllo ünïcode_ident x٣ 世界",
+                  &[(Ident, Some("héllo")),
+                    (Ident, Some("ünïcode_ident")),
+                    (Ident, Some("x٣")),
+                    (Ident, Some("世界"))]);
+
+    // Letters of any case, modifier letters and other letters (category L) may start identifiers.
+    for &c in &['a', 'Z', '_', 'é', 'ǅ', 'ʰ', 'ª', '世'] {
+        assert!(can_start_identifier(c), "{:?} should start an identifier", c);
+    }
+
+    // Alphabetic in Rust, but not Go letters: a letter number (Nl), a combining vowel sign (Mc)
+    // and a circled letter (So).
+    for &c in &['Ⅷ', '\u{93e}', 'Ⓐ'] {
+        assert!(c.is_alphabetic());
+        assert!(!can_start_identifier(c), "{:?} should not start an identifier", c);
+        assert!(!can_continue_identifier(c), "{:?} should not continue an identifier", c);
+    }
+
+    // Decimal digits (Nd) may continue identifiers, but other numeric characters may not.
+    for &c in &['0', '٣', '９'] {
+        assert!(!can_start_identifier(c));
+        assert!(can_continue_identifier(c), "{:?} should continue an identifier", c);
+    }
+    for &c in &['²', '½', 'Ⅷ'] {
+        assert!(c.is_numeric());
+        assert!(!can_continue_identifier(c), "{:?} should not continue an identifier", c);
+    }
+}
+
+#[test]
+fn tokenize_hex_floats() {
+    assert_token("0x1p-2", Float, Some("0x1p-2"));
+    assert_token("0x.8p1", Float, Some("0x.8p1"));
+    assert_token("0X1.8P+3", Float, Some("0X1.8P+3"));
+    assert_token("0x1p4i", Imaginary, Some("0x1p4i"));
+    assert_token("0XFF", Hex, Some("0XFF"));
+
+    // The missing exponent is reported by the parser.
+    assert_token("0x1.8", Float, Some("0x1.8"));
+}
+
+#[test]
+fn lenient_lexer_emits_error_tokens() {
+    let config = LexerConfig { lenient: true, ..LexerConfig::default() };
+    let tokens: Vec<_> = Lexer::with_config("x @ y\n$", config).unwrap().collect();
+
+    let kinds: Vec<_> = tokens.iter().map(|t| (t.token.kind, t.token.value.clone())).collect();
+    assert_eq!(kinds,
+               vec![(Ident, Some("x".into())),
+                    (Error, Some("@".into())),
+                    (Ident, Some("y".into())),
+                    (Semicolon, None),
+                    (Error, Some("$".into()))]);
+
+    assert_eq!(tokens[1].span, Span { start: 2, end: 3 });
+    assert_eq!(tokens[1].token.highlight_class(), HighlightClass::Error);
+}
+
+#[test]
+fn unterminated_literals() {
+    let config = LexerConfig { lenient: true, ..LexerConfig::default() };
+    let cases = [("x := \"abc", "\"abc", Str),
+                 ("x := \"abc\\", "\"abc\\", Str),
+                 ("x := 'a", "'a", Rune),
+                 ("x := '\\", "'\\", Rune),
+                 ("x := `abc\ndef", "`abc\ndef", StrRaw)];
+
+    for &(src, lit, kind) in &cases {
+        let tokens: Vec<_> = Lexer::with_config(src, config).unwrap().collect();
+        let last = tokens.last().unwrap();
+        let span = Span {
+            start: 5,
+            end: src.len() as u32,
+        };
+        assert_eq!(last.token,
+                   Token {
+                       kind: Error,
+                       value: Some(lit.into()),
+                   },
+                   "lexing {:?}",
+                   src);
+        assert_eq!(last.span, span, "lexing {:?}", src);
+
+        let err = tokenize(src).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnterminatedLiteral(kind), "lexing {:?}", src);
+        assert_eq!(err.span, span, "lexing {:?}", src);
+    }
+
+    assert_eq!(ErrorKind::UnterminatedLiteral(StrRaw).to_string(),
+               "raw string literal not terminated");
+}
+
+#[test]
+fn line_directives_remap_lines() {
+    let src = "package main\n\n//line gen.go:100\nvar a = 1\n\nvar b = `\n//line raw:1\n`\n\
+               //line other.y:7\nvar c = 3 //line trailing:1\nvar d\n";
+    let map = LineMap::new("main.go", src).unwrap();
+    let line_of = |needle: &str| map.lookup(src.find(needle).unwrap());
+
+    assert_eq!(line_of("package"), FileLine { file: "main.go", line: 1 });
+    assert_eq!(line_of("//line gen"), FileLine { file: "main.go", line: 3 });
+    assert_eq!(line_of("var a"), FileLine { file: "gen.go", line: 100 });
+    assert_eq!(line_of("= 1"), FileLine { file: "gen.go", line: 100 });
+    // Directives inside string literals are not comments, and ones not at the start of a line are
+    // ignored.
+    assert_eq!(line_of("var b"), FileLine { file: "gen.go", line: 102 });
+    assert_eq!(line_of("`\n//line other"), FileLine { file: "gen.go", line: 104 });
+    assert_eq!(line_of("var c"), FileLine { file: "other.y", line: 7 });
+    assert_eq!(line_of("var d"), FileLine { file: "other.y", line: 8 });
+    assert_eq!(map.lookup(src.len()), FileLine { file: "other.y", line: 9 });
+}
+
+#[test]
+fn tokenize_bare_zero() {
+    // A lone `0` is an octal literal with no digits after the leading zero.
+    assert_token("0", Octal, Some("0"));
+    assert_tokens("0)", &[(Octal, Some("0")), (RParen, None)]);
+    assert_tokens("0;", &[(Octal, Some("0")), (Semicolon, None)]);
+    assert_tokens("f(0, 1)",
+                  &[(Ident, Some("f")),
+                    (LParen, None),
+                    (Octal, Some("0")),
+                    (Comma, None),
+                    (Decimal, Some("1")),
+                    (RParen, None)]);
+
+    assert_token("0.0", Float, Some("0.0"));
+    assert_token("0.5", Float, Some("0.5"));
+    assert_token("0e1", Float, Some("0e1"));
+    assert_token("0i", Imaginary, Some("0i"));
+    assert_token("0x0", Hex, Some("0x0"));
+}
+
+#[test]
+fn lex_all_ends_with_eof() {
+    for src in &["", "package main", "x := 1\n", "// comment\n\n"] {
+        let tokens = lex_all(src).unwrap();
+        let last = tokens.last().unwrap();
+        let end = src.len() as u32;
+        assert_eq!(last.token, Token { kind: Eof, value: None }, "lexing {:?}", src);
+        assert_eq!(last.span, Span { start: end, end: end }, "lexing {:?}", src);
+        assert_eq!(tokens.iter().filter(|t| t.token.kind == Eof).count(), 1);
+    }
+
+    assert_eq!(lex_all("a b").unwrap().len(), 3);
+}
+
+#[test]
+fn tokenize_dots() {
+    assert_token("...", Ellipsis, None);
+    assert_tokens("..", &[(Dot, None), (Dot, None)]);
+    assert_tokens("....", &[(Ellipsis, None), (Dot, None)]);
+    assert_tokens("......", &[(Ellipsis, None), (Ellipsis, None)]);
+    assert_token(".5", Float, Some(".5"));
+    assert_tokens("...5", &[(Ellipsis, None), (Decimal, Some("5"))]);
+    assert_tokens("..5", &[(Dot, None), (Float, Some(".5"))]);
+    assert_tokens("xs...)",
+                  &[(Ident, Some("xs")), (Ellipsis, None), (RParen, None)]);
+    assert_tokens("[...]int",
+                  &[(LBracket, None), (Ellipsis, None), (RBracket, None), (Ident, Some("int"))]);
+}
+
+fn format(src: &str) -> String {
+    format_tokens(&tokenize(src).unwrap())
+}
+
+#[test]
+fn format_compact_source() {
+    assert_eq!(format("func f(){x:=1;return}"), "func f() {\n\tx := 1\n\treturn\n}\n");
+
+    let src = "package main\nimport(\"fmt\";\"os\")\n\
+               type T struct{X,Y int;p *T}\n\
+               func (t *T) M(xs []int, args ...string) (int, error) {\n\
+               for i:=0;i<len(xs);i++{if xs[i]>0&&!t.ok{continue}else if x:=f(); x{} else {\n\
+               g(-xs[i]*2, []*T{{1, 2}}, xs[1:], args...)}}\n\
+               switch v:=<-ch;v{case 1,2:return 0,nil\ndefault:ch<-v}\n\
+               Loop:\n\
+               for{select{case <-done:break Loop}}\n\
+               return len(`raw`)+'c', nil }";
+    assert_eq!(format(src),
+               "package main\n\
+                \n\
+                import (\n\
+                \t\"fmt\"\n\
+                \t\"os\"\n\
+                )\n\
+                \n\
+                type T struct {\n\
+                \tX, Y int\n\
+                \tp *T\n\
+                }\n\
+                \n\
+                func (t *T) M(xs []int, args ...string) (int, error) {\n\
+                \tfor i := 0; i < len(xs); i++ {\n\
+                \t\tif xs[i] > 0 && !t.ok {\n\
+                \t\t\tcontinue\n\
+                \t\t} else if x := f(); x {} else {\n\
+                \t\t\tg(-xs[i]*2, []*T{{1, 2}}, xs[1:], args...)\n\
+                \t\t}\n\
+                \t}\n\
+                \tswitch v := <-ch; v {\n\
+                \tcase 1, 2:\n\
+                \t\treturn 0, nil\n\
+                \tdefault:\n\
+                \t\tch <- v\n\
+                \t}\n\
+                Loop:\n\
+                \tfor {\n\
+                \t\tselect {\n\
+                \t\tcase <-done:\n\
+                \t\t\tbreak Loop\n\
+                \t\t}\n\
+                \t}\n\
+                \treturn len(`raw`) + 'c', nil\n\
+                }\n");
+
+    // Formatting is idempotent.
+    let formatted = format(src);
+    assert_eq!(format(&formatted), formatted);
+    assert_eq!(format(""), "");
+}
+
+#[test]
+fn semantic_tokens_rows_and_columns() {
+    let tokens = semantic_tokens("package main\n\tx := `a\nb`;\n").unwrap();
+    let pos = |row, column| {
+        Position {
+            row: row,
+            column: column,
+        }
+    };
+
+    assert_eq!(tokens,
+               vec![SemanticToken { kind: Package, start: pos(1, 1), end: pos(1, 8) },
+                    SemanticToken { kind: Ident, start: pos(1, 9), end: pos(1, 13) },
+                    SemanticToken { kind: Ident, start: pos(2, 2), end: pos(2, 3) },
+                    SemanticToken { kind: ColonAssign, start: pos(2, 4), end: pos(2, 6) },
+                    SemanticToken { kind: StrRaw, start: pos(2, 7), end: pos(3, 3) },
+                    SemanticToken { kind: Semicolon, start: pos(3, 3), end: pos(3, 4) }]);
+}
+
+#[test]
+fn format_leading_closing_delims() {
+    assert_eq!(format("}"), "}\n");
+    assert_eq!(format(") x"), ") x\n");
+}
@@ -11,9 +11,18 @@ extern crate quick_error;
 
 extern crate num;
 
+use std::any::Any;
+use std::panic;
+
 mod pos;
 pub use self::pos::Position;
 
+mod error;
+pub use self::error::{Error, Result};
+
+#[cfg(test)]
+mod test;
+
 pub mod token;
 pub mod ast;
 pub mod lexer;
@@ -21,7 +30,106 @@ pub mod parser;
 
 pub use parser::Parser;
 
-pub fn parse(src: &str) -> ast::SourceFile {
-    let lexer = lexer::Lexer::new(src).collect();
-    parser::parse_tokens(lexer)
+/// Tokenize and parse a complete source file.
+pub fn parse(src: &str) -> Result<ast::SourceFile> {
+    let tokens = try!(lexer::tokenize(src));
+    Ok(try!(parser::parse_tokens(tokens)))
+}
+
+/// Find and decode the string literals of a source file, without parsing it.
+///
+/// Both interpreted and raw strings are included, in source order, with their spans. As with
+/// `ast::BasicLit::Str`, the values are bytes rather than `String`s, as escapes may produce invalid
+/// UTF-8.
+pub fn extract_strings(src: &str) -> Result<Vec<(token::Span, Vec<u8>)>> {
+    let mut strings = Vec::new();
+
+    for t in try!(lexer::tokenize(src)) {
+        if t.token.kind == token::TokenKind::Str || t.token.kind == token::TokenKind::StrRaw {
+            let span = t.span;
+            strings.push((span, try!(parser::interpret_string_token(t))));
+        }
+    }
+
+    Ok(strings)
+}
+
+/// Like `parse`, but turns a panic in the lexer or parser into an `Error::Panic`.
+///
+/// This is a temporary safety net: some malformed input still panics rather than producing an
+/// error, and this function will go away once it no longer does. The panic message is still
+/// printed by the panic hook. As a panic carries no position, the error's span covers the whole
+/// source.
+pub fn try_parse(src: &str) -> Result<ast::SourceFile> {
+    catch_panics(src, || parse(src))
+}
+
+/// Run `f`, which processes `src`, turning a panic into an `Error::Panic` spanning all of `src`.
+fn catch_panics<T, F>(src: &str, f: F) -> Result<T>
+    where F: FnOnce() -> Result<T> + panic::UnwindSafe
+{
+    match panic::catch_unwind(f) {
+        Ok(result) => result,
+        Err(payload) => {
+            let span = token::Span {
+                start: 0,
+                end: src.len() as u32,
+            };
+            Err(Error::Panic(panic_message(&*payload), span))
+        }
+    }
+}
+
+fn panic_message(payload: &(Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Parse the source files of a single package, given as `(file name, source)` pairs.
+///
+/// Every file is parsed, even if an earlier one fails, and must declare the same package name as
+/// the first file that parses. On failure, all errors are returned along with the name of the file
+/// they occurred in. An empty list of files is also a failure, with no errors.
+pub fn parse_package(files: &[(String, &str)])
+                     -> ::std::result::Result<ast::Package, Vec<(String, Error)>> {
+    let mut name: Option<String> = None;
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+
+    for &(ref file_name, src) in files {
+        let file = match parse(src) {
+            Ok(file) => file,
+            Err(err) => {
+                errors.push((file_name.clone(), err));
+                continue;
+            }
+        };
+
+        match name {
+            Some(ref name) if *name != file.package => {
+                let err = Error::PackageMismatch(name.clone(), file.package.clone());
+                errors.push((file_name.clone(), err));
+                continue;
+            }
+            Some(_) => {}
+            None => name = Some(file.package.clone()),
+        }
+
+        parsed.push(file);
+    }
+
+    match name {
+        Some(name) if errors.is_empty() => {
+            Ok(ast::Package {
+                name: name,
+                files: parsed,
+            })
+        }
+        _ => Err(errors),
+    }
 }
@@ -16,10 +16,11 @@ fn main() {
 
     let s = read_file(&src_file).expect("failed to read file");
     let start = PreciseTime::now();
-    let tokens = rgo::lexer::tokenize(&s);
+    let tokens = rgo::lexer::tokenize(&s).expect("failed to tokenize");
     println!("Lexing: {} µs",
              start.to(PreciseTime::now()).num_microseconds().unwrap());
 
-    let ast: rgo::ast::SourceFile = rgo::parser::parse_tokens(tokens);
+    let ast: rgo::ast::SourceFile = rgo::parser::parse_tokens(tokens)
+        .expect("failed to parse");
     println!("AST:\n{:?}", ast);
 }
@@ -1,3 +1,4 @@
+use std::error;
 use std::fmt;
 use token::{Span, Token, TokenKind};
 
@@ -9,12 +10,46 @@ pub struct Error {
     pub kind: ErrorKind,
 }
 
+impl Error {
+    /// Whether this error was caused by incomplete input, which may become valid if more input
+    /// is appended to it.
+    pub fn is_incomplete(&self) -> bool {
+        match self.kind {
+            ErrorKind::UnexpectedEof { can_continue, .. } => can_continue,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "parse error"
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ErrorKind {
     UnexpectedToken {
         found: Token,
         expected: Vec<TokenKind>,
     },
+    /// The input ended in the middle of a construct.
+    UnexpectedEof {
+        expected: Vec<TokenKind>,
+        /// Whether some delimiter was left open, which means that the input is most likely
+        /// incomplete rather than invalid (e.g. `func f() {` in a REPL).
+        can_continue: bool,
+    },
+    /// The input is nested more deeply than `ParserConfig::max_depth` allows.
+    RecursionLimit {
+        max_depth: usize,
+    },
     Other {
         msg: String,
     },
@@ -40,23 +75,44 @@ impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             ErrorKind::UnexpectedToken { ref found, ref expected } => {
-                try!(write!(f, "expected "));
-
-                if expected.len() > 2 {
-                    try!(write!(f, "one of "));
-
-                    let mut sep = " ";
-                    for tk in expected {
-                        try!(write!(f, "\"{}\"{}", tk, sep));
-                        sep = ", ";
-                    }
-                } else {
-                    try!(write!(f, "\"{}\" ", expected[0]));
+                if expected.is_empty() {
+                    return write!(f, "unexpected \"{}\"", found);
+                }
+
+                try!(write_expected(f, expected));
+                write!(f, ", found \"{}\"", found)
+            }
+            ErrorKind::UnexpectedEof { ref expected, .. } => {
+                try!(write!(f, "unexpected end of input"));
+                if expected.is_empty() {
+                    return Ok(());
                 }
 
-                write!(f, "found \"{}\"", found)
+                try!(write!(f, ", "));
+                write_expected(f, expected)
+            }
+            ErrorKind::RecursionLimit { max_depth } => {
+                write!(f, "nested too deeply (the maximum depth is {})", max_depth)
             }
             ErrorKind::Other { ref msg } => write!(f, "{}", msg),
         }
     }
 }
+
+/// Write a non-empty set of expected tokens, e.g. `expected one of "A", "B" or "C"`.
+fn write_expected(f: &mut fmt::Formatter, expected: &[TokenKind]) -> fmt::Result {
+    let (last, rest) = expected.split_last().unwrap();
+
+    if rest.is_empty() {
+        return write!(f, "expected \"{}\"", last);
+    }
+
+    try!(write!(f, "expected one of "));
+    for (i, tk) in rest.iter().enumerate() {
+        if i > 0 {
+            try!(write!(f, ", "));
+        }
+        try!(write!(f, "\"{}\"", tk));
+    }
+    write!(f, " or \"{}\"", last)
+}
@@ -1,5 +1,7 @@
+use std::fmt;
 use std::mem;
 use std::iter::Peekable;
+use num;
 use num::bigint::BigInt;
 use num::BigRational;
 use token::*;
@@ -11,11 +13,15 @@ mod test;
 mod error;
 pub use self::error::{PResult, Error, ErrorKind};
 
+/// Evaluate `$x` and wrap the result in a `Spanned`, spanning all tokens consumed by `$x`.
+///
+/// If `$x` doesn't consume any tokens (e.g. an empty statement), the span is empty and located at
+/// the start of the current token.
 macro_rules! span {
     ($s:expr, $x:expr) => {{
         let start_off = $s.span.start;
         let val = $x;
-        let end_off = $s.prev_end_offset;
+        let end_off = ::std::cmp::max($s.prev_end_offset, start_off);
 
         Spanned::new(Span {start: start_off, end: end_off}, val)
     }}
@@ -27,6 +33,57 @@ macro_rules! try_span {
     }
 }
 
+/// The largest power of two a hexadecimal float literal may be scaled by, either way. The spec
+/// only requires float constants to have a signed binary exponent of at least 16 bits, and the
+/// limit keeps a literal like `0x1p999999999` from requiring a huge allocation.
+const MAX_BINARY_EXPONENT: i64 = 1 << 16;
+
+/// The tokens which can start an operand, as listed in errors.
+const OPERAND_STARTS: &'static [TokenKind] = &[TokenKind::Ident,
+                                               TokenKind::LParen,
+                                               TokenKind::Decimal,
+                                               TokenKind::Octal,
+                                               TokenKind::Hex,
+                                               TokenKind::Float,
+                                               TokenKind::Imaginary,
+                                               TokenKind::Rune,
+                                               TokenKind::Str,
+                                               TokenKind::StrRaw,
+                                               TokenKind::LBracket,
+                                               TokenKind::Map,
+                                               TokenKind::Struct,
+                                               TokenKind::Func];
+
+/// The tokens besides `OPERAND_STARTS` which can start a primary expression, namely conversions
+/// to types which have no composite literals.
+const CONVERSION_STARTS: &'static [TokenKind] = &[TokenKind::Chan, TokenKind::Interface];
+
+/// unary_op   = "+" | "-" | "!" | "^" | "*" | "&" | "<-" .
+const UNARY_OPS: &'static [TokenKind] = &[TokenKind::Plus,
+                                          TokenKind::Minus,
+                                          TokenKind::Not,
+                                          TokenKind::Caret,
+                                          TokenKind::Star,
+                                          TokenKind::And,
+                                          TokenKind::Arrow];
+
+/// Options controlling the behavior of a `Parser`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// How deeply expressions, types, statements and literal values may be nested before parsing
+    /// fails with `ErrorKind::RecursionLimit`. The parser is recursive, so this keeps
+    /// pathological input from overflowing the stack.
+    ///
+    /// Defaults to 100, which is safe on a 2 MiB stack even in unoptimized builds.
+    pub max_depth: usize,
+}
+
+impl Default for ParserConfig {
+    fn default() -> ParserConfig {
+        ParserConfig { max_depth: 100 }
+    }
+}
+
 pub struct Parser<R: Iterator<Item = TokenAndSpan>> {
     /// Our source of tokens.
     /// Users can choose to read all the tokens up-front, or to read them lazily.
@@ -37,25 +94,55 @@ pub struct Parser<R: Iterator<Item = TokenAndSpan>> {
     span: Span,
     /// Byte offset of the end of the most recently consumed token.
     prev_end_offset: u32,
+    /// The closing delimiters matching the consumed opening delimiters which have not been closed
+    /// yet, innermost last. A closing delimiter which doesn't match the innermost one is ignored.
+    open_delims: Vec<TokenKind>,
+    /// Whether a `{` after a type name ends the expression, rather than starting a composite
+    /// literal. This is the case in the header of an `if`, `for` or `switch` statement, where the
+    /// `{` opens the body instead, unless the literal is nested in parentheses or brackets.
+    no_composite_lit: bool,
+    /// The spans of the `x.(type)` expressions parsed so far in the header of the innermost switch
+    /// statement, or `None` outside of one, where they are rejected straight away.
+    type_switch_guards: Option<Vec<Span>>,
+    /// Whether `next_decl` has reached the end of the file or failed.
+    decls_finished: bool,
+    config: ParserConfig,
+    /// Current nesting depth, as limited by `config.max_depth`.
+    depth: usize,
 }
 
 impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
-    pub fn new(mut it: R) -> Parser<R> {
-        // TODO: handle missing tok gracefully.
-        let first_tok_and_pos = it.next().expect("missing first token");
+    pub fn new(it: R) -> Parser<R> {
+        Parser::with_config(it, ParserConfig::default())
+    }
+
+    pub fn with_config(mut it: R, config: ParserConfig) -> Parser<R> {
+        // An empty token stream is reported as an unexpected EOF by the first parse function.
+        let first_tok_and_pos = it.next().unwrap_or(TokenAndSpan {
+            token: Token {
+                kind: TokenKind::Eof,
+                value: None,
+            },
+            span: Span { start: 0, end: 0 },
+        });
         debug!("first_tok_and_pos: {:?}", first_tok_and_pos);
         Parser {
             token: first_tok_and_pos.token,
             span: first_tok_and_pos.span,
             prev_end_offset: first_tok_and_pos.span.end,
+            open_delims: Vec::new(),
+            no_composite_lit: false,
+            type_switch_guards: None,
+            decls_finished: false,
+            config: config,
+            depth: 0,
             reader: it.peekable(),
         }
     }
 
     /// Parse the tokens into a SourceFile (AST).
     pub fn parse(mut self) -> PResult<ast::SourceFile> {
-        let package_name = try!(self.parse_package_clause());
-        let import_decls = try!(self.parse_import_decls());
+        let (package_name, import_decls) = try!(self.parse_preamble());
         let top_level_decls = try!(self.parse_top_level_decls());
 
         Ok(ast::SourceFile {
@@ -65,37 +152,205 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         })
     }
 
+    /// Parse the tokens into a SourceFile, carrying on after an error in a top-level declaration
+    /// to report as many errors as possible.
+    ///
+    /// After an error, the rest of the declaration is skipped, and the declarations which did
+    /// parse are returned along with every error. An error in the package clause or the imports
+    /// can't be recovered from, and no SourceFile is returned then.
+    pub fn parse_recovering(mut self) -> (Option<ast::SourceFile>, Vec<Error>) {
+        let (package_name, import_decls) = match self.parse_preamble() {
+            Ok(preamble) => preamble,
+            Err(err) => return (None, vec![err]),
+        };
+
+        let mut top_level_decls = Vec::new();
+        let mut errors = Vec::new();
+
+        while self.token.kind != TokenKind::Eof {
+            match self.parse_top_level_decl() {
+                Ok(decl) => top_level_decls.push(decl),
+                Err(err) => {
+                    errors.push(err);
+                    self.resync();
+                }
+            }
+        }
+
+        let file = ast::SourceFile {
+            package: package_name,
+            import_decls: import_decls,
+            top_level_decls: top_level_decls,
+        };
+        (Some(file), errors)
+    }
+
+    /// Parse the package clause and import declarations at the start of a file.
+    ///
+    /// Together with `next_decl`, this allows a file to be processed one declaration at a time,
+    /// without building the whole `SourceFile`.
+    pub fn parse_preamble(&mut self) -> PResult<(ast::Ident, Vec<Spanned<ast::ImportDecl>>)> {
+        let package_name = try!(self.parse_package_clause());
+        let import_decls = try!(self.parse_import_decls());
+        Ok((package_name, import_decls))
+    }
+
+    /// Parse the next top-level declaration, after the preamble has been parsed with
+    /// `parse_preamble`.
+    ///
+    /// Returns `None` at the end of the file. Parsing can't resume after an error, so `None` is
+    /// also returned for every call after the first error.
+    pub fn next_decl(&mut self) -> Option<PResult<Spanned<ast::TopLevelDecl>>> {
+        if self.decls_finished || self.token.kind == TokenKind::Eof {
+            self.decls_finished = true;
+            return None;
+        }
+
+        let res = self.parse_top_level_decl();
+        if res.is_err() {
+            self.decls_finished = true;
+        }
+        Some(res)
+    }
+
+    /// Whether all of the input has been consumed.
+    ///
+    /// Useful after calling a single parse method, which stops at the first token it can't use.
+    pub fn is_at_eof(&self) -> bool {
+        self.token.kind == TokenKind::Eof
+    }
+
     // === Utility functions ===
 
     /// Build a parse error.
+    ///
+    /// Unexpected EOFs are reported as such, so that callers can tell incomplete input apart from
+    /// invalid input.
     fn err(&self, kind: ErrorKind) -> Error {
+        let kind = match kind {
+            ErrorKind::UnexpectedToken { found: Token { kind: TokenKind::Eof, .. }, expected } => {
+                ErrorKind::UnexpectedEof {
+                    expected: expected,
+                    can_continue: !self.open_delims.is_empty(),
+                }
+            }
+            kind => kind,
+        };
+
         Error {
             span: self.span,
             kind: kind,
         }
     }
 
+    /// Keep track of the delimiters which are still open, given the kind of a consumed token.
+    fn track_delim(&mut self, kind: TokenKind) {
+        if let Some(closer) = kind.closing_delim() {
+            self.open_delims.push(closer);
+        } else if self.open_delims.last() == Some(&kind) {
+            self.open_delims.pop();
+        }
+    }
+
+    /// Skip tokens after an error, up to the start of the next top-level declaration.
+    ///
+    /// That is right after the next semicolon outside of any delimiters, or at a `func`, `var`,
+    /// `const`, `type` or `import` keyword outside of any delimiters. The token the error occurred
+    /// at is always skipped, so that parsing makes progress.
+    ///
+    /// A closing delimiter which matches an outer open delimiter, such as the `}` in
+    /// `{ g(; }`, closes the inner ones too, as they are most likely missing their closers.
+    /// Otherwise a single unclosed delimiter would swallow the rest of the file.
+    fn resync(&mut self) {
+        trace!("resync");
+
+        if self.token.kind == TokenKind::Eof {
+            return;
+        }
+        let kind = self.token.kind;
+        self.resync_bump();
+
+        if kind == TokenKind::Semicolon && self.open_delims.is_empty() {
+            return;
+        }
+
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Func | TokenKind::Var | TokenKind::Const | TokenKind::Type |
+                TokenKind::Import if self.open_delims.is_empty() => return,
+                TokenKind::Semicolon if self.open_delims.is_empty() => {
+                    self.bump();
+                    return;
+                }
+                _ => self.resync_bump(),
+            }
+        }
+    }
+
+    /// Advance the parser by one token while resynchronizing, closing any delimiters left open
+    /// inside the one the current token closes.
+    fn resync_bump(&mut self) {
+        let kind = self.token.kind;
+        if let Some(i) = self.open_delims.iter().rposition(|&closer| closer == kind) {
+            self.open_delims.truncate(i + 1);
+        }
+        self.bump();
+    }
+
+    /// Check that the identifiers on the left of a `:=` (the current token) aren't all blank, as
+    /// the blank identifier doesn't declare anything.
+    fn check_declares_something(&self, idents: &[Spanned<String>]) -> PResult<()> {
+        if idents.iter().all(|ident| ident.item == "_") {
+            let msg = "no new variables on left side of :=";
+            return Err(self.err(ErrorKind::other(msg)));
+        }
+        Ok(())
+    }
+
+    /// Run `f` one level deeper, failing instead if that exceeds the maximum depth.
+    fn nested<T, F: FnOnce(&mut Self) -> PResult<T>>(&mut self, f: F) -> PResult<T> {
+        if self.depth >= self.config.max_depth {
+            let kind = ErrorKind::RecursionLimit { max_depth: self.config.max_depth };
+            return Err(self.err(kind));
+        }
+
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
     /// Advance the parser by one token.
     fn bump(&mut self) {
         trace!("bump");
         let next = self.reader.next();
 
+        let kind = self.token.kind;
+        self.track_delim(kind);
         self.prev_end_offset = self.span.end;
 
         if let Some(TokenAndSpan { span, token }) = next {
             self.token = token;
             self.span = span;
         } else {
-            // XXX what span to set?
+            // EOF is located right after the last token.
             self.token = Token {
                 kind: TokenKind::Eof,
                 value: None,
             };
+            self.span = Span {
+                start: self.prev_end_offset,
+                end: self.prev_end_offset,
+            };
         }
     }
 
     /// Advance the parser by one token and return the bumped token.
     fn bump_and_get(&mut self) -> Token {
+        let kind = self.token.kind;
+        self.track_delim(kind);
+
         // The star is used a dummy token and replaced immediately.
         let old_token = mem::replace(&mut self.token,
                                      Token {
@@ -120,6 +375,33 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         Ok(())
     }
 
+    /// Consume the semicolon terminating a clause, declaration or statement.
+    ///
+    /// The lexer only inserts semicolons at newlines, so the last one in a file may be missing.
+    fn eat_semi(&mut self) -> PResult<()> {
+        if self.token.kind == TokenKind::Eof {
+            return Ok(());
+        }
+        self.eat(TokenKind::Semicolon)
+    }
+
+    /// Consume the comma after an item of a list, unless `closer` ends the list next.
+    ///
+    /// The comma may be omitted after the last item.
+    fn eat_list_comma(&mut self, closer: TokenKind) -> PResult<()> {
+        match self.token.kind {
+            TokenKind::Comma => {
+                self.bump();
+                Ok(())
+            }
+            kind if kind == closer => Ok(()),
+            _ => {
+                let expected = vec![TokenKind::Comma, closer];
+                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+            }
+        }
+    }
+
     fn eat_and_get(&mut self, expected: TokenKind) -> PResult<(Token)> {
         if self.token.kind != expected {
             return Err(self.err(ErrorKind::unexpected_token(vec![expected], self.token.clone())));
@@ -136,7 +418,7 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         try!(self.eat(TokenKind::Package));
 
         let package_name = try!(self.parse_ident());
-        try!(self.eat(TokenKind::Semicolon));
+        try!(self.eat_semi());
         Ok(package_name)
     }
 
@@ -174,23 +456,20 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
                 self.bump();
 
                 // There may be multiple `ImportSpec`s in a single "long" import declaration.
-                loop {
-                    match self.token.kind {
-                        // XXX: Should we _know_ that import specs always start with a string
-                        // literal? I'm not sure.
-                        TokenKind::RParen => {
-                            break;
-                        }
-                        _ => {
-                            specs.push(try_span!(self, self.parse_import_spec()));
-                        }
+                while self.token.kind != TokenKind::RParen {
+                    specs.push(try_span!(self, self.parse_import_spec()));
+
+                    // The semicolon may be omitted before the closing parenthesis.
+                    if self.token.kind != TokenKind::RParen {
+                        try!(self.eat(TokenKind::Semicolon));
                     }
                 }
+                try!(self.eat(TokenKind::RParen));
             }
             // Short import (single ImportSpec).
             _ => specs.push(try_span!(self, self.parse_import_spec())),
         }
-        try!(self.eat(TokenKind::Semicolon));
+        try!(self.eat_semi());
 
         Ok(ast::ImportDecl { specs: specs })
     }
@@ -211,7 +490,14 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
                 self.bump();
                 ast::ImportKind::Glob
             }
-            TokenKind::Ident => ast::ImportKind::Alias(self.bump_and_get().value.unwrap()),
+            TokenKind::Ident => {
+                let alias = self.bump_and_get().value.unwrap();
+                if alias == "_" {
+                    ast::ImportKind::Blank
+                } else {
+                    ast::ImportKind::Alias(alias)
+                }
+            }
             _ => ast::ImportKind::Normal,
         };
 
@@ -228,25 +514,44 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
     // Grammar:
     //
     // TopLevelDecl  = Declaration | FunctionDecl | MethodDecl .
-    fn parse_top_level_decls(&mut self) -> PResult<Vec<ast::TopLevelDecl>> {
+    fn parse_top_level_decls(&mut self) -> PResult<Vec<Spanned<ast::TopLevelDecl>>> {
         trace!("parse_top_level_decls");
         let mut decls = Vec::new();
 
-        // FIXME: no loop + unfinished!
+        while let Some(decl) = self.next_decl() {
+            decls.push(try!(decl));
+        }
 
-        match self.token.kind {
+        Ok(decls)
+    }
+
+    /// Parse a single top-level declaration, and the semicolon terminating it.
+    fn parse_top_level_decl(&mut self) -> PResult<Spanned<ast::TopLevelDecl>> {
+        trace!("parse_top_level_decl");
+
+        // FIXME: unfinished!
+
+        let decl = match self.token.kind {
             // FunctionDecl
-            TokenKind::Func => {
-                let fd = try!(self.parse_func_decl());
-                decls.push(ast::TopLevelDecl::Func(fd));
+            TokenKind::Func => span!(self, ast::TopLevelDecl::Func(try!(self.parse_func_decl()))),
+            TokenKind::Var | TokenKind::Const | TokenKind::Type => {
+                span!(self, ast::TopLevelDecl::Statement(try!(self.parse_decl_stmt())))
             }
             _ => {
-                let e = ErrorKind::unexpected_token(vec![TokenKind::Func], self.token.clone());
+                let expected = vec![TokenKind::Func,
+                                    TokenKind::Var,
+                                    TokenKind::Const,
+                                    TokenKind::Type,
+                                    TokenKind::Eof];
+                let e = ErrorKind::unexpected_token(expected, self.token.clone());
                 return Err(self.err(e));
             }
-        }
+        };
 
-        Ok(decls)
+        // TopLevelDecl ";"
+        try!(self.eat_semi());
+
+        Ok(decl)
     }
 
     /// Parse a full function declaration (including signature, name, and block).
@@ -268,7 +573,6 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
             // No body.
             _ => None,
         };
-        try!(self.eat(TokenKind::Semicolon));
 
         Ok(ast::FuncDecl {
             name: name,
@@ -299,11 +603,11 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         let result = match self.token.kind {
             // An opening parenthesis! We can parse an output parameter list.
             TokenKind::LParen => try!(self.parse_func_params()),
-            // Brace = no return type, but a body. We don't care about the body in this function.
-            // Semicolon = no return type and no body.
-            TokenKind::LBrace | TokenKind::Semicolon => ast::Parameters::empty(),
-            // Otherwise, a single, unnamed return type.
-            _ => ast::Parameters::from_single_type(try!(self.parse_type())),
+            // A single, unnamed return type.
+            t if t.can_start_type() => ast::Parameters::from_single_type(try!(self.parse_type())),
+            // Anything else (e.g. a brace starting the body, or the semicolon or brace ending an
+            // interface method spec) means there is no return type.
+            _ => ast::Parameters::empty(),
         };
 
         Ok(ast::FuncSignature {
@@ -326,66 +630,141 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         // ParameterDecl  = [ IdentifierList ] [ "..." ] Type .
         try!(self.eat(TokenKind::LParen));
 
-        let mut decls = Vec::new();
+        let mut items = Vec::new();
 
         // The parameter list is optional.
-        match self.token.kind {
-            TokenKind::Ident | TokenKind::Ellipsis => {
-                decls.push(try!(self.parse_parameter_decl()));
+        if self.token.kind == TokenKind::Ellipsis || self.token.kind.can_start_type() {
+            items.push(try_span!(self, self.parse_parameter_item()));
 
-                while let TokenKind::Comma = self.token.kind {
-                    try!(self.eat(TokenKind::Comma));
-                    decls.push(try!(self.parse_parameter_decl()));
+            while self.token.kind == TokenKind::Comma {
+                self.bump();
+
+                // The parameter list may end with a trailing comma.
+                if self.token.kind == TokenKind::RParen {
+                    break;
                 }
+                items.push(try_span!(self, self.parse_parameter_item()));
             }
-            _ => {}
         }
         try!(self.eat(TokenKind::RParen));
 
+        let decls = try!(self.group_parameter_items(items));
+
         // XXX: do we _need_ Parameters to be a type by itself?
         Ok(ast::Parameters { decls: decls })
     }
 
-    /// Parse a "parameter decl".
-    fn parse_parameter_decl(&mut self) -> PResult<ast::ParameterDecl> {
-        trace!("parse_parameter_decl");
+    /// Parse a single entry of a parameter list, which may or may not be named.
+    ///
+    /// Whether a lone identifier is a name or a type can only be decided once the whole list has
+    /// been parsed, see `group_parameter_items`.
+    fn parse_parameter_item(&mut self) -> PResult<ParameterItem> {
+        trace!("parse_parameter_item");
         // Grammar:
         // ParameterDecl  = [ IdentifierList ] [ "..." ] Type .
 
-        let mut idents = Vec::new();
-        let mut variadic = false;
+        if self.token.kind == TokenKind::Ident {
+            let ident = try!(self.parse_ident());
 
-        // The identifier list is optional.
-        if let TokenKind::Ident = self.token.kind {
-            // Grammar:
-            // IdentifierList = identifier { "," identifier } .
-            idents.push(try!(self.parse_ident()));
-
-            while let TokenKind::Comma = self.token.kind {
-                try!(self.eat(TokenKind::Comma));
-                idents.push(try!(self.parse_ident()));
+            if self.token.kind == TokenKind::Ellipsis || self.token.kind.can_start_type() {
+                let variadic = try!(self.parse_ellipsis());
+                return Ok(ParameterItem::Named(ident, variadic, try!(self.parse_type())));
             }
+
+            let type_name = try!(self.parse_type_name_rest(ident));
+            return Ok(ParameterItem::Ident(type_name));
         }
 
-        // So is the ellipsis that indicates a variadic func.
-        if let TokenKind::Ellipsis = self.token.kind {
+        let variadic = try!(self.parse_ellipsis());
+        Ok(ParameterItem::Type(variadic, try!(self.parse_type())))
+    }
+
+    /// Consume the ellipsis marking a variadic parameter, if there is one.
+    fn parse_ellipsis(&mut self) -> PResult<bool> {
+        if self.token.kind == TokenKind::Ellipsis {
             try!(self.eat(TokenKind::Ellipsis));
-            variadic = true;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Turn the entries of a parameter list into parameter decls.
+    ///
+    /// Either all parameters are named or none are. In a named list, consecutive names share the
+    /// type that follows them: `(a, b int, c string)` declares two `int`s and one `string`.
+    fn group_parameter_items(&self,
+                             items: Vec<Spanned<ParameterItem>>)
+                             -> PResult<Vec<ast::ParameterDecl>> {
+        trace!("group_parameter_items");
+
+        let named = items.iter().any(|item| match item.item {
+            ParameterItem::Named(..) => true,
+            _ => false,
+        });
+
+        if !named {
+            return Ok(items.into_iter()
+                .map(|item| {
+                    let (variadic, typ) = match item.item {
+                        ParameterItem::Ident(type_name) => (false, ast::Type::Plain(type_name)),
+                        ParameterItem::Type(variadic, typ) => (variadic, typ),
+                        ParameterItem::Named(..) => unreachable!(),
+                    };
+
+                    ast::ParameterDecl {
+                        identifiers: vec![],
+                        typ: typ,
+                        variadic: variadic,
+                    }
+                })
+                .collect());
         }
 
-        // The type is mandatory.
-        let typ = try!(self.parse_type());
+        let mixed_err = |span| {
+            Err(Error {
+                span: span,
+                kind: ErrorKind::other("mixed named and unnamed parameters"),
+            })
+        };
 
-        Ok(ast::ParameterDecl {
-            identifiers: idents,
-            typ: typ,
-            variadic: variadic,
-        })
+        let mut decls = Vec::new();
+        let mut idents = Vec::new();
+        let mut last_span = None;
+
+        for item in items {
+            last_span = Some(item.span);
+
+            match item.item {
+                ParameterItem::Ident(ast::MaybeQualifiedIdent { package: None, name }) => {
+                    idents.push(name);
+                }
+                ParameterItem::Named(name, variadic, typ) => {
+                    idents.push(name);
+                    decls.push(ast::ParameterDecl {
+                        identifiers: mem::replace(&mut idents, Vec::new()),
+                        typ: typ,
+                        variadic: variadic,
+                    });
+                }
+                _ => return mixed_err(item.span),
+            }
+        }
+
+        // Names at the end of the list without a type.
+        if !idents.is_empty() {
+            return mixed_err(last_span.unwrap());
+        }
+
+        Ok(decls)
     }
 
     /// Parse a single type (e.g. `[]string`).
     // XXX: type declarations can be very complex; this function needs attention.
     fn parse_type(&mut self) -> PResult<ast::Type> {
+        self.nested(|p| p.parse_type_inner())
+    }
+
+    fn parse_type_inner(&mut self) -> PResult<ast::Type> {
         trace!("parse_type");
         // Grammar:
         //
@@ -407,130 +786,562 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
                 Ok(typ)
             }
             // If a Type starts with an identifier, it can only be a TypeName.
-            TokenKind::Ident => {
-                let part1 = try!(self.parse_ident());
+            TokenKind::Ident => Ok(ast::Type::Plain(try!(self.parse_type_name()))),
+            TokenKind::Struct => {
+                let typ = try!(self.parse_struct_type());
+                Ok(ast::Type::Literal(Box::new(ast::TypeLiteral::Struct(typ))))
+            }
+            TokenKind::Interface => {
+                let typ = try!(self.parse_interface_type());
+                Ok(ast::Type::Literal(Box::new(ast::TypeLiteral::Interface(typ))))
+            }
+            TokenKind::LBracket => {
+                let typ = try!(self.parse_array_or_slice_type());
+                Ok(ast::Type::Literal(Box::new(typ)))
+            }
+            TokenKind::Map => {
+                let typ = try!(self.parse_map_type());
+                Ok(ast::Type::Literal(Box::new(ast::TypeLiteral::Map(typ))))
+            }
+            TokenKind::Func => {
+                let typ = try!(self.parse_func_type());
+                Ok(ast::Type::Literal(Box::new(ast::TypeLiteral::Func(typ))))
+            }
+            TokenKind::Star => {
+                let typ = try!(self.parse_pointer_type());
+                Ok(ast::Type::Literal(Box::new(ast::TypeLiteral::Pointer(typ))))
+            }
+            TokenKind::Chan | TokenKind::Arrow => {
+                let typ = try!(self.parse_chan_type());
+                Ok(ast::Type::Literal(Box::new(ast::TypeLiteral::Chan(typ))))
+            }
+            _ => {
+                let expected = vec![TokenKind::Ident, TokenKind::LParen, TokenKind::LBracket,
+                                    TokenKind::Struct, TokenKind::Star, TokenKind::Func,
+                                    TokenKind::Interface, TokenKind::Map, TokenKind::Chan,
+                                    TokenKind::Arrow];
+                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+            }
+        }
+    }
 
-                let name;
-                let package;
-                match self.token.kind {
-                    // This is a qualified identifier.
-                    // XXX: should we move that to a new function?
-                    // Qualified idents can only appear in:
-                    // - types (that's what we're parsing)
-                    // - operands.
-                    TokenKind::Colon => {
-                        // XXX: I don't like this pattern.
-                        try!(self.eat(TokenKind::Colon));
-                        let part2 = try!(self.parse_ident());
-
-                        package = Some(part1);
-                        name = part2;
-                    }
-                    // Not qualified? Doesn't matter.
-                    _ => {
-                        name = part1;
-                        package = None;
-                    }
-                }
+    /// Parse a type name, which may be qualified with a package name (e.g. `bytes.Buffer`).
+    fn parse_type_name(&mut self) -> PResult<ast::MaybeQualifiedIdent> {
+        trace!("parse_type_name");
+        // Grammar:
+        //
+        // TypeName       = identifier | QualifiedIdent .
+        // QualifiedIdent = PackageName "." identifier .
 
-                Ok(ast::Type::Plain(ast::MaybeQualifiedIdent {
-                    package: package,
-                    name: name,
-                }))
-            }
-            _ => unimplemented!(),
+        let part1 = try!(self.parse_ident());
+        self.parse_type_name_rest(part1)
+    }
+
+    /// Finish parsing a type name whose first identifier has already been consumed.
+    fn parse_type_name_rest(&mut self, part1: String) -> PResult<ast::MaybeQualifiedIdent> {
+        // Qualified idents can only appear in:
+        // - types
+        // - operands.
+        if self.token.kind == TokenKind::Dot {
+            self.bump();
+            let part2 = try!(self.parse_ident());
+
+            Ok(ast::MaybeQualifiedIdent {
+                package: Some(part1),
+                name: part2,
+            })
+        } else {
+            Ok(ast::MaybeQualifiedIdent {
+                package: None,
+                name: part1,
+            })
         }
     }
 
-    fn parse_block(&mut self) -> PResult<ast::Block> {
-        trace!("parse_block");
+    /// Parse an array type (e.g. `[4]int`) or a slice type (e.g. `[]int`).
+    fn parse_array_or_slice_type(&mut self) -> PResult<ast::TypeLiteral> {
+        trace!("parse_array_or_slice_type");
         // Grammar:
-        // Block = "{" StatementList "}" .
-        // StatementList = { Statement ";" } .
-        try!(self.eat(TokenKind::LBrace));
+        //
+        // ArrayType   = "[" ArrayLength "]" ElementType .
+        // ArrayLength = Expression .
+        // SliceType   = "[" "]" ElementType .
 
-        let mut statements = Vec::new();
-        while self.token.kind.can_start_statement() {
-            statements.push(try!(self.parse_statement()));
-            try!(self.eat(TokenKind::Semicolon));
+        try!(self.eat(TokenKind::LBracket));
+
+        if self.token.kind == TokenKind::RBracket {
+            self.bump();
+            return Ok(ast::TypeLiteral::Slice(ast::SliceType {
+                element_type: try!(self.parse_type()),
+            }));
         }
 
-        try!(self.eat(TokenKind::RBrace));
-        Ok(ast::Block(statements))
+        let len = try!(self.parse_expr());
+        try!(self.eat(TokenKind::RBracket));
+
+        Ok(ast::TypeLiteral::Array(ast::ArrayType {
+            len: len,
+            element_type: try!(self.parse_type()),
+        }))
     }
 
-    // XXX: needs thorough review.
-    fn parse_statement(&mut self) -> PResult<ast::Statement> {
-        trace!("parse_statement");
-        // Statement =
-        // 	Declaration | LabeledStmt | SimpleStmt |
-        // 	GoStmt | ReturnStmt | BreakStmt | ContinueStmt | GotoStmt |
-        // 	FallthroughStmt | Block | IfStmt | SwitchStmt | SelectStmt | ForStmt |
-        // 	DeferStmt .
+    /// Parse a map type (e.g. `map[string]int`).
+    fn parse_map_type(&mut self) -> PResult<ast::MapType> {
+        trace!("parse_map_type");
+        // Grammar:
         //
-        // SimpleStmt = EmptyStmt | ExpressionStmt | SendStmt | IncDecStmt | Assignment |
-        //  ShortVarDecl .
+        // MapType     = "map" "[" KeyType "]" ElementType .
+        // KeyType     = Type .
 
-        use token::TokenKind::*;
-        Ok(match self.token.kind {
-            Type | Var | Const => try!(self.parse_decl_stmt()).into(),
-            Go => try!(self.parse_go_stmt()).into(),
-            Defer => try!(self.parse_defer_stmt()).into(),
-            Return => try!(self.parse_return_stmt()).into(),
-            If => try!(self.parse_if_stmt()).into(),
-            Switch => try!(self.parse_switch_stmt()).into(),
-            Select => try!(self.parse_select_stmt()).into(),
-            For => try!(self.parse_for_stmt()).into(),
-            Break => try!(self.parse_break_stmt()).into(),
-            Continue => try!(self.parse_continue_stmt()).into(),
-            Goto => try!(self.parse_goto_stmt()).into(),
-            Fallthrough => try!(self.parse_fallthrough_stmt()).into(),
-            LBrace => try!(self.parse_block()).into(),
-            RBrace => {
-                // a semicolon may be omitted before a closing "}"
-                ast::EmptyStmt.into()
-            }
-            // All simple statements start with something expression-like.
-            t if t.can_start_expr() => try!(self.parse_simple_stmt()).into(),
-            _ => panic!("unexpected token"),
+        try!(self.eat(TokenKind::Map));
+        try!(self.eat(TokenKind::LBracket));
+        let key_type = try!(self.parse_type());
+        try!(self.eat(TokenKind::RBracket));
+
+        Ok(ast::MapType {
+            key_type: key_type,
+            element_type: try!(self.parse_type()),
         })
     }
 
-    fn parse_go_stmt(&mut self) -> PResult<ast::GoStmt> {
-        trace!("parse_go_stmt");
+    /// Parse a pointer type (e.g. `*Point`).
+    fn parse_pointer_type(&mut self) -> PResult<ast::PointerType> {
+        trace!("parse_pointer_type");
+        // Grammar:
+        //
+        // PointerType = "*" BaseType .
+        // BaseType    = Type .
 
-        try!(self.eat(TokenKind::Go));
-        Ok(ast::GoStmt { call: try_span!(self, self.parse_expr()) })
+        try!(self.eat(TokenKind::Star));
+        Ok(ast::PointerType(try!(self.parse_type())))
     }
 
-    fn parse_break_stmt(&mut self) -> PResult<ast::BreakStmt> {
-        trace!("parse_break_stmt");
+    /// Parse a channel type (e.g. `chan int`, `<-chan int`).
+    fn parse_chan_type(&mut self) -> PResult<ast::ChanType> {
+        trace!("parse_chan_type");
+        // Grammar:
+        //
+        // ChannelType = ( "chan" | "chan" "<-" | "<-" "chan" ) ElementType .
+        // ElementType = Type .
 
-        try!(self.eat(TokenKind::Break));
-        let label = if self.token.kind == TokenKind::Semicolon {
-            None
+        // The arrow belongs to the leftmost `chan` possible: `chan<- chan int` is a send-only
+        // channel of channels.
+        let direction = if self.token.kind == TokenKind::Arrow {
+            self.bump();
+            try!(self.eat(TokenKind::Chan));
+            ast::ChanDirection::Receive
         } else {
-            Some(try_span!(self, self.parse_ident()))
+            try!(self.eat(TokenKind::Chan));
+            if self.token.kind == TokenKind::Arrow {
+                self.bump();
+                ast::ChanDirection::Send
+            } else {
+                ast::ChanDirection::Bidirectional
+            }
         };
 
-        Ok(ast::BreakStmt { label: label })
+        Ok(ast::ChanType {
+            element_type: try!(self.parse_type()),
+            direction: direction,
+        })
     }
 
-    fn parse_continue_stmt(&mut self) -> PResult<ast::ContinueStmt> {
-        trace!("parse_continue_stmt");
-
-        try!(self.eat(TokenKind::Continue));
-        let label = if self.token.kind == TokenKind::Semicolon {
-            None
-        } else {
-            Some(try_span!(self, self.parse_ident()))
-        };
+    /// Parse a function type (e.g. `func(int) error`).
+    fn parse_func_type(&mut self) -> PResult<ast::FuncType> {
+        trace!("parse_func_type");
+        // Grammar:
+        //
+        // FunctionType   = "func" Signature .
 
-        Ok(ast::ContinueStmt { label: label })
+        try!(self.eat(TokenKind::Func));
+        Ok(ast::FuncType { signature: try!(self.parse_func_signature()) })
     }
 
-    fn parse_goto_stmt(&mut self) -> PResult<ast::GotoStmt> {
-        trace!("parse_goto_stmt");
+    /// Parse a struct type (e.g. `struct { A, B int }`).
+    fn parse_struct_type(&mut self) -> PResult<ast::StructType> {
+        trace!("parse_struct_type");
+        // Grammar:
+        //
+        // StructType     = "struct" "{" { FieldDecl ";" } "}" .
+
+        try!(self.eat(TokenKind::Struct));
+        try!(self.eat(TokenKind::LBrace));
+
+        let mut field_decls = Vec::new();
+
+        while self.token.kind != TokenKind::RBrace {
+            field_decls.push(try!(self.parse_field_decl()));
+
+            // The semicolon may be omitted before the closing brace.
+            if self.token.kind != TokenKind::RBrace {
+                try!(self.eat(TokenKind::Semicolon));
+            }
+        }
+
+        try!(self.eat(TokenKind::RBrace));
+
+        Ok(ast::StructType { field_decls: field_decls })
+    }
+
+    /// Parse a single field declaration inside of a struct type.
+    fn parse_field_decl(&mut self) -> PResult<ast::FieldDecl> {
+        trace!("parse_field_decl");
+        // Grammar:
+        //
+        // FieldDecl      = (IdentifierList Type | AnonymousField) [ Tag ] .
+        // Tag            = string_lit .
+
+        let inner = match self.token.kind {
+            // Embedded pointer field (e.g. `*bytes.Buffer`).
+            TokenKind::Star => {
+                self.bump();
+
+                ast::InnerFieldDecl::Anonymous {
+                    is_ptr: true,
+                    type_name: try!(self.parse_type_name()),
+                }
+            }
+            _ => {
+                let first = try!(self.parse_ident());
+
+                match self.token.kind {
+                    // An identifier that is followed by a dot, a tag, or the end of the field
+                    // can only be the type name of an embedded field.
+                    TokenKind::Dot | TokenKind::Semicolon | TokenKind::RBrace |
+                    TokenKind::Str | TokenKind::StrRaw => {
+                        ast::InnerFieldDecl::Anonymous {
+                            is_ptr: false,
+                            type_name: try!(self.parse_type_name_rest(first)),
+                        }
+                    }
+                    _ => {
+                        let mut idents = vec![first];
+
+                        while self.token.kind == TokenKind::Comma {
+                            self.bump();
+                            idents.push(try!(self.parse_ident()));
+                        }
+
+                        ast::InnerFieldDecl::Named {
+                            idents: idents,
+                            typ: try!(self.parse_type()),
+                        }
+                    }
+                }
+            }
+        };
+
+        let tag = match self.token.kind {
+            TokenKind::Str | TokenKind::StrRaw => Some(try!(self.parse_string_lit())),
+            _ => None,
+        };
+
+        Ok(ast::FieldDecl {
+            inner: inner,
+            tag: tag,
+        })
+    }
+
+    /// Parse an interface type (e.g. `interface { Read(p []byte) (n int, err error) }`).
+    fn parse_interface_type(&mut self) -> PResult<ast::InterfaceType> {
+        trace!("parse_interface_type");
+        // Grammar:
+        //
+        // InterfaceType      = "interface" "{" { InterfaceElem ";" } "}" .
+        // InterfaceElem      = MethodSpec | TypeConstraint .
+        // MethodSpec         = MethodName Signature | InterfaceTypeName .
+        // MethodName         = identifier .
+        // InterfaceTypeName  = TypeName .
+
+        try!(self.eat(TokenKind::Interface));
+        try!(self.eat(TokenKind::LBrace));
+
+        let mut specs = Vec::new();
+        let mut constraints = Vec::new();
+
+        while self.token.kind != TokenKind::RBrace {
+            if self.token.kind == TokenKind::Ident {
+                let name = try!(self.parse_ident());
+
+                if self.token.kind == TokenKind::LParen {
+                    // A method.
+                    specs.push(ast::MethodSpec {
+                        name: name,
+                        method: ast::InnerMethodSpec::Signature(try!(self.parse_func_signature())),
+                    });
+                } else {
+                    let type_name = try!(self.parse_type_name_rest(name));
+
+                    if self.token.kind == TokenKind::Or {
+                        // The first term of a union.
+                        let first = ast::TypeTerm {
+                            underlying: false,
+                            typ: ast::Type::Plain(type_name),
+                        };
+                        constraints.push(try!(self.parse_type_constraint(first)));
+                    } else {
+                        // An embedded interface.
+                        specs.push(ast::MethodSpec {
+                            name: type_name.name.clone(),
+                            method: ast::InnerMethodSpec::InterfaceName(type_name),
+                        });
+                    }
+                }
+            } else {
+                let first = try!(self.parse_type_term());
+                constraints.push(try!(self.parse_type_constraint(first)));
+            }
+
+            // The semicolon may be omitted before the closing brace.
+            if self.token.kind != TokenKind::RBrace {
+                try!(self.eat(TokenKind::Semicolon));
+            }
+        }
+
+        try!(self.eat(TokenKind::RBrace));
+
+        Ok(ast::InterfaceType {
+            specs: specs,
+            constraints: constraints,
+        })
+    }
+
+    /// Parse the rest of a type constraint (e.g. `~int | ~string`), given its first term.
+    fn parse_type_constraint(&mut self, first: ast::TypeTerm) -> PResult<ast::TypeConstraint> {
+        trace!("parse_type_constraint");
+        // Grammar:
+        //
+        // TypeConstraint     = TypeTerm { "|" TypeTerm } .
+
+        let mut terms = vec![first];
+
+        while self.token.kind == TokenKind::Or {
+            self.bump();
+            terms.push(try!(self.parse_type_term()));
+        }
+
+        Ok(ast::TypeConstraint { terms: terms })
+    }
+
+    /// Parse a single term of a type constraint (e.g. `~int`).
+    fn parse_type_term(&mut self) -> PResult<ast::TypeTerm> {
+        trace!("parse_type_term");
+        // Grammar:
+        //
+        // TypeTerm           = Type | UnderlyingType .
+        // UnderlyingType     = "~" Type .
+
+        let underlying = self.token.kind == TokenKind::Tilde;
+        if underlying {
+            self.bump();
+        }
+
+        Ok(ast::TypeTerm {
+            underlying: underlying,
+            typ: try!(self.parse_type()),
+        })
+    }
+
+    /// Run `f` with composite literals of named types allowed or not, restoring the previous
+    /// setting afterwards.
+    fn with_composite_lits<T, F>(&mut self, allowed: bool, f: F) -> PResult<T>
+        where F: FnOnce(&mut Self) -> PResult<T>
+    {
+        let old = mem::replace(&mut self.no_composite_lit, !allowed);
+        let res = f(self);
+        self.no_composite_lit = old;
+        res
+    }
+
+    fn parse_block(&mut self) -> PResult<ast::Block> {
+        trace!("parse_block");
+        // Grammar:
+        // Block = "{" StatementList "}" .
+        // StatementList = { Statement ";" } .
+        try!(self.eat(TokenKind::LBrace));
+        let statements = try!(self.with_composite_lits(true, |p| p.parse_statement_list(false)));
+        try!(self.eat(TokenKind::RBrace));
+        Ok(ast::Block(statements))
+    }
+
+    /// Parse statements up to the end of a block or case clause.
+    ///
+    /// `fallthrough` is only accepted as the final statement of an expression switch's case
+    /// clause, so it must be allowed explicitly.
+    fn parse_statement_list(&mut self,
+                            allow_fallthrough: bool)
+                            -> PResult<Vec<Spanned<ast::Statement>>> {
+        trace!("parse_statement_list");
+        // StatementList = { Statement ";" } .
+
+        let mut statements = Vec::new();
+
+        loop {
+            match self.token.kind {
+                // A lone semicolon terminates an empty statement, which isn't recorded.
+                TokenKind::Semicolon => {
+                    self.bump();
+                    continue;
+                }
+                kind if kind.can_start_statement() => {}
+                _ => break,
+            }
+
+            if allow_fallthrough && self.token.kind == TokenKind::Fallthrough {
+                let stmt = try_span!(self, self.parse_fallthrough_stmt());
+                statements.push(Spanned::new(stmt.span, stmt.item.into()));
+
+                while self.token.kind == TokenKind::Semicolon {
+                    self.bump();
+                }
+                if self.token.kind.can_start_statement() {
+                    return Err(self.err(ErrorKind::other("fallthrough statement out of place")));
+                }
+                break;
+            }
+
+            statements.push(try_span!(self, self.parse_statement()));
+
+            // Statements ending in a block are no exception: the semicolon after them is only
+            // implicit at the end of a line. It may be omitted before the closing brace, though.
+            if self.token.kind != TokenKind::RBrace {
+                try!(self.eat(TokenKind::Semicolon));
+            }
+        }
+
+        Ok(statements)
+    }
+
+    // XXX: needs thorough review.
+    fn parse_statement(&mut self) -> PResult<ast::Statement> {
+        self.nested(|p| p.parse_statement_inner())
+    }
+
+    fn parse_statement_inner(&mut self) -> PResult<ast::Statement> {
+        trace!("parse_statement");
+        // Statement =
+        // 	Declaration | LabeledStmt | SimpleStmt |
+        // 	GoStmt | ReturnStmt | BreakStmt | ContinueStmt | GotoStmt |
+        // 	FallthroughStmt | Block | IfStmt | SwitchStmt | SelectStmt | ForStmt |
+        // 	DeferStmt .
+        //
+        // SimpleStmt = EmptyStmt | ExpressionStmt | SendStmt | IncDecStmt | Assignment |
+        //  ShortVarDecl .
+
+        use token::TokenKind::*;
+        let kind = self.token.kind;
+        Ok(match kind {
+            Type | Var | Const => try!(self.parse_decl_stmt()).into(),
+            Go => try!(self.parse_go_stmt()).into(),
+            Defer => try!(self.parse_defer_stmt()).into(),
+            Return => try!(self.parse_return_stmt()).into(),
+            If => try!(self.parse_if_stmt()).into(),
+            Switch => try!(self.parse_switch_stmt()).into(),
+            Select => try!(self.parse_select_stmt()).into(),
+            For => try!(self.parse_for_stmt()).into(),
+            Break => try!(self.parse_break_stmt()).into(),
+            Continue => try!(self.parse_continue_stmt()).into(),
+            Goto => try!(self.parse_goto_stmt()).into(),
+            // Case clauses parse `fallthrough` themselves, as it may only end one.
+            Fallthrough => {
+                return Err(self.err(ErrorKind::other("fallthrough statement out of place")))
+            }
+            LBrace => try!(self.parse_block()).into(),
+            Ident if self.next_kind() == Colon => try!(self.parse_labeled_stmt()).into(),
+            RBrace => {
+                // a semicolon may be omitted before a closing "}"
+                ast::EmptyStmt.into()
+            }
+            // All simple statements start with something expression-like.
+            t if t.can_start_expr() => try!(self.parse_simple_stmt()).into(),
+            _ => {
+                return Err(self.err(ErrorKind::unexpected_token(Vec::new(), self.token.clone())))
+            }
+        })
+    }
+
+    fn parse_labeled_stmt(&mut self) -> PResult<ast::LabeledStmt> {
+        trace!("parse_labeled_stmt");
+        // LabeledStmt = Label ":" Statement .
+        // Label       = identifier .
+
+        let label = try_span!(self, self.parse_ident());
+        try!(self.eat(TokenKind::Colon));
+
+        // A label at the end of a block, or directly followed by a semicolon, labels an empty
+        // statement.
+        let statement = if self.token.kind == TokenKind::RBrace ||
+                           self.token.kind == TokenKind::Semicolon {
+            span!(self, ast::EmptyStmt.into())
+        } else {
+            try_span!(self, self.parse_statement())
+        };
+
+        Ok(ast::LabeledStmt {
+            label: label,
+            statement: Box::new(statement),
+        })
+    }
+
+    fn parse_go_stmt(&mut self) -> PResult<ast::GoStmt> {
+        trace!("parse_go_stmt");
+
+        try!(self.eat(TokenKind::Go));
+        Ok(ast::GoStmt { call: try!(self.parse_call_of("go")) })
+    }
+
+    /// Parse the call of a go or defer statement.
+    fn parse_call_of(&mut self, keyword: &str) -> PResult<Spanned<ast::Expr>> {
+        let expr = try_span!(self, self.parse_expr());
+
+        let is_call = match expr.item {
+            ast::Expr::Unary(ast::UnaryExpr::Primary(ref primary)) => {
+                match **primary {
+                    ast::PrimaryExpr::FuncCall(_) => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+
+        if !is_call {
+            return Err(Error {
+                span: expr.span,
+                kind: ErrorKind::other(format!("expression in {} must be function call", keyword)),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_break_stmt(&mut self) -> PResult<ast::BreakStmt> {
+        trace!("parse_break_stmt");
+
+        try!(self.eat(TokenKind::Break));
+        let label = if self.token.kind == TokenKind::Ident {
+            Some(try_span!(self, self.parse_ident()))
+        } else {
+            None
+        };
+
+        Ok(ast::BreakStmt { label: label })
+    }
+
+    fn parse_continue_stmt(&mut self) -> PResult<ast::ContinueStmt> {
+        trace!("parse_continue_stmt");
+
+        try!(self.eat(TokenKind::Continue));
+        let label = if self.token.kind == TokenKind::Ident {
+            Some(try_span!(self, self.parse_ident()))
+        } else {
+            None
+        };
+
+        Ok(ast::ContinueStmt { label: label })
+    }
+
+    fn parse_goto_stmt(&mut self) -> PResult<ast::GotoStmt> {
+        trace!("parse_goto_stmt");
 
         try!(self.eat(TokenKind::Goto));
         Ok(ast::GotoStmt { label: try_span!(self, self.parse_ident()) })
@@ -547,33 +1358,259 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         trace!("parse_defer_stmt");
 
         try!(self.eat(TokenKind::Defer));
-        Ok(ast::DeferStmt { call: try_span!(self, self.parse_expr()) })
+        Ok(ast::DeferStmt { call: try!(self.parse_call_of("defer")) })
     }
 
     fn parse_return_stmt(&mut self) -> PResult<ast::ReturnStmt> {
         trace!("parse_return_stmt");
+        // ReturnStmt = "return" [ ExpressionList ] .
+
         try!(self.eat(TokenKind::Return));
-        Ok(ast::ReturnStmt { expr: try_span!(self, self.parse_expr()) })
+
+        let exprs = if self.token.kind.can_start_expr() {
+            try!(self.parse_expr_list())
+        } else {
+            Vec::new()
+        };
+
+        Ok(ast::ReturnStmt { exprs: exprs })
     }
 
     fn parse_if_stmt(&mut self) -> PResult<ast::IfStmt> {
         trace!("parse_if_stmt");
-        unimplemented!()
+        // IfStmt = "if" [ SimpleStmt ";" ] Expression Block [ "else" ( IfStmt | Block ) ] .
+
+        try!(self.eat(TokenKind::If));
+
+        let (before_stmt, header) =
+            try!(self.with_composite_lits(false, |p| p.parse_stmt_header()));
+
+        let condition = match header {
+            Some(Spanned { item: ast::SimpleStmt::Expr(expr), .. }) => expr,
+            Some(Spanned { span, .. }) => {
+                return Err(Error {
+                    span: span,
+                    kind: ErrorKind::other("expected condition"),
+                })
+            }
+            None => return Err(self.err(ErrorKind::other("expected condition"))),
+        };
+
+        let block = try!(self.parse_block());
+
+        let opt_else = if self.token.kind == TokenKind::Else {
+            self.bump();
+
+            match self.token.kind {
+                TokenKind::If => Some(Box::new(ast::Else::If(try!(self.parse_if_stmt())))),
+                TokenKind::LBrace => Some(Box::new(ast::Else::Block(try!(self.parse_block())))),
+                _ => {
+                    let expected = vec![TokenKind::If, TokenKind::LBrace];
+                    return Err(self.err(ErrorKind::unexpected_token(expected,
+                                                                    self.token.clone())));
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(ast::IfStmt {
+            before_stmt: before_stmt,
+            condition: condition,
+            block: block,
+            opt_else: opt_else,
+        })
     }
 
     fn parse_switch_stmt(&mut self) -> PResult<ast::SwitchStmt> {
         trace!("parse_switch_stmt");
-        unimplemented!()
-    }
+        // SwitchStmt      = ExprSwitchStmt | TypeSwitchStmt .
+        // ExprSwitchStmt  = "switch" [ SimpleStmt ";" ] [ Expression ]
+        //                   "{" { ExprCaseClause } "}" .
+        // TypeSwitchStmt  = "switch" [ SimpleStmt ";" ] TypeSwitchGuard
+        //                   "{" { TypeCaseClause } "}" .
+        // TypeSwitchGuard = [ identifier ":=" ] PrimaryExpr "." "(" "type" ")" .
+
+        try!(self.eat(TokenKind::Switch));
+
+        let outer_guards = mem::replace(&mut self.type_switch_guards, Some(Vec::new()));
+        let res = self.with_composite_lits(false, |p| p.parse_stmt_header());
+        let mut guards = mem::replace(&mut self.type_switch_guards, outer_guards)
+            .unwrap_or_default();
+        let (init, header) = try!(res);
+
+        // Either the binding and guard of a type switch, or the tag of an expression switch.
+        let guard = match header {
+            None => Err(None),
+            Some(header) => {
+                match header.item {
+                    ast::SimpleStmt::Expr(expr) => {
+                        type_switch_guard(expr).map(|guard| (None, guard)).map_err(Some)
+                    }
+                    ast::SimpleStmt::ShortVarDecl(ast::ShortVarDecl { mut lhs, mut rhs }) => {
+                        let guard = if lhs.len() == 1 && rhs.len() == 1 {
+                            type_switch_guard(rhs.pop().unwrap()).ok()
+                        } else {
+                            None
+                        };
+
+                        match guard {
+                            Some(guard) => Ok((lhs.pop(), guard)),
+                            None => {
+                                return Err(Error {
+                                    span: header.span,
+                                    kind: ErrorKind::other("expected type switch guard"),
+                                })
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err(Error {
+                            span: header.span,
+                            kind: ErrorKind::other("expected switch expression"),
+                        })
+                    }
+                }
+            }
+        };
 
-    fn parse_select_stmt(&mut self) -> PResult<ast::SelectStmt> {
-        trace!("parse_select_stmt");
-        unimplemented!()
+        // The guard ends the header, so it is the last `x.(type)` parsed. Any other one is nested
+        // in an expression or in the init statement.
+        if guard.is_ok() {
+            guards.pop();
+        }
+        if let Some(&span) = guards.first() {
+            return Err(misplaced_type_switch_guard(span));
+        }
+
+        match guard {
+            Ok((binding, expr)) => {
+                Ok(ast::SwitchStmt::Type(ast::TypeSwitchStmt {
+                    init: init,
+                    binding: binding,
+                    expr: expr,
+                    clauses: try!(self.parse_type_case_clauses()),
+                }))
+            }
+            Err(expr) => {
+                Ok(ast::SwitchStmt::Expr(ast::ExprSwitchStmt {
+                    init: init,
+                    expr: expr,
+                    clauses: try!(self.parse_expr_case_clauses()),
+                }))
+            }
+        }
     }
 
-    fn parse_for_stmt(&mut self) -> PResult<ast::ForStmt> {
-        // ForStmt = "for" [ Condition | ForClause | RangeClause ] Block .
-        // Condition = Expression .
+    /// Parse the header of an `if` or `switch` statement, up to the opening brace of its body.
+    ///
+    /// Returns the optional init statement, and the simple statement that follows it, which should
+    /// be the condition or switch tag. The latter is only `None` if the header is empty.
+    fn parse_stmt_header(&mut self)
+                         -> PResult<(Option<ast::SimpleStmt>, Option<Spanned<ast::SimpleStmt>>)> {
+        trace!("parse_stmt_header");
+        // [ SimpleStmt ";" ] [ SimpleStmt ]
+
+        let mut init = None;
+        let mut header = None;
+
+        if self.token.kind != TokenKind::LBrace && self.token.kind != TokenKind::Semicolon {
+            header = Some(span!(self, try!(self.parse_simple_stmt())));
+        }
+
+        // What we parsed was the init statement.
+        if self.token.kind == TokenKind::Semicolon {
+            self.bump();
+            init = header.take().map(|stmt| stmt.item);
+
+            if self.token.kind != TokenKind::LBrace {
+                header = Some(span!(self, try!(self.parse_simple_stmt())));
+            }
+        }
+
+        Ok((init, header))
+    }
+
+    fn parse_expr_case_clauses(&mut self) -> PResult<Vec<ast::ExprCaseClause>> {
+        trace!("parse_expr_case_clauses");
+        // ExprCaseClause = ExprSwitchCase ":" StatementList .
+        // ExprSwitchCase = "case" ExpressionList | "default" .
+
+        try!(self.eat(TokenKind::LBrace));
+        let mut clauses = Vec::new();
+
+        while self.token.kind != TokenKind::RBrace {
+            let exprs = if self.token.kind == TokenKind::Default {
+                self.bump();
+                None
+            } else {
+                try!(self.eat(TokenKind::Case));
+                Some(try!(self.parse_expr_list()))
+            };
+            try!(self.eat(TokenKind::Colon));
+
+            clauses.push(ast::ExprCaseClause {
+                exprs: exprs,
+                body: try!(self.parse_statement_list(true)),
+            });
+        }
+
+        if let Some(stmt) = clauses.last().and_then(|clause| clause.body.last()) {
+            if let ast::Statement::Fallthrough(_) = stmt.item {
+                return Err(Error {
+                    span: stmt.span,
+                    kind: ErrorKind::other("cannot fallthrough final case in switch"),
+                });
+            }
+        }
+
+        try!(self.eat(TokenKind::RBrace));
+        Ok(clauses)
+    }
+
+    fn parse_type_case_clauses(&mut self) -> PResult<Vec<ast::TypeCaseClause>> {
+        trace!("parse_type_case_clauses");
+        // TypeCaseClause  = TypeSwitchCase ":" StatementList .
+        // TypeSwitchCase  = "case" TypeList | "default" .
+        // TypeList        = Type { "," Type } .
+
+        try!(self.eat(TokenKind::LBrace));
+        let mut clauses = Vec::new();
+
+        while self.token.kind != TokenKind::RBrace {
+            let types = if self.token.kind == TokenKind::Default {
+                self.bump();
+                None
+            } else {
+                try!(self.eat(TokenKind::Case));
+
+                let mut types = vec![try_span!(self, self.parse_type())];
+                while self.token.kind == TokenKind::Comma {
+                    self.bump();
+                    types.push(try_span!(self, self.parse_type()));
+                }
+                Some(types)
+            };
+            try!(self.eat(TokenKind::Colon));
+
+            clauses.push(ast::TypeCaseClause {
+                types: types,
+                body: try!(self.parse_statement_list(false)),
+            });
+        }
+
+        try!(self.eat(TokenKind::RBrace));
+        Ok(clauses)
+    }
+
+    fn parse_select_stmt(&mut self) -> PResult<ast::SelectStmt> {
+        trace!("parse_select_stmt");
+        unimplemented!()
+    }
+
+    fn parse_for_stmt(&mut self) -> PResult<ast::ForStmt> {
+        // ForStmt = "for" [ Condition | ForClause | RangeClause ] Block .
+        // Condition = Expression .
         //
         // ForClause = [ InitStmt ] ";" [ Condition ] ";" [ PostStmt ] .
         // InitStmt = SimpleStmt .
@@ -585,221 +1622,949 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         try!(self.eat(TokenKind::For));
 
         Ok(ast::ForStmt {
-            header: try!(self.parse_for_header()),
+            header: try!(self.with_composite_lits(false, |p| p.parse_for_header())),
             body: try!(self.parse_block()),
         })
 
     }
 
-    fn parse_for_header(&mut self) -> PResult<ast::ForHeader> {
-        trace!("parse_for_header");
-        unimplemented!()
+    fn parse_for_header(&mut self) -> PResult<ast::ForHeader> {
+        trace!("parse_for_header");
+
+        // An empty header is equivalent to an empty for clause (`for ;; {}`).
+        if self.token.kind == TokenKind::LBrace {
+            return Ok(ast::ForHeader::ForClause(ast::ForClause {
+                init: None,
+                condition: None,
+                post: None,
+            }));
+        }
+
+        // A range clause without iteration variables (`for range ch {}`).
+        if self.token.kind == TokenKind::Range {
+            self.bump();
+            return Ok(ast::ForHeader::RangeClause(ast::RangeClause {
+                iter_vars: ast::IterVars::Exprs(Vec::new()),
+                expr: try_span!(self, self.parse_expr()),
+            }));
+        }
+
+        let mut init = None;
+
+        if self.token.kind != TokenKind::Semicolon {
+            let exprs = try!(self.parse_expr_list());
+
+            // The iteration variables of a range clause look like the start of an assignment or
+            // short variable declaration, so check for `range` before parsing the rest.
+            if self.next_kind() == TokenKind::Range {
+                let iter_vars = match self.token.kind {
+                    TokenKind::Assign => ast::IterVars::Exprs(exprs),
+                    TokenKind::ColonAssign => {
+                        let idents = try!(self.expr_list_to_ident_list(&exprs));
+                        try!(self.check_declares_something(&idents));
+                        ast::IterVars::Idents(idents)
+                    }
+                    _ => {
+                        let expected = vec![TokenKind::Assign, TokenKind::ColonAssign];
+                        return Err(self.err(ErrorKind::unexpected_token(expected,
+                                                                        self.token.clone())));
+                    }
+                };
+                self.bump();
+                try!(self.eat(TokenKind::Range));
+
+                return Ok(ast::ForHeader::RangeClause(ast::RangeClause {
+                    iter_vars: iter_vars,
+                    expr: try_span!(self, self.parse_expr()),
+                }));
+            }
+
+            let stmt = try!(self.parse_simple_stmt_rest(exprs));
+
+            if self.token.kind == TokenKind::LBrace {
+                return match stmt {
+                    ast::SimpleStmt::Expr(expr) => Ok(ast::ForHeader::Condition(expr.item)),
+                    _ => {
+                        let expected = vec![TokenKind::Semicolon];
+                        Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+                    }
+                };
+            }
+
+            init = Some(stmt);
+        }
+
+        try!(self.eat(TokenKind::Semicolon));
+
+        let condition = if self.token.kind != TokenKind::Semicolon {
+            Some(try!(self.parse_expr()))
+        } else {
+            None
+        };
+
+        try!(self.eat(TokenKind::Semicolon));
+
+        let post = if self.token.kind != TokenKind::LBrace {
+            let post = try_span!(self, self.parse_simple_stmt());
+            if let ast::SimpleStmt::ShortVarDecl(_) = post.item {
+                return Err(Error {
+                    span: post.span,
+                    kind: ErrorKind::other("cannot declare in post statement of for loop"),
+                });
+            }
+            Some(post.item)
+        } else {
+            None
+        };
+
+        Ok(ast::ForHeader::ForClause(ast::ForClause {
+            init: init,
+            condition: condition,
+            post: post,
+        }))
+    }
+
+    fn expr_list_to_ident_list(&self,
+                               exprs: &Vec<Spanned<ast::Expr>>)
+                               -> PResult<Vec<Spanned<String>>> {
+        trace!("expr_list_to_ident_list");
+        // XXX: better errors
+
+        let mut idents = Vec::new();
+
+        use ast::{Expr, UnaryExpr, PrimaryExpr, Operand};
+
+        for expr in exprs {
+            if let Expr::Unary(UnaryExpr::Primary(x)) = expr.item.clone() {
+                if let PrimaryExpr::Operand(Operand::Ident(mqident)) = *x {
+                    if mqident.package.is_some() {
+                        return Err(Error {
+                            kind: ErrorKind::other("expected unqualified ident"),
+                            span: expr.span,
+                        });
+                    }
+
+                    idents.push(Spanned::new(expr.span, mqident.name));
+                    continue;
+                }
+            }
+
+            // didn't successfully turn the expr into an ident
+            return Err(Error {
+                kind: ErrorKind::other("expected ident"),
+                span: expr.span,
+            });
+        }
+
+        Ok(idents)
+    }
+
+    fn parse_simple_stmt(&mut self) -> PResult<ast::SimpleStmt> {
+        // SimpleStmt = EmptyStmt | ExpressionStmt | SendStmt | IncDecStmt | Assignment |
+        //  ShortVarDecl .
+        //
+        // EmptyStmt = .
+        // ExpressionStmt = Expression .
+        // SendStmt = Channel "<-" Expression .
+        // Channel  = Expression .
+        // IncDecStmt = Expression ( "++" | "--" ) .
+        // Assignment = ExpressionList assign_op ExpressionList .
+        // assign_op = [ add_op | mul_op ] "=" .
+        //
+        // ShortVarDecl = IdentifierList ":=" ExpressionList .
+        trace!("parse_simple_stmt");
+
+        let exprs = try!(self.parse_expr_list());
+        self.parse_simple_stmt_rest(exprs)
+    }
+
+    /// Parse the rest of a simple statement, given the expression list it starts with.
+    fn parse_simple_stmt_rest(&mut self,
+                              exprs: Vec<Spanned<ast::Expr>>)
+                              -> PResult<ast::SimpleStmt> {
+        trace!("parse_simple_stmt_rest");
+
+        if self.token.kind.is_assign_op() {
+            let op = ast::BinaryOperation::from_token_kind_assign_op(self.bump_and_get().kind);
+            return Ok(ast::SimpleStmt::Assignment(ast::Assignment {
+                lhs: exprs,
+                rhs: try!(self.parse_expr_list()),
+                op: op,
+            }));
+        }
+
+        if self.token.kind == TokenKind::ColonAssign {
+            let idents = try!(self.expr_list_to_ident_list(&exprs));
+            try!(self.check_declares_something(&idents));
+            self.bump();
+            return Ok(ast::SimpleStmt::ShortVarDecl(ast::ShortVarDecl {
+                lhs: idents,
+                rhs: try!(self.parse_expr_list()),
+            }));
+        }
+
+        let expr;
+
+        // Now we have no possible SimpleStmt types left that start with a list of exprs.
+        if exprs.len() > 1 {
+            return Err(self.err(
+                    ErrorKind::unexpected_token(vec![TokenKind::Assign], self.token.clone())
+            ));
+        } else if exprs.len() == 1 {
+            // move exprs to prevent access later and avoid cloning
+            expr = exprs.into_iter().next().unwrap();
+        } else {
+            panic!("BUG");
+        }
+
+        match self.token.kind {
+            TokenKind::Arrow => {
+                self.bump();
+                Ok(ast::SimpleStmt::Send(ast::SendStmt {
+                    channel: expr,
+                    expr: try_span!(self, self.parse_expr()),
+                }))
+            }
+            TokenKind::Increment => {
+                self.bump();
+                Ok(ast::SimpleStmt::IncDec(ast::IncDecStmt {
+                    expr: expr,
+                    is_dec: false,
+                }))
+            }
+            TokenKind::Decrement => {
+                self.bump();
+                Ok(ast::SimpleStmt::IncDec(ast::IncDecStmt {
+                    expr: expr,
+                    is_dec: true,
+                }))
+            }
+            // Whatever comes after an expression statement is checked by the caller.
+            _ => Ok(ast::SimpleStmt::Expr(expr)),
+        }
+    }
+
+    fn parse_decl_stmt(&mut self) -> PResult<ast::DeclStmt> {
+        trace!("parse_decl_stmt");
+        // Declaration   = ConstDecl | TypeDecl | VarDecl .
+
+        match self.token.kind {
+            TokenKind::Const => Ok(ast::DeclStmt::Const(try!(self.parse_const_decl()))),
+            TokenKind::Type => Ok(ast::DeclStmt::TypeDecl(try!(self.parse_type_decl()))),
+            TokenKind::Var => Ok(ast::DeclStmt::VarDecl(try!(self.parse_var_decl()))),
+            _ => {
+                let expected = vec![TokenKind::Const, TokenKind::Type, TokenKind::Var];
+                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+            }
+        }
+    }
+
+    /// Parse either a single spec, or a parenthesized group of specs, using `parse_spec`.
+    ///
+    /// This is the common part of constant, type and variable declarations.
+    fn parse_spec_group<T, F>(&mut self, mut parse_spec: F) -> PResult<Vec<Spanned<T>>>
+        where T: fmt::Debug + Clone + PartialEq + Eq,
+              F: FnMut(&mut Self) -> PResult<T>
+    {
+        let mut specs = Vec::new();
+
+        match self.token.kind {
+            // Grouped declaration.
+            TokenKind::LParen => {
+                self.bump();
+
+                while self.token.kind != TokenKind::RParen {
+                    specs.push(try_span!(self, parse_spec(self)));
+
+                    // The semicolon may be omitted before the closing parenthesis.
+                    if self.token.kind != TokenKind::RParen {
+                        try!(self.eat(TokenKind::Semicolon));
+                    }
+                }
+
+                try!(self.eat(TokenKind::RParen));
+            }
+            _ => specs.push(try_span!(self, parse_spec(self))),
+        }
+
+        Ok(specs)
+    }
+
+    /// Parse a constant declaration (e.g. `const a, b = 1, 2`).
+    fn parse_const_decl(&mut self) -> PResult<ast::ConstDecl> {
+        trace!("parse_const_decl");
+        // Grammar:
+        //
+        // ConstDecl      = "const" ( ConstSpec | "(" { ConstSpec ";" } ")" ) .
+
+        try!(self.eat(TokenKind::Const));
+        let mut specs = try!(self.parse_spec_group(Parser::parse_const_spec));
+
+        // `iota` starts over at 0 in every declaration.
+        for (iota, spec) in specs.iter_mut().enumerate() {
+            spec.item.iota = iota as u32;
+        }
+
+        Ok(ast::ConstDecl { specs: specs })
+    }
+
+    fn parse_const_spec(&mut self) -> PResult<ast::ConstSpec> {
+        trace!("parse_const_spec");
+        // Grammar:
+        //
+        // ConstSpec      = IdentifierList [ [ Type ] "=" ExpressionList ] .
+
+        let idents = try!(self.parse_ident_list());
+
+        // Without a value, the previous spec of the group is repeated (`iota`-style).
+        if let TokenKind::Semicolon | TokenKind::RParen | TokenKind::Eof = self.token.kind {
+            return Ok(ast::ConstSpec {
+                idents: idents,
+                inner: None,
+                iota: 0,
+            });
+        }
+
+        let typ = if self.token.kind == TokenKind::Assign {
+            None
+        } else {
+            Some(try!(self.parse_type()))
+        };
+
+        try!(self.eat(TokenKind::Assign));
+
+        Ok(ast::ConstSpec {
+            idents: idents,
+            inner: Some(ast::ConstSpecInner {
+                typ: typ,
+                exprs: try!(self.parse_expr_list()),
+            }),
+            iota: 0,
+        })
+    }
+
+    /// Parse a type declaration (e.g. `type Celsius float64`).
+    fn parse_type_decl(&mut self) -> PResult<ast::TypeDecl> {
+        trace!("parse_type_decl");
+        // Grammar:
+        //
+        // TypeDecl     = "type" ( TypeSpec | "(" { TypeSpec ";" } ")" ) .
+
+        try!(self.eat(TokenKind::Type));
+        Ok(ast::TypeDecl { specs: try!(self.parse_spec_group(Parser::parse_type_spec)) })
+    }
+
+    fn parse_type_spec(&mut self) -> PResult<ast::TypeSpec> {
+        trace!("parse_type_spec");
+        // Grammar:
+        //
+        // TypeSpec     = AliasDecl | TypeDef .
+        // AliasDecl    = identifier "=" Type .
+        // TypeDef      = identifier Type .
+
+        let ident = try_span!(self, self.parse_ident());
+        let is_alias = self.token.kind == TokenKind::Assign;
+        if is_alias {
+            self.bump();
+        }
+
+        Ok(ast::TypeSpec {
+            ident: ident,
+            typ: try_span!(self, self.parse_type()),
+            is_alias: is_alias,
+        })
+    }
+
+    /// Parse a variable declaration (e.g. `var a, b int = 1, 2`).
+    fn parse_var_decl(&mut self) -> PResult<ast::VarDecl> {
+        trace!("parse_var_decl");
+        // Grammar:
+        //
+        // VarDecl     = "var" ( VarSpec | "(" { VarSpec ";" } ")" ) .
+
+        try!(self.eat(TokenKind::Var));
+        Ok(ast::VarDecl { specs: try!(self.parse_spec_group(Parser::parse_var_spec)) })
+    }
+
+    fn parse_var_spec(&mut self) -> PResult<ast::VarSpec> {
+        trace!("parse_var_spec");
+        // Grammar:
+        //
+        // VarSpec     = IdentifierList ( Type [ "=" ExpressionList ] | "=" ExpressionList ) .
+
+        let idents = try!(self.parse_ident_list());
+
+        let typ = if self.token.kind == TokenKind::Assign {
+            None
+        } else {
+            Some(try!(self.parse_type()))
+        };
+
+        let exprs = if self.token.kind == TokenKind::Assign {
+            self.bump();
+            try!(self.parse_expr_list())
+        } else {
+            Vec::new()
+        };
+
+        Ok(ast::VarSpec {
+            idents: idents,
+            typ: typ,
+            exprs: exprs,
+        })
+    }
+
+    // XXX: error msg
+    fn parse_expr(&mut self) -> PResult<ast::Expr> {
+        trace!("parse_expr");
+
+        self.nested(|p| p.parse_potential_binary_expr(0))
+    }
+
+    fn parse_expr_list(&mut self) -> PResult<Vec<Spanned<ast::Expr>>> {
+        // ExpressionList = Expression { "," Expression } .
+        trace!("parse_expr_list");
+
+        let mut res = Vec::new();
+
+        res.push(try_span!(self, self.parse_expr()));
+
+        while self.token.kind == TokenKind::Comma {
+            self.bump();
+            res.push(try_span!(self, self.parse_expr()));
+        }
+
+        Ok(res)
+    }
+
+    /// Parse a unary *expression*, which can be a primary expression OR a unary *operation*.
+    // XXX: too much repetition, could probably be shortened
+    fn parse_unary_expr(&mut self) -> PResult<ast::UnaryExpr> {
+        // UnaryExpr  = PrimaryExpr | unary_op UnaryExpr .
+        trace!("parse_unary_expr");
+
+        match self.token.kind {
+            // unary_op   = "+" | "-" | "!" | "^" | "*" | "&" | "<-" .
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Not | TokenKind::Caret |
+            TokenKind::Star | TokenKind::And | TokenKind::Arrow => {
+                Ok(ast::UnaryExpr::UnaryOperation(try!(self.parse_unary_operation())))
+            }
+            // No operator, this can only be a primary expression.
+            t if OPERAND_STARTS.contains(&t) || CONVERSION_STARTS.contains(&t) => {
+                let x = try!(self.parse_primary_expr());
+                Ok(ast::UnaryExpr::Primary(Box::new(x)))
+            }
+            _ => {
+                let expected = [OPERAND_STARTS, CONVERSION_STARTS, UNARY_OPS].concat();
+                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+            }
+        }
+    }
+
+    fn parse_primary_expr(&mut self) -> PResult<ast::PrimaryExpr> {
+        // PrimaryExpr =
+        // 	Operand |
+        // 	Conversion |
+        // 	PrimaryExpr Selector |
+        // 	PrimaryExpr Index |
+        // 	PrimaryExpr Slice |
+        // 	PrimaryExpr TypeAssertion |
+        // 	PrimaryExpr Arguments .
+        trace!("parse_primary_expr");
+
+        let start = self.span.start;
+        let expr = match self.token.kind {
+            TokenKind::LBracket | TokenKind::Map | TokenKind::Struct | TokenKind::Chan |
+            TokenKind::Interface => try!(self.parse_conversion_or_composite_lit()),
+            TokenKind::LParen => try!(self.parse_paren_expr()),
+            t if OPERAND_STARTS.contains(&t) => {
+                ast::PrimaryExpr::Operand(try!(self.parse_operand()))
+            }
+            _ => {
+                let expected = [OPERAND_STARTS, CONVERSION_STARTS].concat();
+                return Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())));
+            }
+        };
+
+        self.parse_primary_expr_rest(start, expr)
+    }
+
+    /// Parse the postfix operators applied to a primary expression starting at `start`.
+    fn parse_primary_expr_rest(&mut self,
+                               start: u32,
+                               mut expr: ast::PrimaryExpr)
+                               -> PResult<ast::PrimaryExpr> {
+        trace!("parse_primary_expr_rest");
+
+        // Postfix operators are applied left to right, each one wrapping what came before.
+        loop {
+            let operand_span = Span {
+                start: start,
+                end: self.prev_end_offset,
+            };
+
+            expr = match self.token.kind {
+                TokenKind::Dot => {
+                    self.bump();
+
+                    match self.token.kind {
+                        // TypeAssertion  = "." "(" Type ")" .
+                        TokenKind::LParen => {
+                            self.bump();
+
+                            let typ = if self.token.kind == TokenKind::Type {
+                                self.bump();
+                                None
+                            } else {
+                                Some(try_span!(self, self.parse_type()))
+                            };
+                            try!(self.eat(TokenKind::RParen));
+
+                            // `x.(type)` is only valid as the guard of a type switch, which is
+                            // checked by parse_switch_stmt once the whole header is parsed.
+                            if typ.is_none() {
+                                let span = Span {
+                                    start: start,
+                                    end: self.prev_end_offset,
+                                };
+                                match self.type_switch_guards {
+                                    Some(ref mut guards) => guards.push(span),
+                                    None => return Err(misplaced_type_switch_guard(span)),
+                                }
+                            }
+
+                            ast::PrimaryExpr::TypeAssertion(ast::TypeAssertion {
+                                expr: Box::new(Spanned::new(operand_span, expr)),
+                                typ: typ,
+                            })
+                        }
+                        // Selector       = "." identifier .
+                        _ => {
+                            ast::PrimaryExpr::SelectorExpr(ast::SelectorExpr {
+                                operand: Box::new(expr),
+                                selector: try!(self.parse_ident()),
+                            })
+                        }
+                    }
+                }
+                TokenKind::LBracket => {
+                    let operand = Spanned::new(operand_span, expr);
+                    try!(self.with_composite_lits(true, |p| p.parse_index_or_slice(operand)))
+                }
+                TokenKind::LParen => {
+                    ast::PrimaryExpr::FuncCall(ast::FuncCall {
+                        callee: Box::new(Spanned::new(operand_span, expr)),
+                        args: try!(self.with_composite_lits(true, |p| p.parse_call_args())),
+                    })
+                }
+                _ => break,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_index_or_slice(&mut self,
+                            operand: Spanned<ast::PrimaryExpr>)
+                            -> PResult<ast::PrimaryExpr> {
+        // Index          = "[" Expression "]" .
+        // Slice          = "[" [ Expression ] ":" [ Expression ] "]" |
+        //                  "[" [ Expression ] ":" Expression ":" Expression "]" .
+        trace!("parse_index_or_slice");
+
+        try!(self.eat(TokenKind::LBracket));
+
+        let low = if self.token.kind != TokenKind::Colon {
+            let index = try_span!(self, self.parse_expr());
+
+            if self.token.kind == TokenKind::RBracket {
+                self.bump();
+                return Ok(ast::PrimaryExpr::Indexing(ast::IndexExpr {
+                    operand: Box::new(operand),
+                    index: index,
+                }));
+            }
+
+            Some(index)
+        } else {
+            None
+        };
+
+        try!(self.eat(TokenKind::Colon));
+
+        let high = match self.token.kind {
+            TokenKind::Colon | TokenKind::RBracket => None,
+            _ => Some(try_span!(self, self.parse_expr())),
+        };
+
+        let max = if self.token.kind == TokenKind::Colon {
+            // Only the first index of a full slice expression may be omitted.
+            if high.is_none() {
+                return Err(self.err(ErrorKind::other("middle index required in 3-index slice")));
+            }
+
+            self.bump();
+            Some(try_span!(self, self.parse_expr()))
+        } else {
+            None
+        };
+
+        try!(self.eat(TokenKind::RBracket));
+
+        Ok(ast::PrimaryExpr::Slicing(ast::SliceExpr {
+            operand: Box::new(operand),
+            slicing: ast::Slicing {
+                low: low,
+                high: high,
+                max: max,
+            },
+        }))
+    }
+
+    fn parse_call_args(&mut self) -> PResult<ast::Arguments> {
+        // Arguments      = "(" [ ( ExpressionList | Type [ "," ExpressionList ] ) [ "..." ]
+        //                  [ "," ] ] ")" .
+        trace!("parse_call_args");
+
+        try!(self.eat(TokenKind::LParen));
+        let mut exprs = Vec::new();
+        let mut variadic = false;
+
+        // FIXME: type arguments (e.g. `make([]int, n)`).
+        while self.token.kind != TokenKind::RParen {
+            if variadic {
+                let msg = "can only use ... with final argument in list";
+                return Err(self.err(ErrorKind::other(msg)));
+            }
+
+            exprs.push(try_span!(self, self.parse_expr()));
+            variadic = try!(self.parse_ellipsis());
+
+            try!(self.eat_list_comma(TokenKind::RParen));
+        }
+
+        try!(self.eat(TokenKind::RParen));
+
+        Ok(ast::Arguments {
+            typ: None,
+            expressions: exprs,
+            variadic: variadic,
+        })
+    }
+
+    fn parse_operand(&mut self) -> PResult<ast::Operand> {
+        // Operand     = Literal | OperandName | MethodExpr | "(" Expression ")" .
+        // OperandName = identifier | QualifiedIdent.
+        trace!("parse_operand");
+
+        match self.token.kind {
+            TokenKind::Ident => {
+                let start = self.span.start;
+                let name = try!(self.parse_ident());
+
+                // Whether `a.b` is a qualified identifier or a selector expression depends on
+                // what `a` refers to, which isn't known yet. It is parsed as the former.
+                let ident = if self.token.kind == TokenKind::Dot &&
+                               self.next_kind() == TokenKind::Ident {
+                    try!(self.parse_type_name_rest(name))
+                } else {
+                    ast::MaybeQualifiedIdent {
+                        package: None,
+                        name: name,
+                    }
+                };
+
+                if self.token.kind != TokenKind::LBrace || self.no_composite_lit {
+                    return Ok(ast::Operand::Ident(ident));
+                }
+
+                // A composite literal of a named type.
+                let typ = Spanned::new(Span {
+                                           start: start,
+                                           end: self.prev_end_offset,
+                                       },
+                                       ast::LiteralType::Type(ident));
+                let lit = try!(self.finish_composite_lit(typ));
+                Ok(ast::Operand::Lit(ast::Literal::Composite(lit)))
+            }
+            t if t.can_start_basic_lit() => {
+                Ok(ast::Operand::Lit(ast::Literal::Basic(try!(self.parse_basic_lit()))))
+            }
+            TokenKind::LBracket | TokenKind::Map | TokenKind::Struct => {
+                Ok(ast::Operand::Lit(ast::Literal::Composite(try!(self.parse_composite_lit()))))
+            }
+            TokenKind::Func => {
+                Ok(ast::Operand::Lit(ast::Literal::Func(try!(self.parse_func_lit()))))
+            }
+            TokenKind::LParen => {
+                self.bump();
+                let expr = try!(self.with_composite_lits(true, |p| p.parse_expr()));
+                try!(self.eat(TokenKind::RParen));
+                Ok(ast::Operand::Expr(expr))
+            }
+            _ => {
+                let expected = OPERAND_STARTS.to_vec();
+                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+            }
+        }
+    }
+
+    /// Parse an expression in parentheses, or a conversion to a type in parentheses (e.g.
+    /// `(chan int)(nil)`).
+    fn parse_paren_expr(&mut self) -> PResult<ast::PrimaryExpr> {
+        // Operand    = ... | "(" Expression ")" .
+        // Conversion = Type "(" Expression [ "," ] ")" .
+        // Type       = ... | "(" Type ")" .
+        trace!("parse_paren_expr");
+
+        let start = self.span.start;
+        try!(self.eat(TokenKind::LParen));
+
+        // Only a type literal can start with one of these. A pointer type can't be told apart
+        // from a dereference if a name follows the star, so `(*T)(x)` is parsed as a call.
+        let starts_type = match self.token.kind {
+            TokenKind::LBracket | TokenKind::Map | TokenKind::Chan | TokenKind::Struct |
+            TokenKind::Interface | TokenKind::Func => true,
+            TokenKind::Star => {
+                match self.next_kind() {
+                    TokenKind::LBracket | TokenKind::Map | TokenKind::Chan |
+                    TokenKind::Struct | TokenKind::Interface | TokenKind::Func => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+
+        if !starts_type {
+            let expr = try!(self.with_composite_lits(true, |p| p.parse_expr()));
+            try!(self.eat(TokenKind::RParen));
+            return Ok(ast::PrimaryExpr::Operand(ast::Operand::Expr(expr)));
+        }
+
+        let typ = try_span!(self, self.parse_type());
+
+        if self.token.kind == TokenKind::RParen {
+            self.bump();
+            let span = Span {
+                start: start,
+                end: self.prev_end_offset,
+            };
+            return self.finish_conversion(Spanned::new(span, typ.item));
+        }
+
+        // The type starts an expression, e.g. `([]int{1, 2})` or `(func() {})`.
+        let expr = try!(self.with_composite_lits(true, |p| p.parse_expr_starting_with_type(typ)));
+        try!(self.eat(TokenKind::RParen));
+        Ok(ast::PrimaryExpr::Operand(ast::Operand::Expr(expr)))
+    }
+
+    /// Parse the rest of an expression, given the type it starts with.
+    fn parse_expr_starting_with_type(&mut self, typ: Spanned<ast::Type>) -> PResult<ast::Expr> {
+        trace!("parse_expr_starting_with_type");
+
+        let span = typ.span;
+        let primary = match typ.item {
+            ast::Type::Literal(lit) => {
+                match *lit {
+                    ast::TypeLiteral::Func(func) => {
+                        ast::PrimaryExpr::Operand(ast::Operand::Lit(ast::Literal::Func(
+                            ast::FuncLit {
+                                signature: func.signature,
+                                body: try!(self.parse_block()),
+                            })))
+                    }
+                    lit => {
+                        let typ = Spanned::new(span, ast::Type::Literal(Box::new(lit)));
+                        try!(self.finish_conversion_or_composite_lit(typ))
+                    }
+                }
+            }
+            item => try!(self.finish_conversion_or_composite_lit(Spanned::new(span, item))),
+        };
+
+        let primary = try_span!(self, self.parse_primary_expr_rest(span.start, primary));
+        let unary = Spanned::new(primary.span,
+                                 ast::Expr::Unary(ast::UnaryExpr::Primary(Box::new(primary.item))));
+        self.parse_binary_expr_rest(unary, 0)
     }
 
-    fn expr_list_to_ident_list(&self,
-                               exprs: &Vec<Spanned<ast::Expr>>)
-                               -> PResult<Vec<Spanned<String>>> {
-        trace!("expr_list_to_ident_list");
-        // XXX: better errors
+    fn parse_func_lit(&mut self) -> PResult<ast::FuncLit> {
+        // FunctionLit = "func" Function .
+        trace!("parse_func_lit");
 
-        let mut idents = Vec::new();
+        try!(self.eat(TokenKind::Func));
 
-        use ast::{Expr, UnaryExpr, PrimaryExpr, Operand};
+        Ok(ast::FuncLit {
+            signature: try!(self.parse_func_signature()),
+            body: try!(self.parse_block()),
+        })
+    }
 
-        for expr in exprs {
-            if let Expr::Unary(UnaryExpr::Primary(x)) = expr.item.clone() {
-                if let PrimaryExpr::Operand(Operand::Ident(mqident)) = *x {
-                    if mqident.package.is_some() {
-                        return Err(Error {
-                            kind: ErrorKind::other("expected unqualified ident"),
-                            span: expr.span,
-                        });
-                    }
+    fn parse_composite_lit(&mut self) -> PResult<ast::CompositeLit> {
+        // CompositeLit  = LiteralType LiteralValue .
+        trace!("parse_composite_lit");
 
-                    idents.push(Spanned::new(expr.span, mqident.name));
-                    continue;
+        let typ = try!(self.parse_literal_type());
+        self.finish_composite_lit(typ)
+    }
+
+    /// Parse the value of a composite literal, given its type.
+    fn finish_composite_lit(&mut self,
+                            typ: Spanned<ast::LiteralType>)
+                            -> PResult<ast::CompositeLit> {
+        trace!("finish_composite_lit");
+
+        let val = try!(self.parse_literal_value());
+
+        // Only struct literals may not mix keyed and positional elements. A named type could be an
+        // array whose keys are constants, so checking its literals is left to the type checker.
+        if let ast::LiteralType::Struct(_) = typ.item {
+            if let Some(first) = val.elems.first() {
+                let keyed = first.key.is_some();
+                if let Some(elem) = val.elems.iter().find(|elem| elem.key.is_some() != keyed) {
+                    let span = elem.key.as_ref().map_or(elem.elem.span, |key| key.span);
+                    return Err(Error {
+                        span: span,
+                        kind: ErrorKind::other("mixture of field:value and value elements in \
+                                                struct literal"),
+                    });
                 }
             }
-
-            // didn't successfully turn the expr into an ident
-            return Err(Error {
-                kind: ErrorKind::other("expected ident"),
-                span: expr.span,
-            });
         }
 
-        Ok(idents)
+        Ok(ast::CompositeLit {
+            typ: typ,
+            val: val,
+        })
     }
 
-    fn parse_simple_stmt(&mut self) -> PResult<ast::SimpleStmt> {
-        // SimpleStmt = EmptyStmt | ExpressionStmt | SendStmt | IncDecStmt | Assignment |
-        //  ShortVarDecl .
-        //
-        // EmptyStmt = .
-        // ExpressionStmt = Expression .
-        // SendStmt = Channel "<-" Expression .
-        // Channel  = Expression .
-        // IncDecStmt = Expression ( "++" | "--" ) .
-        // Assignment = ExpressionList assign_op ExpressionList .
-        // assign_op = [ add_op | mul_op ] "=" .
-        //
-        // ShortVarDecl = IdentifierList ":=" ExpressionList .
-        trace!("parse_simple_stmt");
+    fn parse_literal_type(&mut self) -> PResult<Spanned<ast::LiteralType>> {
+        // LiteralType   = StructType | ArrayType | "[" "..." "]" ElementType |
+        //                 SliceType | MapType | TypeName .
+        trace!("parse_literal_type");
 
-        let exprs = try!(self.parse_expr_list());
+        let typ = try_span!(self, self.parse_type());
+        to_literal_type(typ)
+    }
 
-        if self.token.kind.is_assign_op() {
-            let op = ast::BinaryOperation::from_token_kind_assign_op(self.bump_and_get().kind);
-            return Ok(ast::SimpleStmt::Assignment(ast::Assignment {
-                lhs: exprs,
-                rhs: try!(self.parse_expr_list()),
-                op: op,
-            }));
-        }
+    /// Parse a primary expression starting with a type literal, which is either a composite
+    /// literal or a conversion (e.g. `[]int{1, 2}` or `[]byte(s)`).
+    ///
+    /// Conversions to a type name look just like function calls, and are parsed as such.
+    fn parse_conversion_or_composite_lit(&mut self) -> PResult<ast::PrimaryExpr> {
+        // Conversion = Type "(" Expression [ "," ] ")" .
+        trace!("parse_conversion_or_composite_lit");
 
-        if self.token.kind == TokenKind::ColonAssign {
-            let idents = try!(self.expr_list_to_ident_list(&exprs));
-            return Ok(ast::SimpleStmt::ShortVarDecl(ast::ShortVarDecl {
-                lhs: idents,
-                rhs: try!(self.parse_expr_list()),
-            }));
-        }
+        let typ = try_span!(self, self.parse_type());
+        self.finish_conversion_or_composite_lit(typ)
+    }
 
-        let expr;
+    /// Parse the rest of a conversion or composite literal, given its type.
+    fn finish_conversion_or_composite_lit(&mut self,
+                                          typ: Spanned<ast::Type>)
+                                          -> PResult<ast::PrimaryExpr> {
+        trace!("finish_conversion_or_composite_lit");
 
-        // Now we have no possible SimpleStmt types left that start with a list of exprs.
-        if exprs.len() > 1 {
-            return Err(self.err(
-                    ErrorKind::unexpected_token(vec![TokenKind::Assign], self.token.clone())
-            ));
-        } else if exprs.len() == 1 {
-            // move exprs to prevent access later and avoid cloning
-            expr = exprs.into_iter().next().unwrap();
-        } else {
-            panic!("BUG");
+        if self.token.kind != TokenKind::LParen {
+            let lit = try!(self.finish_composite_lit(try!(to_literal_type(typ))));
+            return Ok(ast::PrimaryExpr::Operand(ast::Operand::Lit(ast::Literal::Composite(lit))));
         }
 
-        match self.token.kind {
-            TokenKind::Arrow => {
-                self.bump();
-                Ok(ast::SimpleStmt::Send(ast::SendStmt {
-                    channel: expr,
-                    expr: try_span!(self, self.parse_expr()),
-                }))
-            }
-            TokenKind::Increment => {
-                self.bump();
-                Ok(ast::SimpleStmt::IncDec(ast::IncDecStmt {
-                    expr: expr,
-                    is_dec: false,
-                }))
-            }
-            TokenKind::Decrement => {
-                self.bump();
-                Ok(ast::SimpleStmt::IncDec(ast::IncDecStmt {
-                    expr: expr,
-                    is_dec: true,
-                }))
-            }
-            TokenKind::Semicolon => Ok(ast::SimpleStmt::Expr(expr)),
-            _ => {
-                let expected = vec![TokenKind::Arrow,
-                                    TokenKind::Increment,
-                                    TokenKind::Decrement,
-                                    TokenKind::Assign];
-                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
-            }
-        }
+        self.finish_conversion(typ)
     }
 
-    fn parse_decl_stmt(&mut self) -> PResult<ast::SimpleStmt> {
-        trace!("parse_decl_stmt");
-        unimplemented!()
-    }
+    /// Parse the parenthesized expression of a conversion, given the type to convert to.
+    fn finish_conversion(&mut self, typ: Spanned<ast::Type>) -> PResult<ast::PrimaryExpr> {
+        trace!("finish_conversion");
 
-    // XXX: error msg
-    fn parse_expr(&mut self) -> PResult<ast::Expr> {
-        trace!("parse_expr");
+        try!(self.eat(TokenKind::LParen));
+        let expr = try_span!(self, self.with_composite_lits(true, |p| p.parse_expr()));
+        if self.token.kind == TokenKind::Comma {
+            self.bump();
+        }
+        try!(self.eat(TokenKind::RParen));
 
-        self.parse_potential_binary_expr(0)
+        Ok(ast::PrimaryExpr::Conversion(ast::Conversion {
+            typ: typ,
+            expr: expr,
+        }))
     }
 
-    fn parse_expr_list(&mut self) -> PResult<Vec<Spanned<ast::Expr>>> {
-        // ExpressionList = Expression { "," Expression } .
-        trace!("parse_expr_list");
+    fn parse_literal_value(&mut self) -> PResult<ast::LiteralValue> {
+        // LiteralValue  = "{" [ ElementList [ "," ] ] "}" .
+        // ElementList   = KeyedElement { "," KeyedElement } .
+        trace!("parse_literal_value");
 
-        let mut res = Vec::new();
+        try!(self.eat(TokenKind::LBrace));
+        let mut elems = Vec::new();
 
-        res.push(try_span!(self, self.parse_expr()));
+        while self.token.kind != TokenKind::RBrace {
+            let elem = self.nested(|p| p.with_composite_lits(true, |p| p.parse_keyed_elem()));
+            elems.push(try!(elem));
 
-        while self.token.kind == TokenKind::Comma {
-            self.bump();
-            res.push(try_span!(self, self.parse_expr()));
+            try!(self.eat_list_comma(TokenKind::RBrace));
         }
 
-        Ok(res)
+        try!(self.eat(TokenKind::RBrace));
+        Ok(ast::LiteralValue { elems: elems })
     }
 
-    /// Parse a unary *expression*, which can be a primary expression OR a unary *operation*.
-    // XXX: too much repetition, could probably be shortened
-    fn parse_unary_expr(&mut self) -> PResult<ast::UnaryExpr> {
-        // UnaryExpr  = PrimaryExpr | unary_op UnaryExpr .
-        trace!("parse_unary_expr");
+    fn parse_keyed_elem(&mut self) -> PResult<ast::KeyedElem> {
+        // KeyedElement  = [ Key ":" ] Element .
+        // Key           = FieldName | Expression | LiteralValue .
+        trace!("parse_keyed_elem");
 
-        match self.token.kind {
-            // kind: unary operator
-            TokenKind::Plus | TokenKind::Minus | TokenKind::Not | TokenKind::Caret |
-            TokenKind::And => {
-                let op = ast::UnaryOperator::from_token_kind(self.token.kind).expect("BUG");
-                self.bump();
-                let x = try_span!(self, self.parse_unary_expr());
-                Ok(ast::UnaryExpr::UnaryOperation(ast::UnaryOperation {
-                    operator: op,
-                    operand: Box::new(x),
-                }))
-            }
-            // channel receive operation
-            TokenKind::Arrow => {
-                self.bump();
-                let x = try_span!(self, self.parse_unary_expr());
-                Ok(ast::UnaryExpr::UnaryOperation(ast::UnaryOperation {
-                    operator: ast::UnaryOperator::ChanReceive,
-                    operand: Box::new(x),
-                }))
-            }
+        let first = try_span!(self, self.parse_elem());
 
-            // deref expression - star op
-            TokenKind::Star => {
-                self.bump();
-                let x = try_span!(self, self.parse_unary_expr());
-                Ok(ast::UnaryExpr::UnaryOperation(ast::UnaryOperation {
-                    operator: ast::UnaryOperator::Deref,
-                    operand: Box::new(x),
-                }))
-            }
-            // No operator, this can only be a primary expression.
-            _ => {
-                let x = try!(self.parse_primary_expr());
-                Ok(ast::UnaryExpr::Primary(Box::new(x)))
-            }
+        if self.token.kind != TokenKind::Colon {
+            return Ok(ast::KeyedElem {
+                key: None,
+                elem: first,
+            });
         }
+        self.bump();
+
+        // A field name can't be told apart from an expression without knowing the literal type,
+        // so bare identifiers are kept as expressions.
+        let key = match first.item {
+            ast::Elem::Expr(expr) => ast::Key::Expr(expr),
+            ast::Elem::LiteralValue(val) => ast::Key::LiteralValue(val),
+        };
+
+        Ok(ast::KeyedElem {
+            key: Some(Spanned::new(first.span, key)),
+            elem: try_span!(self, self.parse_elem()),
+        })
     }
 
-    fn parse_primary_expr(&mut self) -> PResult<ast::PrimaryExpr> {
-        unimplemented!()
+    fn parse_elem(&mut self) -> PResult<ast::Elem> {
+        // Element       = Expression | LiteralValue .
+        trace!("parse_elem");
+
+        // Within a composite literal of array, slice or map type, elements and keys that are
+        // themselves composite literals may elide their type, which is that of the enclosing
+        // literal's elements or keys.
+        if self.token.kind == TokenKind::LBrace {
+            Ok(ast::Elem::LiteralValue(try!(self.parse_literal_value())))
+        } else {
+            Ok(ast::Elem::Expr(try!(self.parse_expr())))
+        }
     }
 
     fn parse_unary_operator(&mut self) -> PResult<ast::UnaryOperator> {
         trace!("parse_unary_operator");
 
-        let k = match self.token.kind {
-            TokenKind::Plus => ast::UnaryOperator::Plus,
-            TokenKind::Minus => ast::UnaryOperator::Minus,
-            TokenKind::Not => ast::UnaryOperator::Xor,
-            _ => panic!(),
-        };
-
-        Ok(k)
+        match ast::UnaryOperator::from_token_kind(self.token.kind) {
+            Some(op) => {
+                self.bump();
+                Ok(op)
+            }
+            None => {
+                use token::TokenKind::*;
+                let expected = vec![Plus, Minus, Not, Caret, Star, And, Arrow];
+                Err(self.err(ErrorKind::unexpected_token(expected, self.token.clone())))
+            }
+        }
     }
 
     fn parse_basic_lit(&mut self) -> PResult<ast::BasicLit> {
@@ -847,9 +2612,18 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         let mut char_indices = value.char_indices().peekable();
         let result;
 
-        let (_, c) = char_indices.next().unwrap();
+        let c = match char_indices.next() {
+            Some((_, c)) => c,
+            None => return Err(self.err(ErrorKind::other("empty rune literal"))),
+        };
         if c == '\\' {
-            let &(_, pc) = char_indices.peek().expect("BUG: rune lit containing only \\");
+            let pc = match char_indices.peek() {
+                Some(&(_, pc)) => pc,
+                None => {
+                    let msg = "escape sequence not terminated in rune literal";
+                    return Err(self.err(ErrorKind::other(msg)));
+                }
+            };
 
             // First check to see if we have a simple escape.
             if let Some(escape_byte) = self.get_simple_escape(pc) {
@@ -894,6 +2668,10 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         // exponent  = ( "e" | "E" ) [ "+" | "-" ] decimals .
         trace!("interpret_float_lit");
 
+        if value.starts_with("0x") || value.starts_with("0X") {
+            return self.interpret_hex_float_lit(&value[2..], token_name);
+        }
+
         let mut res = BigRational::from_integer(BigInt::from(0u8));
         let mut chars = value.chars().peekable();
         let mut parse_exponent = false;
@@ -913,9 +2691,8 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
                     res = res * BigRational::from_integer(BigInt::from(10u8));
                     res = res + BigRational::from_integer(BigInt::from(digit));
                 } else {
-                    res = res +
-                          digit_value /
-                          BigRational::from_integer(BigInt::from(10u32.pow(digits_after_dot)));
+                    let divisor = num::pow(BigInt::from(10u8), digits_after_dot as usize);
+                    res = res + digit_value / BigRational::from_integer(divisor);
 
                     digits_after_dot += 1;
                 }
@@ -955,6 +2732,85 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         Ok(res)
     }
 
+    /// Interpret the value of a hexadecimal float/imaginary literal, without its `0x` prefix.
+    fn interpret_hex_float_lit(&mut self, value: &str, token_name: &str) -> PResult<BigRational> {
+        // hex_mantissa = hex_digits [ "." [ hex_digits ] ] | "." hex_digits .
+        // hex_exponent = ( "p" | "P" ) [ "+" | "-" ] decimal_digits .
+        trace!("interpret_hex_float_lit");
+
+        let p = match value.find(|c| c == 'p' || c == 'P') {
+            Some(p) => p,
+            None => {
+                let e = ErrorKind::other("hexadecimal mantissa requires a 'p' exponent");
+                return Err(self.err(e));
+            }
+        };
+        let (mantissa, exponent) = (&value[..p], &value[p + 1..]);
+
+        if mantissa.chars().all(|c| c == '.') {
+            return Err(self.err(ErrorKind::other(format!("{} has no mantissa digits",
+                                                         token_name))));
+        }
+
+        // The mantissa is an integer scaled down by 16 for each digit after the dot.
+        let mut digits = BigInt::from(0u8);
+        let mut scale = 0i64;
+        let mut after_dot = false;
+
+        for c in mantissa.chars() {
+            if c == '.' {
+                after_dot = true;
+                continue;
+            }
+
+            let digit = match c.to_digit(16) {
+                Some(d) => d,
+                None => {
+                    let msg = format!("invalid character in {}: {}", token_name, c);
+                    return Err(self.err(ErrorKind::other(msg)));
+                }
+            };
+            digits = digits * BigInt::from(16u8) + BigInt::from(digit);
+            if after_dot {
+                scale -= 4;
+            }
+        }
+
+        let (negative, exponent) = if exponent.starts_with('-') {
+            (true, &exponent[1..])
+        } else {
+            (false, exponent.trim_left_matches('+'))
+        };
+
+        if exponent.is_empty() {
+            return Err(self.err(ErrorKind::other(format!("malformed {} exponent", token_name))));
+        }
+
+        let exponent = match exponent.parse::<i64>() {
+            Ok(exponent) if negative => -exponent,
+            Ok(exponent) => exponent,
+            Err(_) => {
+                return Err(self.err(ErrorKind::other(format!("{} exponent is too large",
+                                                             token_name))))
+            }
+        };
+
+        let power = match scale.checked_add(exponent) {
+            Some(power) if power.abs() <= MAX_BINARY_EXPONENT => power,
+            _ => {
+                return Err(self.err(ErrorKind::other(format!("{} exponent is too large",
+                                                             token_name))))
+            }
+        };
+        let factor = num::pow(BigInt::from(2u8), power.abs() as usize);
+
+        Ok(if power < 0 {
+            BigRational::new(digits, factor)
+        } else {
+            BigRational::from_integer(digits * factor)
+        })
+    }
+
     fn parse_int_lit(&mut self) -> PResult<BigInt> {
         // int_lit     = decimal_lit | octal_lit | hex_lit .
         // decimal_lit = ( "1" … "9" ) { decimal_digit } .
@@ -1015,7 +2871,7 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
 
         Ok(ast::UnaryOperation {
             operator: try!(self.parse_unary_operator()),
-            operand: Box::new(try_span!(self, self.parse_unary_expr())),
+            operand: Box::new(try_span!(self, self.nested(|p| p.parse_unary_expr()))),
         })
     }
 
@@ -1026,16 +2882,28 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         trace!("parse_potential_binary_expr");
 
         let a = try_span!(self, self.parse_unary_expr());
-        let mut x = Spanned::new(a.span, ast::Expr::Unary(a.item));
+        self.parse_binary_expr_rest(Spanned::new(a.span, ast::Expr::Unary(a.item)), prec1)
+    }
+
+    /// Parse the binary operations applied to `x` whose precedence is at least `prec1`.
+    fn parse_binary_expr_rest(&mut self,
+                              mut x: Spanned<ast::Expr>,
+                              prec1: i32)
+                              -> PResult<ast::Expr> {
+        trace!("parse_binary_expr_rest");
 
         loop {
-            let op_kind = ast::BinaryOperation::from_token_kind(self.token.kind).unwrap();
-            let precedence = op_kind.precedence();
+            // Anything that isn't a binary operator ends the expression.
+            let precedence = match self.token.kind.binary_precedence() {
+                Some(precedence) => precedence as i32,
+                None => return Ok(x.item),
+            };
             if precedence < prec1 {
                 return Ok(x.item);
             }
 
-            self.bump();
+            let op_kind = ast::BinaryOperation::from_token_kind(self.bump_and_get().kind)
+                .expect("BUG: token with a binary precedence is not a binary operator");
 
             let y = try_span!(self, self.parse_potential_binary_expr(precedence + 1));
             let binop_span = Span {
@@ -1069,6 +2937,23 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         }
     }
 
+    /// Parse a list of identifiers separated by commas (e.g. `a, b, c`).
+    fn parse_ident_list(&mut self) -> PResult<Vec<Spanned<String>>> {
+        trace!("parse_ident_list");
+        // Grammar:
+        //
+        // IdentifierList = identifier { "," identifier } .
+
+        let mut idents = vec![try_span!(self, self.parse_ident())];
+
+        while self.token.kind == TokenKind::Comma {
+            self.bump();
+            idents.push(try_span!(self, self.parse_ident()));
+        }
+
+        Ok(idents)
+    }
+
     /// Parse a string literal, whether interpreted or raw.
     /// This is useful because one will often expect a string literal without caring about its
     /// kind.
@@ -1083,6 +2968,9 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
         // ```
 
         match self.token.kind {
+            TokenKind::Str | TokenKind::StrRaw if self.token.value.is_none() => {
+                Err(self.err(ErrorKind::other("missing value in string literal")))
+            }
             TokenKind::Str => {
                 // Interpret the string.
                 let raw_val = self.bump_and_get().value.expect("BUG: missing Str value");
@@ -1258,8 +3146,100 @@ impl<R: Iterator<Item = TokenAndSpan>> Parser<R> {
     }
 }
 
-pub fn parse_tokens(tokens: Vec<TokenAndSpan>) -> ast::SourceFile {
+/// Check that a type may be the type of a composite literal, and convert it.
+fn to_literal_type(typ: Spanned<ast::Type>) -> PResult<Spanned<ast::LiteralType>> {
+    let lit = match typ.item {
+        ast::Type::Plain(name) => return Ok(Spanned::new(typ.span, ast::LiteralType::Type(name))),
+        ast::Type::Literal(lit) => *lit,
+    };
+
+    let lit_type = match lit {
+        ast::TypeLiteral::Struct(typ) => ast::LiteralType::Struct(typ),
+        ast::TypeLiteral::Array(typ) => ast::LiteralType::Array(typ),
+        ast::TypeLiteral::Slice(typ) => ast::LiteralType::Slice(typ),
+        ast::TypeLiteral::Map(typ) => ast::LiteralType::Map(typ),
+        _ => {
+            return Err(Error {
+                span: typ.span,
+                kind: ErrorKind::other("invalid composite literal type"),
+            })
+        }
+    };
+
+    Ok(Spanned::new(typ.span, lit_type))
+}
+
+/// If `expr` is a type switch guard (`x.(type)`), return the expression whose type is switched on.
+/// Otherwise, return `expr` unchanged.
+fn type_switch_guard(expr: Spanned<ast::Expr>)
+                     -> ::std::result::Result<Spanned<ast::PrimaryExpr>, Spanned<ast::Expr>> {
+    use ast::{Expr, UnaryExpr, PrimaryExpr};
+
+    match expr.item {
+        Expr::Unary(UnaryExpr::Primary(primary)) => {
+            match *primary {
+                PrimaryExpr::TypeAssertion(ast::TypeAssertion { expr, typ: None }) => Ok(*expr),
+                primary => {
+                    Err(Spanned::new(expr.span,
+                                     Expr::Unary(UnaryExpr::Primary(Box::new(primary)))))
+                }
+            }
+        }
+        item => Err(Spanned::new(expr.span, item)),
+    }
+}
+
+/// The error for an `x.(type)` expression which isn't the guard of a type switch.
+fn misplaced_type_switch_guard(span: Span) -> Error {
+    Error {
+        span: span,
+        kind: ErrorKind::other("use of .(type) outside type switch"),
+    }
+}
+
+/// An entry of a parameter list, before it is known whether the list is named.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParameterItem {
+    /// A lone (possibly qualified) identifier, which is either a name or a type.
+    Ident(ast::MaybeQualifiedIdent),
+    /// A name followed by a type, which may be variadic.
+    Named(String, bool, ast::Type),
+    /// A type that cannot be a name, which may be variadic.
+    Type(bool, ast::Type),
+}
+
+pub fn parse_tokens(tokens: Vec<TokenAndSpan>) -> PResult<ast::SourceFile> {
     let parser = Parser::new(tokens.into_iter());
-    // XXX: unwrapping
-    parser.parse().unwrap()
+    parser.parse()
+}
+
+/// Decode a string literal token, interpreted (`Str`) or raw (`StrRaw`), into the bytes it denotes.
+pub fn interpret_string_token(token: TokenAndSpan) -> PResult<Vec<u8>> {
+    Parser::new(Some(token).into_iter()).parse_string_lit()
+}
+
+/// Decode the source text of a rune literal, quotes included (e.g. `'\n'`), into the code point it
+/// denotes.
+pub fn interpret_rune_lit(text: &str) -> PResult<char> {
+    let span = Span {
+        start: 0,
+        end: text.len() as u32,
+    };
+
+    if text.len() < 2 || !text.starts_with('\'') || !text.ends_with('\'') {
+        return Err(Error {
+            span: span,
+            kind: ErrorKind::other("expected a quoted rune literal"),
+        });
+    }
+
+    let token = TokenAndSpan {
+        token: Token {
+            kind: TokenKind::Rune,
+            value: Some(text[1..text.len() - 1].into()),
+        },
+        span: span,
+    };
+
+    Parser::new(Some(token).into_iter()).parse_rune_lit()
 }
@@ -2,13 +2,14 @@ use super::*;
 use std::str::FromStr;
 use lexer;
 use ast;
+use ast::diff::assert_ast_eq;
 use num::bigint::BigInt;
 use num::BigRational;
 
 // String literals
 
 fn assert_interpret_string_eq(lit: &str, expect: Vec<u8>) {
-    let tokens = lexer::tokenize(format!("\"{}\"", lit).as_ref());
+    let tokens = lexer::tokenize(format!("\"{}\"", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -20,7 +21,7 @@ fn assert_interpret_string_eq(lit: &str, expect: Vec<u8>) {
 }
 
 fn assert_interpret_string_valid(lit: &str) {
-    let tokens = lexer::tokenize(format!("\"{}\"", lit).as_ref());
+    let tokens = lexer::tokenize(format!("\"{}\"", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -63,7 +64,7 @@ fn test_interpret_string_invalid_codepoint() {
 // Rune literals
 
 fn assert_interpret_rune_eq(lit: &str, expect: char) {
-    let tokens = lexer::tokenize(format!("'{}'", lit).as_ref());
+    let tokens = lexer::tokenize(format!("'{}'", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -75,7 +76,7 @@ fn assert_interpret_rune_eq(lit: &str, expect: char) {
 }
 
 fn assert_interpret_rune_valid(lit: &str) {
-    let tokens = lexer::tokenize(format!("'{}'", lit).as_ref());
+    let tokens = lexer::tokenize(format!("'{}'", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -104,6 +105,33 @@ fn test_interpret_runes() {
     }
 }
 
+#[test]
+fn interpret_quoted_rune_lit() {
+    assert_eq!(interpret_rune_lit("'a'"), Ok('a'));
+    assert_eq!(interpret_rune_lit(r"'\n'"), Ok('\n'));
+    assert_eq!(interpret_rune_lit(r"'\u00e9'"), Ok('é'));
+    assert_eq!(interpret_rune_lit(r"'\''"), Ok('\''));
+
+    assert_eq!(parse_basic_lit_src(r"'\u00e9'").as_char(), Some('é'));
+    assert_eq!(parse_basic_lit_src("1").as_char(), None);
+}
+
+#[test]
+fn interpret_quoted_rune_lit_invalid() {
+    // `e` followed by a combining acute accent: two code points.
+    let err = interpret_rune_lit("'e\u{301}'").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("multiple characters in rune literal"));
+
+    assert!(interpret_rune_lit("'ab'").is_err());
+    assert!(interpret_rune_lit("a").is_err());
+    assert!(interpret_rune_lit("'").is_err());
+
+    let err = interpret_rune_lit("''").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("empty rune literal"));
+    let err = interpret_rune_lit(r"'\'").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("escape sequence not terminated in rune literal"));
+}
+
 #[test]
 #[should_panic]
 fn test_interpret_rune_too_many_characters() {
@@ -137,7 +165,7 @@ fn test_interpret_rune_invalid_codepoint() {
 // Int literals
 
 fn assert_interpret_int_eq(lit: &str, expect: BigInt) {
-    let tokens = lexer::tokenize(format!("{}", lit).as_ref());
+    let tokens = lexer::tokenize(format!("{}", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -160,7 +188,7 @@ fn test_interpret_ints() {
 // Float/imaginary literals
 
 fn assert_interpret_float_eq(lit: &str, expect: BigRational) {
-    let tokens = lexer::tokenize(format!("{}", lit).as_ref());
+    let tokens = lexer::tokenize(format!("{}", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -176,7 +204,7 @@ fn assert_interpret_float_eq(lit: &str, expect: BigRational) {
 }
 
 fn assert_interpret_imaginary_eq(lit: &str, expect: BigRational) {
-    let tokens = lexer::tokenize(format!("{}", lit).as_ref());
+    let tokens = lexer::tokenize(format!("{}", lit).as_ref()).unwrap();
 
     assert_eq!(tokens.len(), 1);
 
@@ -240,3 +268,2387 @@ fn test_interpret_imaginaries() {
         assert_interpret_imaginary_eq(t.0, t.1.clone());
     }
 }
+
+// Helpers for parsing whole constructs
+
+fn parser(src: &str) -> Parser<::std::vec::IntoIter<TokenAndSpan>> {
+    Parser::new(lexer::tokenize(src).unwrap().into_iter())
+}
+
+fn plain_type(name: &str) -> ast::Type {
+    ast::Type::Plain(ast::MaybeQualifiedIdent {
+        package: None,
+        name: name.into(),
+    })
+}
+
+// Types
+
+/// Render the structure of a type as an S-expression. Struct and interface types are displayed.
+fn type_sexpr(typ: &ast::Type) -> String {
+    use ast::TypeLiteral::*;
+
+    let lit = match *typ {
+        ast::Type::Plain(_) => return typ.to_string(),
+        ast::Type::Literal(ref lit) => lit,
+    };
+    let params = |params: &ast::Parameters| {
+        let types: Vec<_> = params.decls.iter().map(|decl| type_sexpr(&decl.typ)).collect();
+        format!("({})", types.join(" "))
+    };
+
+    match **lit {
+        Array(ref array) => {
+            format!("(array {} {})",
+                    sexpr(&array.len),
+                    type_sexpr(&array.element_type))
+        }
+        Slice(ref slice) => format!("(slice {})", type_sexpr(&slice.element_type)),
+        Pointer(ref pointer) => format!("(pointer {})", type_sexpr(&pointer.0)),
+        Map(ref map) => {
+            format!("(map {} {})",
+                    type_sexpr(&map.key_type),
+                    type_sexpr(&map.element_type))
+        }
+        Chan(ref chan) => format!("(chan {:?} {})", chan.direction, type_sexpr(&chan.element_type)),
+        Func(ref func) => {
+            format!("(func {} {})",
+                    params(&func.signature.parameters),
+                    params(&func.signature.result))
+        }
+        Struct(_) | Interface(_) => typ.to_string(),
+    }
+}
+
+fn assert_type_sexpr(src: &str, expect: &str) {
+    let typ = parser(src).parse_type().unwrap();
+    assert_eq!(type_sexpr(&typ), expect, "parsing `{}`", src);
+}
+
+#[test]
+fn parse_compound_types() {
+    assert_type_sexpr("[]func(int) int", "(slice (func (int) (int)))");
+    assert_type_sexpr("map[string]chan int", "(map string (chan Bidirectional int))");
+    assert_type_sexpr("[]*Point", "(slice (pointer Point))");
+    assert_type_sexpr("[][]map[K]V", "(slice (slice (map K V)))");
+    assert_type_sexpr("*[4]*geo.Point", "(pointer (array 4 (pointer geo.Point)))");
+    assert_type_sexpr("func(func() error) []<-chan int",
+                      "(func ((func () (error))) ((slice (chan Receive int))))");
+    assert_type_sexpr("map[*T][]func()",
+                      "(map (pointer T) (slice (func () ())))");
+    assert_type_sexpr("chan<- struct{}", "(chan Send struct{})");
+    // The arrow binds to the leftmost `chan`.
+    assert_type_sexpr("chan<- chan int", "(chan Send (chan Bidirectional int))");
+    assert_type_sexpr("chan (<-chan int)", "(chan Bidirectional (chan Receive int))");
+
+    // Dimensions and keys nest from left to right.
+    assert_type_sexpr("[3][4]int", "(array 3 (array 4 int))");
+    assert_type_sexpr("[2][]*[1]T", "(array 2 (slice (pointer (array 1 T))))");
+    assert_type_sexpr("map[K1]map[K2]V", "(map K1 (map K2 V))");
+    assert_type_sexpr("map[[2]int]map[string][]V", "(map (array 2 int) (map string (slice V)))");
+
+    let err = parser("[]+").parse_type().unwrap_err();
+    match err.kind {
+        ErrorKind::UnexpectedToken { ref found, .. } => assert_eq!(found.kind, TokenKind::Plus),
+        ref kind => panic!("expected an unexpected token error, got {:?}", kind),
+    }
+}
+
+#[test]
+fn parse_parenthesized_types() {
+    assert_type_sexpr("chan chan int", "(chan Bidirectional (chan Bidirectional int))");
+    assert_type_sexpr("chan (chan int)", "(chan Bidirectional (chan Bidirectional int))");
+    assert_type_sexpr("(chan int)", "(chan Bidirectional int)");
+    assert_type_sexpr("((int))", "int");
+    assert_type_sexpr("[](*T)", "(slice (pointer T))");
+    assert_type_sexpr("map[(string)](func())", "(map string (func () ()))");
+
+    // A conversion to a parenthesized type.
+    assert_expr_sexpr("(chan int)(nil)", "(chan int Convert nil)");
+    assert_expr_sexpr("([]byte)(s)", "([]byte Convert s)");
+    assert_expr_sexpr("(func())(f)", "(func() Convert f)");
+    assert_expr_sexpr("(*[]int)(p)", "(*[]int Convert p)");
+    assert_expr_sexpr("(map[K]V)(m)[k]", "((map[K]V Convert m) Index k)");
+    assert_expr_roundtrip("(*[]int)(p)", "(*[]int)(p)");
+    // `*T` may be a dereference, so this is a call until the type checker knows better.
+    assert_expr_sexpr("(*T)(x)", "((Deref T) Call x)");
+
+    // A type may also start an expression in parentheses.
+    assert_expr_roundtrip("([]int{1, 2}[0] + 1)", "([]int{1, 2}[0] + 1)");
+    assert_expr_roundtrip("(func() {})()", "(func() {})()");
+    assert_expr_sexpr("([]byte(s))", "([]byte Convert s)");
+}
+
+#[test]
+fn parse_anonymous_struct_var_type() {
+    let decl = parser("var x struct { A, B int; C string }").parse_var_decl().unwrap();
+
+    assert_eq!(decl.specs.len(), 1);
+    let spec = &decl.specs[0].item;
+    assert_eq!(spec.idents[0].item, "x");
+    assert!(spec.exprs.is_empty());
+
+    let expected = ast::Type::Literal(Box::new(ast::TypeLiteral::Struct(ast::StructType {
+        field_decls: vec![ast::FieldDecl {
+                              inner: ast::InnerFieldDecl::Named {
+                                  idents: vec!["A".into(), "B".into()],
+                                  typ: plain_type("int"),
+                              },
+                              tag: None,
+                          },
+                          ast::FieldDecl {
+                              inner: ast::InnerFieldDecl::Named {
+                                  idents: vec!["C".into()],
+                                  typ: plain_type("string"),
+                              },
+                              tag: None,
+                          }],
+    })));
+    assert_ast_eq(&Some(expected), &spec.typ);
+}
+
+fn embedded_field(is_ptr: bool, package: Option<&str>, name: &str) -> ast::InnerFieldDecl {
+    ast::InnerFieldDecl::Anonymous {
+        is_ptr: is_ptr,
+        type_name: ast::MaybeQualifiedIdent {
+            package: package.map(String::from),
+            name: name.into(),
+        },
+    }
+}
+
+#[test]
+fn parse_embedded_pointer_field() {
+    let typ = parser("struct { *bytes.Buffer; *T; n int }").parse_struct_type().unwrap();
+
+    let inner: Vec<_> = typ.field_decls.into_iter().map(|f| f.inner).collect();
+    assert_ast_eq(&vec![embedded_field(true, Some("bytes"), "Buffer"),
+                        embedded_field(true, None, "T"),
+                        ast::InnerFieldDecl::Named {
+                            idents: vec!["n".into()],
+                            typ: plain_type("int"),
+                        }],
+                  &inner);
+}
+
+#[test]
+fn parse_blank_field_names() {
+    let typ = parser("struct { _ int; X int; _, y [0]func() }").parse_struct_type().unwrap();
+
+    let inner: Vec<_> = typ.field_decls.into_iter().map(|f| f.inner).collect();
+    assert_eq!(inner.len(), 3);
+    assert_ast_eq(&ast::InnerFieldDecl::Named {
+                      idents: vec!["_".into()],
+                      typ: plain_type("int"),
+                  },
+                  &inner[0]);
+    match inner[2] {
+        ast::InnerFieldDecl::Named { ref idents, ref typ } => {
+            assert_eq!(idents, &["_", "y"]);
+            assert_eq!(type_sexpr(typ), "(array 0 (func () ()))");
+        }
+        ref field => panic!("expected a named field, got {:?}", field),
+    }
+
+    // A lone `_` is an embedded field, which a type checker rejects.
+    let typ = parser("struct { _ }").parse_struct_type().unwrap();
+    assert_ast_eq(&embedded_field(false, None, "_"), &typ.field_decls[0].inner);
+}
+
+#[test]
+fn parse_embedded_qualified_field() {
+    let typ = parser("struct {\n\tsync.Mutex `json:\"-\"`\n\tT\n}")
+        .parse_struct_type()
+        .unwrap();
+
+    assert_ast_eq(&vec![ast::FieldDecl {
+                            inner: embedded_field(false, Some("sync"), "Mutex"),
+                            tag: Some(b"json:\"-\"".to_vec()),
+                        },
+                        ast::FieldDecl {
+                            inner: embedded_field(false, None, "T"),
+                            tag: None,
+                        }],
+                  &typ.field_decls);
+}
+
+#[test]
+fn parse_struct_tags() {
+    let src = "struct {\n\
+               \tName string `json:\"name\"`\n\
+               \tAge, Height int\n\
+               \tID int \"db:\\\"id\\\"\"\n\
+               }";
+    let typ = parser(src).parse_struct_type().unwrap();
+
+    let tags: Vec<_> = typ.field_decls.iter().map(|f| f.tag.clone()).collect();
+    assert_eq!(tags,
+               [Some(b"json:\"name\"".to_vec()), None, Some(b"db:\"id\"".to_vec())]);
+    match typ.field_decls[0].inner {
+        ast::InnerFieldDecl::Named { ref idents, .. } => assert_eq!(idents, &["Name"]),
+        ref field => panic!("expected a named field, got {:?}", field),
+    }
+
+    // The tag must follow the type directly.
+    assert!(parser("struct { Name string; `json:\"name\"` }").parse_struct_type().is_err());
+}
+
+#[test]
+fn parse_empty_interface_param() {
+    let decl = parser("func f(v interface{})\n").parse_func_decl().unwrap();
+
+    let params = decl.signature.parameters.decls;
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].identifiers, vec!["v".to_owned()]);
+    assert_ast_eq(&ast::Type::Literal(Box::new(ast::TypeLiteral::Interface(ast::InterfaceType {
+                      specs: vec![],
+                      constraints: vec![],
+                  }))),
+                  &params[0].typ);
+}
+
+#[test]
+fn parse_anonymous_interface_param() {
+    let decl = parser("func f(interface{ Foo(); Bar(x int) string })\n").parse_func_decl().unwrap();
+
+    let params = decl.signature.parameters.decls;
+    assert_eq!(params.len(), 1);
+    assert!(params[0].identifiers.is_empty());
+
+    let expected = ast::InterfaceType {
+        specs: vec![ast::MethodSpec {
+                        name: "Foo".into(),
+                        method: ast::InnerMethodSpec::Signature(ast::FuncSignature {
+                            parameters: ast::Parameters::empty(),
+                            result: ast::Parameters::empty(),
+                        }),
+                    },
+                    ast::MethodSpec {
+                        name: "Bar".into(),
+                        method: ast::InnerMethodSpec::Signature(ast::FuncSignature {
+                            parameters: ast::Parameters {
+                                decls: vec![ast::ParameterDecl {
+                                                identifiers: vec!["x".into()],
+                                                typ: plain_type("int"),
+                                                variadic: false,
+                                            }],
+                            },
+                            result: ast::Parameters::from_single_type(plain_type("string")),
+                        }),
+                    }],
+        constraints: vec![],
+    };
+    assert_ast_eq(&ast::Type::Literal(Box::new(ast::TypeLiteral::Interface(expected))),
+                  &params[0].typ);
+}
+
+fn param_decl(identifiers: &[&str], typ: &str) -> ast::ParameterDecl {
+    ast::ParameterDecl {
+        identifiers: identifiers.iter().map(|&s| s.into()).collect(),
+        typ: plain_type(typ),
+        variadic: false,
+    }
+}
+
+#[test]
+fn parse_grouped_result_names() {
+    let grouped = parser("() (a, b int)").parse_func_signature().unwrap().result;
+    let separate = parser("() (a int, b int)").parse_func_signature().unwrap().result;
+
+    assert_ast_eq(&vec![param_decl(&["a", "b"], "int")], &grouped.decls);
+    assert_ast_eq(&vec![param_decl(&["a"], "int"), param_decl(&["b"], "int")],
+                  &separate.decls);
+
+    let int = plain_type("int");
+    assert_eq!(grouped.expand(), vec![(Some("a"), &int), (Some("b"), &int)]);
+    assert_eq!(grouped.expand(), separate.expand());
+}
+
+#[test]
+fn parse_result_forms() {
+    let result = |src: &str| parser(src).parse_func_signature().unwrap().result.decls;
+
+    assert_ast_eq(&vec![param_decl(&[], "int")], &result("() int"));
+    assert_ast_eq(&vec![param_decl(&[], "int"), param_decl(&[], "error")],
+                  &result("() (int, error)"));
+    assert_ast_eq(&vec![param_decl(&["n"], "int")], &result("() (n int)"));
+    assert_ast_eq(&vec![param_decl(&["n"], "int"), param_decl(&["err"], "error")],
+                  &result("() (n int, err error)"));
+
+    // Parentheses around a single unnamed type are allowed, and make no difference.
+    assert_ast_eq(&result("() int"), &result("() (int)"));
+    assert!(result("() ()").is_empty());
+
+    let err = parser("() (n int, error)").parse_func_signature().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("mixed named and unnamed parameters"));
+}
+
+#[test]
+fn parse_unnamed_params() {
+    let params = parser("(int, pkg.T, ...string)").parse_func_params().unwrap();
+
+    let expected = vec![param_decl(&[], "int"),
+                        ast::ParameterDecl {
+                            identifiers: vec![],
+                            typ: ast::Type::Plain(ast::MaybeQualifiedIdent {
+                                package: Some("pkg".into()),
+                                name: "T".into(),
+                            }),
+                            variadic: false,
+                        },
+                        ast::ParameterDecl {
+                            identifiers: vec![],
+                            typ: plain_type("string"),
+                            variadic: true,
+                        }];
+    assert_ast_eq(&expected, &params.decls);
+}
+
+#[test]
+fn parse_mixed_named_and_unnamed_params() {
+    let err = parser("(a, b int, string)").parse_func_params().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("mixed named and unnamed parameters"));
+    assert_eq!(err.span, Span { start: 11, end: 17 });
+}
+
+#[test]
+fn parse_all_named_or_all_unnamed_params() {
+    let params = parser("()").parse_func_params().unwrap();
+    assert!(params.decls.is_empty());
+
+    // A lone identifier is a type, not a parameter name.
+    let params = parser("(int)").parse_func_params().unwrap();
+    assert_ast_eq(&vec![param_decl(&[], "int")], &params.decls);
+
+    let params = parser("(int, string)").parse_func_params().unwrap();
+    assert_ast_eq(&vec![param_decl(&[], "int"), param_decl(&[], "string")],
+                  &params.decls);
+
+    let params = parser("(a, b int)").parse_func_params().unwrap();
+    assert_ast_eq(&vec![param_decl(&["a", "b"], "int")], &params.decls);
+
+    let err = parser("(a int, string)").parse_func_params().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("mixed named and unnamed parameters"));
+}
+
+#[test]
+fn parse_empty_struct_and_interface_types() {
+    let empty_struct = ast::TypeLiteral::Struct(ast::StructType { field_decls: vec![] });
+    let empty_interface = ast::TypeLiteral::Interface(ast::InterfaceType {
+        specs: vec![],
+        constraints: vec![],
+    });
+
+    let typ = parser("map[int]struct{}").parse_type().unwrap();
+    let expected = ast::TypeLiteral::Map(ast::MapType {
+        key_type: plain_type("int"),
+        element_type: ast::Type::Literal(Box::new(empty_struct)),
+    });
+    assert_ast_eq(&ast::Type::Literal(Box::new(expected)), &typ);
+
+    let params = parser("(v interface{}, w interface {\n})").parse_func_params().unwrap();
+    assert_eq!(params.decls.len(), 2);
+    for decl in &params.decls {
+        assert_ast_eq(&ast::Type::Literal(Box::new(empty_interface.clone())), &decl.typ);
+    }
+
+    // `any` is just a type name to the parser.
+    let params = parser("(v any)").parse_func_params().unwrap();
+    assert_ast_eq(&vec![param_decl(&["v"], "any")], &params.decls);
+}
+
+fn type_term(underlying: bool, name: &str) -> ast::TypeTerm {
+    ast::TypeTerm {
+        underlying: underlying,
+        typ: plain_type(name),
+    }
+}
+
+#[test]
+fn parse_interface_underlying_type_constraint() {
+    let typ = parser("interface { ~int | ~string }").parse_interface_type().unwrap();
+
+    let expected = ast::InterfaceType {
+        specs: vec![],
+        constraints: vec![ast::TypeConstraint {
+                              terms: vec![type_term(true, "int"), type_term(true, "string")],
+                          }],
+    };
+    assert_ast_eq(&expected, &typ);
+
+    let typ = parser("interface { ~int }").parse_interface_type().unwrap();
+    assert_ast_eq(&vec![ast::TypeConstraint { terms: vec![type_term(true, "int")] }],
+                  &typ.constraints);
+}
+
+#[test]
+fn parse_interface_union_constraint() {
+    let typ = parser("interface {\n\tconstraints.Ordered\n\tint | string\n}")
+        .parse_interface_type()
+        .unwrap();
+
+    let expected = ast::InterfaceType {
+        specs: vec![ast::MethodSpec {
+                        name: "Ordered".into(),
+                        method: ast::InnerMethodSpec::InterfaceName(ast::MaybeQualifiedIdent {
+                            package: Some("constraints".into()),
+                            name: "Ordered".into(),
+                        }),
+                    }],
+        constraints: vec![ast::TypeConstraint {
+                              terms: vec![type_term(false, "int"), type_term(false, "string")],
+                          }],
+    };
+    assert_ast_eq(&expected, &typ);
+}
+
+#[test]
+fn parse_interface_embedding_qualified_interfaces() {
+    let typ = parser("interface { io.Reader; io.Writer; Foo() error }")
+        .parse_interface_type()
+        .unwrap();
+
+    let embedded = |name: &str| {
+        ast::MethodSpec {
+            name: name.into(),
+            method: ast::InnerMethodSpec::InterfaceName(ast::MaybeQualifiedIdent {
+                package: Some("io".into()),
+                name: name.into(),
+            }),
+        }
+    };
+
+    assert_eq!(typ.specs.len(), 3);
+    assert_ast_eq(&embedded("Reader"), &typ.specs[0]);
+    assert_ast_eq(&embedded("Writer"), &typ.specs[1]);
+
+    assert_eq!(typ.specs[2].name, "Foo");
+    match typ.specs[2].method {
+        ast::InnerMethodSpec::Signature(ref sig) => {
+            assert!(sig.parameters.decls.is_empty());
+            assert_eq!(sig.result.decls.len(), 1);
+            assert_ast_eq(&plain_type("error"), &sig.result.decls[0].typ);
+        }
+        ref method => panic!("expected a method signature, got {:?}", method),
+    }
+    assert!(typ.constraints.is_empty());
+}
+
+#[test]
+fn parse_interface_embedding_unqualified_interfaces() {
+    let typ = parser("interface { Reader; Writer; Flush() error }")
+        .parse_interface_type()
+        .unwrap();
+
+    let embedded = |name: &str| {
+        ast::MethodSpec {
+            name: name.into(),
+            method: ast::InnerMethodSpec::InterfaceName(ast::MaybeQualifiedIdent {
+                package: None,
+                name: name.into(),
+            }),
+        }
+    };
+
+    assert_eq!(typ.specs.len(), 3);
+    assert_ast_eq(&embedded("Reader"), &typ.specs[0]);
+    assert_ast_eq(&embedded("Writer"), &typ.specs[1]);
+
+    assert_eq!(typ.specs[2].name, "Flush");
+    match typ.specs[2].method {
+        ast::InnerMethodSpec::Signature(ref sig) => {
+            assert!(sig.parameters.decls.is_empty());
+            assert_eq!(sig.result.decls.len(), 1);
+        }
+        ref method => panic!("expected a method signature, got {:?}", method),
+    }
+    assert!(typ.constraints.is_empty());
+
+    // A lone embedded interface, on its own line.
+    let typ = parser("interface {\n\tReader\n}").parse_interface_type().unwrap();
+    assert_ast_eq(&vec![embedded("Reader")], &typ.specs);
+}
+
+// Expressions
+
+/// Render an expression as a fully parenthesized S-expression, e.g. `((a Sub b) Sub c)`.
+fn sexpr(expr: &ast::Expr) -> String {
+    match *expr {
+        ast::Expr::Binary(ref binary) => {
+            format!("({} {:?} {})",
+                    sexpr(&binary.lhs.item),
+                    binary.op,
+                    sexpr(&binary.rhs.item))
+        }
+        ast::Expr::Unary(ref unary) => sexpr_unary(unary),
+    }
+}
+
+fn sexpr_unary(unary: &ast::UnaryExpr) -> String {
+    match *unary {
+        ast::UnaryExpr::Primary(ref primary) => sexpr_primary(primary),
+        ast::UnaryExpr::UnaryOperation(ref op) => {
+            format!("({:?} {})", op.operator, sexpr_unary(&op.operand.item))
+        }
+    }
+}
+
+fn sexpr_primary(primary: &ast::PrimaryExpr) -> String {
+    use ast::{PrimaryExpr, Operand, Literal};
+
+    match *primary {
+        PrimaryExpr::Operand(Operand::Ident(ref ident)) => {
+            match ident.package {
+                Some(ref package) => format!("{}.{}", package, ident.name),
+                None => ident.name.clone(),
+            }
+        }
+        PrimaryExpr::Operand(Operand::Lit(Literal::Basic(ref lit))) => lit.to_string(),
+        // Parentheses are already explicit in the S-expression.
+        PrimaryExpr::Operand(Operand::Expr(ref expr)) => sexpr(expr),
+        PrimaryExpr::SelectorExpr(ref sel) => {
+            format!("({} Selector {})", sexpr_primary(&sel.operand), sel.selector)
+        }
+        PrimaryExpr::Indexing(ref index) => {
+            format!("({} Index {})",
+                    sexpr_primary(&index.operand.item),
+                    sexpr(&index.index.item))
+        }
+        PrimaryExpr::Slicing(ref slice) => {
+            let bound = |bound: &Option<Spanned<ast::Expr>>| {
+                bound.as_ref().map(|b| sexpr(&b.item)).unwrap_or_else(String::new)
+            };
+            let mut bounds = format!("{}:{}",
+                                     bound(&slice.slicing.low),
+                                     bound(&slice.slicing.high));
+            if slice.slicing.max.is_some() {
+                bounds = format!("{}:{}", bounds, bound(&slice.slicing.max));
+            }
+            format!("({} Slice {})", sexpr_primary(&slice.operand.item), bounds)
+        }
+        PrimaryExpr::Conversion(ref conversion) => {
+            format!("({} Convert {})", conversion.typ.item, sexpr(&conversion.expr.item))
+        }
+        PrimaryExpr::TypeAssertion(ref assertion) => {
+            let typ = assertion.typ.as_ref().map(|t| t.item.to_string());
+            format!("({} Assert {})",
+                    sexpr_primary(&assertion.expr.item),
+                    typ.unwrap_or_else(|| "type".into()))
+        }
+        PrimaryExpr::FuncCall(ref call) => {
+            let mut s = format!("({} Call", sexpr_primary(&call.callee.item));
+            for arg in &call.args.expressions {
+                s.push(' ');
+                s.push_str(&sexpr(&arg.item));
+            }
+            if call.args.variadic {
+                s.push_str("...");
+            }
+            s.push(')');
+            s
+        }
+        ref other => format!("{:?}", other),
+    }
+}
+
+fn assert_expr_sexpr(src: &str, expect: &str) {
+    let expr = parser(src).parse_expr().unwrap();
+    assert_eq!(sexpr(&expr), expect, "parsing `{}`", src);
+}
+
+#[test]
+fn parse_binary_expr_left_associative() {
+    assert_expr_sexpr("a - b - c", "((a Sub b) Sub c)");
+    assert_expr_sexpr("a / b / c", "((a Div b) Div c)");
+    assert_expr_sexpr("a << b >> c", "((a LeftShift b) RightShift c)");
+    assert_expr_sexpr("a && b && c", "((a LogAnd b) LogAnd c)");
+    assert_expr_sexpr("a || b || c", "((a LogOr b) LogOr c)");
+    assert_expr_sexpr("a == b != c", "((a Equals b) NotEqual c)");
+}
+
+#[test]
+fn parse_binary_expr_mixed_precedence() {
+    assert_expr_sexpr("a || b && c", "(a LogOr (b LogAnd c))");
+    assert_expr_sexpr("a && b || c", "((a LogAnd b) LogOr c)");
+    assert_expr_sexpr("a + b * c - d", "((a Add (b Mul c)) Sub d)");
+    assert_expr_sexpr("a == b || c < d && e",
+                      "((a Equals b) LogOr ((c LessThan d) LogAnd e))");
+    assert_expr_sexpr("1 + 2 << 3", "(1 Add (2 LeftShift 3))");
+}
+
+#[test]
+fn parse_binary_expr_parenthesized() {
+    assert_expr_sexpr("a - (b - c)", "(a Sub (b Sub c))");
+    assert_expr_sexpr("(a || b) && c", "((a LogOr b) LogAnd c)");
+}
+
+// Basic literal re-emission
+
+fn parse_basic_lit_src(src: &str) -> ast::BasicLit {
+    parser(src).parse_basic_lit().unwrap()
+}
+
+#[test]
+fn large_int_lit_accessors() {
+    let lit = parse_basic_lit_src("42");
+    assert_eq!(lit.as_i64(), Some(42));
+    assert_eq!(lit.as_u64(), Some(42));
+
+    // Exceeds i64, but fits in u64.
+    let lit = parse_basic_lit_src("18446744073709551615");
+    assert_eq!(lit.as_i64(), None);
+    assert_eq!(lit.as_u64(), Some(u64::max_value()));
+
+    // Exceeds u64; only the arbitrary-precision value is available.
+    let lit = parse_basic_lit_src("0x10000000000000000");
+    assert_eq!(lit.as_i64(), None);
+    assert_eq!(lit.as_u64(), None);
+    let expect = BigInt::from(u64::max_value()) + BigInt::from(1);
+    assert_eq!(lit.as_big(), Some(&expect));
+
+    let lit = parse_basic_lit_src("'a'");
+    assert_eq!(lit.as_big(), None);
+    assert_eq!(lit.as_i64(), None);
+}
+
+#[test]
+fn parse_hex_float_lits() {
+    let ratio = |n: i64, d: i64| BigRational::new(BigInt::from(n), BigInt::from(d));
+
+    assert_eq!(parse_basic_lit_src("0x1p-2"), ast::BasicLit::Float(ratio(1, 4)));
+    assert_eq!(parse_basic_lit_src("0x.8p1"), ast::BasicLit::Float(ratio(1, 1)));
+    assert_eq!(parse_basic_lit_src("0x1.8p3"), ast::BasicLit::Float(ratio(12, 1)));
+    assert_eq!(parse_basic_lit_src("0X1FFFP-16"),
+               ast::BasicLit::Float(ratio(0x1fff, 0x10000)));
+    assert_eq!(parse_basic_lit_src("0x1p4i"), ast::BasicLit::Imaginary(ratio(16, 1)));
+
+    let err = parser("0x1.8").parse_basic_lit().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("hexadecimal mantissa requires a 'p' exponent"));
+
+    let err = parser("0x1p").parse_basic_lit().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("malformed float literal exponent"));
+
+    // Scaling the mantissa must neither overflow nor need a huge power of two.
+    for src in &["0x.1p-9223372036854775807", "0x1p999999999", "0x1p65537"] {
+        let err = parser(src).parse_basic_lit().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::other("float literal exponent is too large"));
+    }
+    assert!(parser("0x1p65536").parse_basic_lit().is_ok());
+
+    // The lexer never produces these, but hand-built tokens must not panic.
+    let tokens = vec![TokenAndSpan {
+                          token: Token {
+                              kind: TokenKind::Float,
+                              value: Some("0xg.p1".into()),
+                          },
+                          span: Span { start: 0, end: 6 },
+                      }];
+    let err = Parser::new(tokens.into_iter()).parse_basic_lit().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("invalid character in float literal: g"));
+
+    // Tiny exponents need many decimal digits, but must still round-trip exactly.
+    assert_basic_lit_roundtrip("0x1p-100");
+    assert_basic_lit_roundtrip("0x1.8p-1074i");
+}
+
+fn assert_basic_lit_roundtrip(src: &str) {
+    let lit = parse_basic_lit_src(src);
+    let emitted = format!("{}", lit);
+
+    assert_ast_eq(&lit, &parse_basic_lit_src(&emitted));
+}
+
+#[test]
+fn display_basic_lit() {
+    assert_eq!(format!("{}", parse_basic_lit_src("0x1F")), "31");
+    assert_eq!(format!("{}", parse_basic_lit_src("1.25e2")), "125.0");
+    assert_eq!(format!("{}", parse_basic_lit_src(".5i")), "0.5i");
+    assert_eq!(format!("{}", parse_basic_lit_src(r"'\''")), r"'\''");
+    assert_eq!(format!("{}", parse_basic_lit_src(r#"'"'"#)), r#"'"'"#);
+    assert_eq!(format!("{}", parse_basic_lit_src(r#""a\"b\n\xff""#)),
+               r#""a\"b\n\xff""#);
+    assert_eq!(format!("{}", parse_basic_lit_src(r"`a\nb`")), r#""a\\nb""#);
+}
+
+#[test]
+fn display_basic_lit_roundtrip() {
+    let srcs = ["0", "0755", "0xdeadbeef", "123456789012345678901234567890", "0.", "3.14159",
+                "1e-10", "6.02214e23", "1E6i", "2.5i", "'a'", "'本'", r"'\x00'", r"'ዤ'",
+                r"'\\'", r"'\a'", r#""""#, r#""hello, 世界""#, r#""\t\"quoted\"\x01\x7f""#,
+                r#""\xe6\x97""#, "`raw\\string`", "`multi\nline`", "1e-50", "1.5e-45i"];
+
+    for src in &srcs {
+        assert_basic_lit_roundtrip(src);
+    }
+}
+
+// Crate-level errors
+
+#[test]
+fn parse_source_reports_parse_error() {
+    let err = ::parse("package 42\n").unwrap_err();
+
+    match err {
+        ::Error::Parse(ref e) => assert_eq!(e.span, Span { start: 8, end: 10 }),
+        ref e => panic!("expected a parse error, got {:?}", e),
+    }
+    assert_eq!(format!("{}", err),
+               "parse error at 8..10: expected \"Ident\", found \"Decimal(42)\"");
+}
+
+#[test]
+fn parse_errors_point_at_the_offending_token() {
+    let src = "package main\n\nfunc main() {\n\tx := f(1 2)\n}\n";
+    let tokens = lexer::tokenize(src).unwrap();
+    let err = Parser::new(tokens.into_iter()).parse().unwrap_err();
+
+    assert_eq!(&src[err.span.start as usize..err.span.end as usize], "2");
+    let lexer = lexer::Lexer::new(src).unwrap();
+    assert_eq!(lexer.position(err.span.start),
+               Ok(::Position {
+                   row: 4,
+                   column: 11,
+               }));
+}
+
+#[test]
+fn adjacent_strs_are_a_syntax_error() {
+    let src = "package main\n\nvar s = \"a\" \"b\"\n";
+    match ::parse(src) {
+        Err(::Error::Parse(err)) => {
+            assert_eq!(&src[err.span.start as usize..err.span.end as usize], "\"b\"");
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn trailing_tokens() {
+    let mut p = parser("a + b c");
+    assert_eq!(sexpr(&p.parse_expr().unwrap()), "(a Add b)");
+    assert!(!p.is_at_eof());
+
+    let mut p = parser("a + b");
+    p.parse_expr().unwrap();
+    assert!(p.is_at_eof());
+
+    // A whole file must be consumed.
+    let src = "package main\nxyz";
+    match ::parse(src) {
+        Err(::Error::Parse(err)) => {
+            assert_eq!(err.span, Span { start: 13, end: 16 });
+            match err.kind {
+                ErrorKind::UnexpectedToken { ref found, .. } => {
+                    assert_eq!(found.kind, TokenKind::Ident)
+                }
+                ref kind => panic!("expected an unexpected token error, got {:?}", kind),
+            }
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_errors_list_expected_tokens() {
+    let err = parser("var x = 1 )").parse_top_level_decl().unwrap_err();
+    match err.kind {
+        ErrorKind::UnexpectedToken { ref expected, ref found } => {
+            assert_eq!(found.kind, TokenKind::RParen);
+            assert_eq!(expected, &[TokenKind::Semicolon]);
+        }
+        ref kind => panic!("expected an unexpected token error, got {:?}", kind),
+    }
+
+    let err = parser("x := ]").parse_simple_stmt().unwrap_err();
+    assert_eq!(err.kind.to_string(),
+               "expected one of \"Ident\", \"LParen\", \"Decimal\", \"Octal\", \"Hex\", \
+                \"Float\", \"Imaginary\", \"Rune\", \"Str\", \"StrRaw\", \"LBracket\", \"Map\", \
+                \"Struct\", \"Func\", \"Chan\", \"Interface\", \"Plus\", \"Minus\", \"Not\", \
+                \"Caret\", \"Star\", \"And\" or \"Arrow\", found \"RBracket\"");
+
+    // A primary expression can't start with a unary operator.
+    let err = parser("]").parse_primary_expr().unwrap_err();
+    match err.kind {
+        ErrorKind::UnexpectedToken { ref expected, .. } => {
+            assert!(expected.contains(&TokenKind::Chan));
+            assert!(!expected.contains(&TokenKind::Plus));
+        }
+        ref kind => panic!("expected an unexpected token error, got {:?}", kind),
+    }
+
+    let err = parser("f(a").parse_expr().unwrap_err();
+    assert_eq!(err.kind.to_string(),
+               "unexpected end of input, expected one of \"Comma\" or \"RParen\"");
+    let err = parser("T{a b}").parse_expr().unwrap_err();
+    assert_eq!(err.kind.to_string(),
+               "expected one of \"Comma\" or \"RBrace\", found \"Ident(b)\"");
+}
+
+#[test]
+fn parse_blank_assignments() {
+    match parser("_ = f()").parse_simple_stmt().unwrap() {
+        ast::SimpleStmt::Assignment(assignment) => {
+            assert_eq!(assignment.op, None);
+            assert_eq!(sexpr(&assignment.lhs[0].item), "_");
+            assert_eq!(sexpr(&assignment.rhs[0].item), "(f Call)");
+        }
+        stmt => panic!("expected an assignment, got {:?}", stmt),
+    }
+    assert_assignment_lhs("_, _ = a, b", &["_", "_"]);
+
+    // `:=` must declare at least one variable that isn't blank.
+    for src in &["_ := x", "_, _ := f()"] {
+        let err = parser(src).parse_simple_stmt().unwrap_err();
+        assert_eq!(format!("{}", err.kind), "no new variables on left side of :=");
+    }
+    let err = parser("_, _ := f()").parse_simple_stmt().unwrap_err();
+    assert_eq!(err.span, Span { start: 5, end: 7 });
+
+    // The same goes for the iteration variables of a range clause.
+    for src in &["for _ := range xs {}", "for _, _ := range xs {}"] {
+        let err = parser(src).parse_for_stmt().unwrap_err();
+        assert_eq!(format!("{}", err.kind), "no new variables on left side of :=");
+    }
+    let err = parser("for _ := range xs {}").parse_for_stmt().unwrap_err();
+    assert_eq!(err.span, Span { start: 6, end: 8 });
+    assert!(parser("for _, x := range xs {}").parse_for_stmt().is_ok());
+    assert!(parser("for _ = range xs {}").parse_for_stmt().is_ok());
+
+    match parser("_, err := f()").parse_simple_stmt().unwrap() {
+        ast::SimpleStmt::ShortVarDecl(decl) => assert_eq!(decl.lhs.len(), 2),
+        stmt => panic!("expected a short variable declaration, got {:?}", stmt),
+    }
+}
+
+fn assert_short_var_decl_rhs(src: &str, expect: &str) {
+    match parser(src).parse_simple_stmt().unwrap() {
+        ast::SimpleStmt::ShortVarDecl(decl) => {
+            assert_eq!(decl.rhs.len(), 1);
+            assert_eq!(sexpr(&decl.rhs[0].item), expect, "parsing `{}`", src);
+        }
+        stmt => panic!("expected a short variable declaration, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_short_var_decl_lists() {
+    let decl = |src: &str| match parser(src).parse_simple_stmt().unwrap() {
+        ast::SimpleStmt::ShortVarDecl(decl) => {
+            let lhs: Vec<_> = decl.lhs.iter().map(|ident| ident.item.clone()).collect();
+            let rhs: Vec<_> = decl.rhs.iter().map(|expr| sexpr(&expr.item)).collect();
+            format!("{} := {}", lhs.join(", "), rhs.join(", "))
+        }
+        stmt => panic!("expected a short variable declaration, got {:?}", stmt),
+    };
+
+    // The number of names and values needn't match; that's for a type checker to decide.
+    assert_eq!(decl("v, ok := m[k]"), "v, ok := (m Index k)");
+    assert_eq!(decl("a, b, c := f()"), "a, b, c := (f Call)");
+    assert_eq!(decl("n, err := w.Write(p)"), "n, err := (w.Write Call p)");
+    assert_eq!(decl("x, y := 1, y + 1"), "x, y := 1, (y Add 1)");
+
+    // The other comma-ok forms.
+    assert_eq!(decl("v, ok := <-ch"), "v, ok := (ChanReceive ch)");
+    assert_eq!(decl("s, ok := x.(fmt.Stringer)"), "s, ok := (x Assert fmt.Stringer)");
+    assert_assignment_lhs("v, ok = x.(T)", &["v", "ok"]);
+    assert_assignment_lhs("m[k], ok = <-ch", &["(m Index k)", "ok"]);
+
+    let err = parser("a, b.c := f()").parse_simple_stmt().unwrap_err();
+    assert_eq!(err.span, Span { start: 3, end: 6 });
+}
+
+#[test]
+fn parse_receive_expr() {
+    assert_short_var_decl_rhs("v := <-ch", "(ChanReceive ch)");
+    // Unary operators bind more tightly than any binary operator.
+    assert_short_var_decl_rhs("x := <-ch + 1", "((ChanReceive ch) Add 1)");
+    assert_short_var_decl_rhs("x := 1 * <-ch", "(1 Mul (ChanReceive ch))");
+    assert_expr_sexpr("<-<-ch", "(ChanReceive (ChanReceive ch))");
+    assert_expr_sexpr("-<-ch", "(Minus (ChanReceive ch))");
+}
+
+#[test]
+fn parse_stacked_unary_ops() {
+    assert_expr_sexpr("!-x", "(Not (Minus x))");
+    assert_expr_sexpr("*&y", "(Deref (And y))");
+    assert_expr_sexpr("-^z", "(Minus (Xor z))");
+    assert_expr_sexpr("+!<-ch", "(Plus (Not (ChanReceive ch)))");
+    assert_expr_sexpr("&*p.f", "(And (Deref p.f))");
+    // The whole operand of a binary operator is a unary expression.
+    assert_expr_sexpr("!-x == -^z", "((Not (Minus x)) Equals (Minus (Xor z)))");
+    assert_expr_sexpr("a - -b", "(a Sub (Minus b))");
+}
+
+fn assert_assignment_lhs(src: &str, expect: &[&str]) {
+    match parser(src).parse_simple_stmt().unwrap() {
+        ast::SimpleStmt::Assignment(assignment) => {
+            let lhs: Vec<_> = assignment.lhs.iter().map(|e| sexpr(&e.item)).collect();
+            assert_eq!(lhs, expect, "parsing `{}`", src);
+        }
+        stmt => panic!("expected an assignment, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_assignment_targets() {
+    assert_assignment_lhs("a[i] = x", &["(a Index i)"]);
+    assert_assignment_lhs("m[\"k\"] = v", &["(m Index \"k\")"]);
+    // Without type information, `p.field` can't be told apart from a qualified identifier.
+    assert_assignment_lhs("p.field = y", &["p.field"]);
+    assert_assignment_lhs("p.q.r += y", &["(p.q Selector r)"]);
+    assert_assignment_lhs("*ptr = z", &["(Deref ptr)"]);
+    assert_assignment_lhs("f().x, a[0][1], *p.q = 1, 2, 3",
+                          &["((f Call) Selector x)", "((a Index 0) Index 1)", "(Deref p.q)"]);
+}
+
+#[test]
+fn parse_postfix_exprs() {
+    assert_expr_sexpr("f()", "(f Call)");
+    assert_expr_sexpr("f(a, b + c,)", "(f Call a (b Add c))");
+    assert_expr_sexpr("a[i+1]", "(a Index (i Add 1))");
+    assert_expr_sexpr("a[:]", "(a Slice :)");
+    assert_expr_sexpr("a[1:]", "(a Slice 1:)");
+    assert_expr_sexpr("a[:n:m]", "(a Slice :n:m)");
+    // Postfix operators bind more tightly than unary ones.
+    assert_expr_sexpr("-a.b[0]", "(Minus (a.b Index 0))");
+
+    let err = parser("a[1::2]").parse_expr().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("middle index required in 3-index slice"));
+}
+
+#[test]
+fn parse_conversions() {
+    // Conversions to named types can't be told apart from calls yet.
+    assert_expr_sexpr("MyInt(5)", "(MyInt Call 5)");
+    assert_expr_sexpr("string(x)", "(string Call x)");
+    assert_expr_sexpr("time.Duration(n) * time.Second",
+                      "((time.Duration Call n) Mul time.Second)");
+
+    assert_expr_sexpr("[]byte(\"hi\")", "([]byte Convert \"hi\")");
+    assert_expr_sexpr("[]rune(s,)[0]", "(([]rune Convert s) Index 0)");
+    assert_expr_sexpr("map[string]int(m)", "(map[string]int Convert m)");
+    assert_expr_sexpr("chan<- int(ch)", "(chan<- int Convert ch)");
+    assert_expr_sexpr("interface{}(x)", "(interface{} Convert x)");
+    assert_expr_roundtrip("[]byte(s + \"!\")", "[]byte(s + \"!\")");
+
+    let err = parser("chan int{}").parse_expr().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("invalid composite literal type"));
+    assert_eq!(err.span, Span { start: 0, end: 8 });
+}
+
+#[test]
+fn parse_variadic_call_args() {
+    assert_expr_sexpr("append(a, b...)", "(append Call a b...)");
+    assert_expr_sexpr("f(xs...,)", "(f Call xs...)");
+    assert_expr_sexpr("append(a, b...)[i]", "((append Call a b...) Index i)");
+    assert_expr_sexpr("g(f(a...), b)", "(g Call (f Call a...) b)");
+
+    let err = parser("f(a..., b)").parse_expr().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("can only use ... with final argument in list"));
+    assert_eq!(err.span, Span { start: 8, end: 9 });
+}
+
+#[test]
+fn parse_postfix_chain_left_associative() {
+    assert_expr_sexpr("a.b.c.d", "((a.b Selector c) Selector d)");
+    assert_expr_sexpr("a.b().c[0].d",
+                      "((((a.b Call) Selector c) Index 0) Selector d)");
+    assert_expr_sexpr("f(x)(y)[1][2]", "((((f Call x) Call y) Index 1) Index 2)");
+
+    // A long chain is built iteratively, so it doesn't grow the parser's stack.
+    const LINKS: usize = 300;
+    let mut src = String::from("x");
+    for _ in 0..LINKS {
+        src.push_str(".f()[0]");
+    }
+
+    let expr = match parser(&src).parse_expr().unwrap() {
+        ast::Expr::Unary(ast::UnaryExpr::Primary(primary)) => *primary,
+        expr => panic!("expected a primary expression, got {:?}", expr),
+    };
+
+    // Walk down the left spine: index, call, selector, repeated. The innermost `x.f` is parsed
+    // as a qualified identifier rather than a selector.
+    let mut depth = 0;
+    let mut node = &expr;
+    loop {
+        node = match (depth % 3, node) {
+            (0, &ast::PrimaryExpr::Indexing(ref index)) => &index.operand.item,
+            (1, &ast::PrimaryExpr::FuncCall(ref call)) => &call.callee.item,
+            (2, &ast::PrimaryExpr::SelectorExpr(ref sel)) => &sel.operand,
+            (_, node) => {
+                assert_eq!(sexpr_primary(node), "x.f");
+                break;
+            }
+        };
+        depth += 1;
+    }
+    assert_eq!(depth, 3 * LINKS - 1);
+}
+
+fn operand(src: &str) -> ast::Operand {
+    let mut p = parser(src);
+    let operand = p.parse_operand().unwrap();
+    assert_eq!(p.token.kind, TokenKind::Eof, "`{}` wasn't fully parsed", src);
+    operand
+}
+
+#[test]
+fn parse_operands() {
+    use ast::{Operand, Literal, BasicLit, MaybeQualifiedIdent};
+
+    assert_ast_eq(&Operand::Ident(MaybeQualifiedIdent {
+                      package: None,
+                      name: "x".into(),
+                  }),
+                  &operand("x"));
+    assert_ast_eq(&Operand::Ident(MaybeQualifiedIdent {
+                      package: Some("fmt".into()),
+                      name: "Println".into(),
+                  }),
+                  &operand("fmt.Println"));
+    assert_ast_eq(&Operand::Lit(Literal::Basic(BasicLit::Rune('a'))), &operand("'a'"));
+    assert_ast_eq(&Operand::Lit(Literal::Basic(BasicLit::Str(b"s".to_vec()))),
+                  &operand("`s`"));
+
+    match operand("[]int{1}") {
+        Operand::Lit(Literal::Composite(lit)) => assert_eq!(lit.val.elems.len(), 1),
+        operand => panic!("expected a composite literal, got {:?}", operand),
+    }
+
+    match operand("func(a int) int { return a }") {
+        Operand::Lit(Literal::Func(lit)) => {
+            assert_eq!(lit.signature.parameters.decls.len(), 1);
+            assert_eq!(lit.body.0.len(), 1);
+        }
+        operand => panic!("expected a function literal, got {:?}", operand),
+    }
+
+    match operand("(1 + x)") {
+        Operand::Expr(expr) => assert_eq!(sexpr(&expr), "(1 Add x)"),
+        operand => panic!("expected a parenthesized expression, got {:?}", operand),
+    }
+
+    // A type assertion isn't part of the operand.
+    let mut p = parser("x.(T)");
+    p.parse_operand().unwrap();
+    assert_eq!(p.token.kind, TokenKind::Dot);
+}
+
+fn composite_lit(src: &str) -> ast::CompositeLit {
+    use ast::{Expr, UnaryExpr, PrimaryExpr, Operand, Literal};
+
+    match parser(src).parse_expr().unwrap() {
+        Expr::Unary(UnaryExpr::Primary(primary)) => {
+            match *primary {
+                PrimaryExpr::Operand(Operand::Lit(Literal::Composite(lit))) => lit,
+                expr => panic!("expected a composite literal, got {:?}", expr),
+            }
+        }
+        expr => panic!("expected a composite literal, got {:?}", expr),
+    }
+}
+
+/// Render the elements of a literal value, with `{...}` for elements whose type was elided.
+fn literal_value_sexpr(val: &ast::LiteralValue) -> String {
+    let elems: Vec<_> = val.elems
+        .iter()
+        .map(|e| {
+            let elem = match e.elem.item {
+                ast::Elem::Expr(ref expr) => sexpr(expr),
+                ast::Elem::LiteralValue(ref val) => literal_value_sexpr(val),
+            };
+            match e.key {
+                Some(Spanned { item: ast::Key::Expr(ref expr), .. }) => {
+                    format!("{}: {}", sexpr(expr), elem)
+                }
+                Some(Spanned { item: ref key, .. }) => format!("{:?}: {}", key, elem),
+                None => elem,
+            }
+        })
+        .collect();
+    format!("{{{}}}", elems.join(", "))
+}
+
+fn slice_of(element_type: ast::Type) -> ast::Type {
+    ast::Type::Literal(Box::new(ast::TypeLiteral::Slice(ast::SliceType {
+        element_type: element_type,
+    })))
+}
+
+#[test]
+fn parse_nested_slice_lit_with_elided_types() {
+    let lit = composite_lit("[][]int{{1, 2}, {3, 4}}");
+
+    assert_eq!(lit.typ.span, Span { start: 0, end: 7 });
+    assert_ast_eq(&ast::LiteralType::Slice(ast::SliceType {
+                      element_type: slice_of(plain_type("int")),
+                  }),
+                  &lit.typ.item);
+
+    assert_eq!(literal_value_sexpr(&lit.val), "{{1, 2}, {3, 4}}");
+
+    let spans: Vec<_> = lit.val.elems.iter().map(|e| e.elem.span).collect();
+    assert_eq!(spans, vec![Span { start: 8, end: 14 }, Span { start: 16, end: 22 }]);
+}
+
+#[test]
+fn parse_map_lit_with_elided_value_type() {
+    let lit = composite_lit("map[string][]int{\"a\": {1}, \"b\": {},}");
+
+    assert_ast_eq(&ast::LiteralType::Map(ast::MapType {
+                      key_type: plain_type("string"),
+                      element_type: slice_of(plain_type("int")),
+                  }),
+                  &lit.typ.item);
+    assert_eq!(literal_value_sexpr(&lit.val), "{\"a\": {1}, \"b\": {}}");
+
+    let elem = &lit.val.elems[0];
+    assert_eq!(elem.key.as_ref().unwrap().span, Span { start: 17, end: 20 });
+    assert_eq!(elem.elem.span, Span { start: 22, end: 25 });
+}
+
+#[test]
+fn parse_array_lit_with_computed_keys() {
+    let lit = composite_lit("[N]int{0: x, N-1: y}");
+
+    match lit.typ.item {
+        ast::LiteralType::Array(ref typ) => assert_eq!(sexpr(&typ.len), "N"),
+        ref typ => panic!("expected an array type, got {:?}", typ),
+    }
+    assert_eq!(literal_value_sexpr(&lit.val), "{0: x, (N Sub 1): y}");
+
+    // The computed key is a full binary expression spanning `N-1`.
+    let key = lit.val.elems[1].key.as_ref().unwrap();
+    assert_eq!(key.span, Span { start: 13, end: 16 });
+    match key.item {
+        ast::Key::Expr(ast::Expr::Binary(_)) => {}
+        ref key => panic!("expected a binary expression key, got {:?}", key),
+    }
+}
+
+#[test]
+fn parse_named_struct_lits() {
+    let lit = composite_lit("Point{1, 2}");
+    assert_eq!(lit.typ.span, Span { start: 0, end: 5 });
+    assert_ast_eq(&ast::LiteralType::Type(ast::MaybeQualifiedIdent {
+                      package: None,
+                      name: "Point".into(),
+                  }),
+                  &lit.typ.item);
+    assert_eq!(literal_value_sexpr(&lit.val), "{1, 2}");
+    assert!(lit.val.elems.iter().all(|elem| elem.key.is_none()));
+
+    let lit = composite_lit("image.Point{X: 1, Y: 2}");
+    assert_eq!(lit.typ.span, Span { start: 0, end: 11 });
+    assert_eq!(literal_value_sexpr(&lit.val), "{X: 1, Y: 2}");
+
+    // Keyed and positional elements can't be mixed in a struct literal.
+    let mixed = ErrorKind::other("mixture of field:value and value elements in struct literal");
+    let err = parser("struct{ a, b int }{1, b: 2}").parse_expr().unwrap_err();
+    assert_eq!(err.kind, mixed);
+    assert_eq!(err.span, Span { start: 22, end: 23 });
+
+    let err = parser("struct{ a, b int }{a: 1, 2}").parse_expr().unwrap_err();
+    assert_eq!(err.kind, mixed);
+
+    // Array and slice literals may mix them, and a named type could be either, even if its keys
+    // are identifiers (such as constants used as array indices).
+    assert_eq!(literal_value_sexpr(&composite_lit("[]int{1, 5: 2}").val), "{1, 5: 2}");
+    assert_eq!(literal_value_sexpr(&composite_lit("Ints{1, 5: 2}").val), "{1, 5: 2}");
+    assert_eq!(literal_value_sexpr(&composite_lit("Arr{N: 1, 2}").val), "{N: 1, 2}");
+}
+
+#[test]
+fn composite_lits_of_named_types_in_headers() {
+    // The `{` after a type name in a header opens the body, not a composite literal.
+    let stmt = parser("if x == T {}").parse_if_stmt().unwrap();
+    assert_eq!(sexpr(&stmt.condition.item), "(x Equals T)");
+
+    match for_header("for _, p := range points {}") {
+        ast::ForHeader::RangeClause(clause) => assert_eq!(sexpr(&clause.expr.item), "points"),
+        header => panic!("expected a range clause, got {:?}", header),
+    }
+
+    // Unless it is nested in parentheses, brackets or a block.
+    let stmt = parser("if x == (T{}) {}").parse_if_stmt().unwrap();
+    assert_eq!(stmt.condition.item.to_string(), "x == (T{})");
+    let stmt = parser("if f(T{1}) {}").parse_if_stmt().unwrap();
+    assert_eq!(stmt.condition.item.to_string(), "f(T{1})");
+    let stmt = parser("if m[T{}] {}").parse_if_stmt().unwrap();
+    assert_eq!(stmt.condition.item.to_string(), "m[T{}]");
+    let stmt = parser("if x := []T{T{}}; f(func() { y := T{} }) { z := T{} }")
+        .parse_if_stmt()
+        .unwrap();
+    assert_eq!(stmt.to_string(),
+               "if x := []T{T{}}; f(func() { y := T{} }) { z := T{} }");
+
+    let block = parser("{ p := Point{1, 2}; switch p {} }").parse_block().unwrap();
+    assert_eq!(block.to_string(), "{ p := Point{1, 2}; switch p {} }");
+}
+
+#[test]
+fn composite_lits_in_returns_and_call_args() {
+    let src = "func f() Point {\n\
+               \tif ok {\n\
+               \t\treturn Point{1, 2}\n\
+               \t}\n\
+               \tg(Config{Debug: true}, &Config{})\n\
+               \treturn Point{X: h(Point{}).X}\n\
+               }";
+    let decl = parser(src).parse_func_decl().unwrap();
+    let stmts: Vec<_> = decl.body.unwrap().0.iter().map(|s| s.item.to_string()).collect();
+    assert_eq!(stmts,
+               ["if ok { return Point{1, 2} }",
+                "g(Config{Debug: true}, &Config{})",
+                "return Point{X: h(Point{}).X}"]);
+
+    // The restriction still holds in the header of a control clause.
+    let stmt = parser("switch x == T {}").parse_switch_stmt().unwrap();
+    assert_eq!(stmt.to_string(), "switch x == T {}");
+    match for_header("for i < N {}") {
+        ast::ForHeader::Condition(cond) => assert_eq!(sexpr(&cond), "(i LessThan N)"),
+        header => panic!("expected a condition, got {:?}", header),
+    }
+}
+
+// Statements
+
+#[test]
+fn parse_label_at_block_end() {
+    let stmt = parser("for { Done: }").parse_for_stmt().unwrap();
+
+    let expected = ast::ForStmt {
+        header: ast::ForHeader::ForClause(ast::ForClause {
+            init: None,
+            condition: None,
+            post: None,
+        }),
+        body: ast::Block(vec![Spanned::new(Span { start: 6, end: 11 },
+                                           ast::Statement::Labeled(ast::LabeledStmt {
+            label: Spanned::new(Span { start: 6, end: 10 }, "Done".into()),
+            statement: Box::new(Spanned::new(Span { start: 12, end: 12 },
+                                             ast::Statement::Empty(ast::EmptyStmt))),
+        }))]),
+    };
+    assert_ast_eq(&expected, &stmt);
+}
+
+#[test]
+fn parse_label_after_statement() {
+    let block = parser("{ x := 1; End: }").parse_block().unwrap();
+
+    assert_eq!(block.0.len(), 2);
+    match block.0[1].item {
+        ast::Statement::Labeled(ref stmt) => {
+            assert_eq!(stmt.label.item, "End");
+            assert_eq!(stmt.statement.item, ast::Statement::Empty(ast::EmptyStmt));
+        }
+        ref stmt => panic!("expected a labeled statement, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_label_before_semicolon() {
+    let block = parser("{ L: ; x++ }").parse_block().unwrap();
+
+    assert_eq!(block.0.len(), 2);
+    match block.0[0].item {
+        ast::Statement::Labeled(ref stmt) => {
+            assert_eq!(stmt.label.item, "L");
+            assert_eq!(stmt.statement.item, ast::Statement::Empty(ast::EmptyStmt));
+        }
+        ref stmt => panic!("expected a labeled statement, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_label_before_non_statement() {
+    let err = parser("{ L: ) }").parse_block().unwrap_err();
+    assert_eq!(err.kind,
+               ErrorKind::unexpected_token(Vec::new(),
+                                           Token {
+                                               kind: TokenKind::RParen,
+                                               value: None,
+                                           }));
+    assert_eq!(err.span, Span { start: 5, end: 6 });
+}
+
+#[test]
+fn parse_goto_forward_reference() {
+    let decl = parser("func f() { goto End; x := 1; End: }").parse_func_decl().unwrap();
+    let body = decl.body.unwrap();
+
+    assert_eq!(body.0.len(), 3);
+    match body.0[0].item {
+        ast::Statement::Goto(ref stmt) => assert_eq!(stmt.label.item, "End"),
+        ref stmt => panic!("expected a goto statement, got {:?}", stmt),
+    }
+    match body.0[2].item {
+        ast::Statement::Labeled(ref stmt) => assert_eq!(stmt.label.item, "End"),
+        ref stmt => panic!("expected a labeled statement, got {:?}", stmt),
+    }
+}
+
+fn ident_primary(name: &str) -> ast::PrimaryExpr {
+    ast::PrimaryExpr::Operand(ast::Operand::Ident(ast::MaybeQualifiedIdent {
+        package: None,
+        name: name.into(),
+    }))
+}
+
+#[test]
+fn parse_predeclared_identifiers() {
+    // `nil`, `true` and `false` are predeclared identifiers rather than keywords, and may even be
+    // shadowed.
+    let ident = |name: &str| {
+        ast::Expr::Unary(ast::UnaryExpr::Primary(Box::new(ident_primary(name))))
+    };
+
+    for name in &["nil", "true", "false"] {
+        let src = format!("x := {}", name);
+        match parser(&src).parse_simple_stmt().unwrap() {
+            ast::SimpleStmt::ShortVarDecl(decl) => {
+                let rhs: Vec<_> = decl.rhs.into_iter().map(|expr| expr.item).collect();
+                assert_eq!(rhs, vec![ident(name)]);
+            }
+            stmt => panic!("expected a short variable declaration, got {:?}", stmt),
+        }
+    }
+
+    let stmt = parser("return false").parse_return_stmt().unwrap();
+    assert_eq!(stmt.exprs, vec![Spanned::new(Span { start: 7, end: 12 }, ident("false"))]);
+
+    let decl = parser("true := 0").parse_simple_stmt().unwrap();
+    assert_eq!(decl.to_string(), "true := 0");
+}
+
+fn type_switch(src: &str) -> ast::TypeSwitchStmt {
+    match parser(src).parse_switch_stmt().unwrap() {
+        ast::SwitchStmt::Type(stmt) => stmt,
+        stmt => panic!("expected a type switch, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_bound_type_switch() {
+    let stmt = type_switch("switch v := x.(type) {\ncase int, string:\n\treturn v\ncase nil:\n\
+                            default:\n}");
+
+    assert_eq!(stmt.init, None);
+    assert_eq!(stmt.binding, Some(Spanned::new(Span { start: 7, end: 8 }, "v".into())));
+    assert_eq!(stmt.expr, Spanned::new(Span { start: 12, end: 13 }, ident_primary("x")));
+
+    let types: Vec<_> = stmt.clauses
+        .iter()
+        .map(|clause| {
+            clause.types.as_ref().map(|types| types.iter().map(|t| t.item.clone()).collect())
+        })
+        .collect();
+    assert_ast_eq(&vec![Some(vec![plain_type("int"), plain_type("string")]),
+                        Some(vec![plain_type("nil")]),
+                        None],
+                  &types);
+
+    let bodies: Vec<_> = stmt.clauses.iter().map(|clause| clause.body.len()).collect();
+    assert_eq!(bodies, vec![1, 0, 0]);
+}
+
+#[test]
+fn parse_unbound_type_switch() {
+    let stmt = type_switch("switch a.b.c.(type) { default: }");
+
+    assert_eq!(stmt.binding, None);
+    assert_ast_eq(&ast::PrimaryExpr::SelectorExpr(ast::SelectorExpr {
+                      operand: Box::new(ast::PrimaryExpr::Operand(ast::Operand::Ident(
+                          ast::MaybeQualifiedIdent {
+                              package: Some("a".into()),
+                              name: "b".into(),
+                          }))),
+                      selector: "c".into(),
+                  }),
+                  &stmt.expr.item);
+    assert_eq!(stmt.clauses.len(), 1);
+    assert_eq!(stmt.clauses[0].types, None);
+}
+
+#[test]
+fn parse_type_switch_with_init() {
+    let stmt = type_switch("switch y := 1; v := y.(type) {}");
+
+    assert!(stmt.init.is_some());
+    assert_eq!(stmt.binding.unwrap().item, "v");
+    assert!(stmt.clauses.is_empty());
+}
+
+#[test]
+fn reject_misplaced_type_switch_guards() {
+    let kind = ErrorKind::other("use of .(type) outside type switch");
+
+    let err = parser("y := x.(type)").parse_simple_stmt().unwrap_err();
+    assert_eq!(err.kind, kind);
+    assert_eq!(err.span, Span { start: 5, end: 13 });
+
+    let err = parser("f(x.(type))").parse_expr().unwrap_err();
+    assert_eq!(err.kind, kind);
+
+    for src in &["switch f(x.(type)) {}",
+                 "switch x.(type) + 1 {}",
+                 "switch v := x.(type); v {}",
+                 "switch f(x.(type)).(type) {}",
+                 "switch func() { y := x.(type) }; z.(type) {}"] {
+        let err = parser(src).parse_switch_stmt().unwrap_err();
+        assert_eq!(err.kind, kind, "{}", src);
+    }
+
+    // The guard of a switch nested in a header is still fine.
+    let src = "switch func() { switch x.(type) {} }; y.(type) {}";
+    assert!(parser(src).parse_switch_stmt().is_ok());
+}
+
+#[test]
+fn parse_expr_switch() {
+    match parser("switch x { case 1, 2: y++; default: }").parse_switch_stmt().unwrap() {
+        ast::SwitchStmt::Expr(stmt) => {
+            assert_eq!(sexpr(&stmt.expr.unwrap().item), "x");
+            assert_eq!(stmt.clauses.len(), 2);
+            assert_eq!(stmt.clauses[0].exprs.as_ref().map(|exprs| exprs.len()), Some(2));
+            assert_eq!(stmt.clauses[0].body.len(), 1);
+            assert_eq!(stmt.clauses[1].exprs, None);
+        }
+        stmt => panic!("expected an expression switch, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_case_expr_lists() {
+    let src = "switch x { case 1, 2, 3: foo(); default: bar() }";
+    match parser(src).parse_switch_stmt().unwrap() {
+        ast::SwitchStmt::Expr(stmt) => {
+            let exprs: Vec<_> = stmt.clauses[0]
+                .exprs
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|expr| sexpr(&expr.item))
+                .collect();
+            assert_eq!(exprs, ["1", "2", "3"]);
+            assert_eq!(stmt.clauses[0].body.len(), 1);
+
+            assert_eq!(stmt.clauses[1].exprs, None);
+            assert_eq!(stmt.clauses[1].body.len(), 1);
+        }
+        stmt => panic!("expected an expression switch, got {:?}", stmt),
+    }
+
+    // A case needs at least one expression.
+    assert!(parser("switch x { case: }").parse_switch_stmt().is_err());
+}
+
+fn for_header(src: &str) -> ast::ForHeader {
+    parser(src).parse_for_stmt().unwrap().header
+}
+
+#[test]
+fn for_post_stmt_cannot_declare() {
+    match for_header("for i := 0; i < 10; i++ {}") {
+        ast::ForHeader::ForClause(clause) => {
+            assert_eq!(clause.post.unwrap().to_string(), "i++");
+        }
+        header => panic!("expected a for clause, got {:?}", header),
+    }
+
+    let err = parser("for i := 0; ; j := 1 {}").parse_for_stmt().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("cannot declare in post statement of for loop"));
+    assert_eq!(err.span, Span { start: 14, end: 20 });
+}
+
+#[test]
+fn parse_range_over_int() {
+    match for_header("for i := range 10 {}") {
+        ast::ForHeader::RangeClause(clause) => {
+            assert_eq!(clause.iter_vars,
+                       ast::IterVars::Idents(vec![Spanned::new(Span { start: 4, end: 5 },
+                                                               "i".into())]));
+            assert_eq!(clause.expr.span, Span { start: 15, end: 17 });
+            assert_eq!(sexpr(&clause.expr.item), "10");
+        }
+        header => panic!("expected a range clause, got {:?}", header),
+    }
+}
+
+#[test]
+fn parse_range_clauses() {
+    match for_header("for k, v = range m {}") {
+        ast::ForHeader::RangeClause(clause) => {
+            match clause.iter_vars {
+                ast::IterVars::Exprs(ref exprs) => assert_eq!(exprs.len(), 2),
+                ref vars => panic!("expected assigned iteration variables, got {:?}", vars),
+            }
+            assert_eq!(sexpr(&clause.expr.item), "m");
+        }
+        header => panic!("expected a range clause, got {:?}", header),
+    }
+
+    match for_header("for range n {}") {
+        ast::ForHeader::RangeClause(clause) => {
+            assert_eq!(clause.iter_vars, ast::IterVars::Exprs(Vec::new()));
+        }
+        header => panic!("expected a range clause, got {:?}", header),
+    }
+}
+
+#[test]
+fn parse_range_clause_targets() {
+    match for_header("for i := range xs {}") {
+        ast::ForHeader::RangeClause(clause) => {
+            assert_eq!(clause.iter_vars,
+                       ast::IterVars::Idents(vec![Spanned::new(Span { start: 4, end: 5 },
+                                                               "i".into())]));
+        }
+        header => panic!("expected a range clause, got {:?}", header),
+    }
+
+    match for_header("for arr[i], m[k] = range pairs {}") {
+        ast::ForHeader::RangeClause(clause) => {
+            match clause.iter_vars {
+                ast::IterVars::Exprs(ref exprs) => {
+                    let exprs: Vec<_> = exprs.iter().map(|e| sexpr(&e.item)).collect();
+                    assert_eq!(exprs, ["(arr Index i)", "(m Index k)"]);
+                }
+                ref vars => panic!("expected assigned iteration variables, got {:?}", vars),
+            }
+            assert_eq!(sexpr(&clause.expr.item), "pairs");
+        }
+        header => panic!("expected a range clause, got {:?}", header),
+    }
+
+    // Only identifiers can be declared.
+    assert!(parser("for arr[i] := range xs {}").parse_for_stmt().is_err());
+}
+
+#[test]
+fn parse_for_condition_and_clause() {
+    match for_header("for a < b {}") {
+        ast::ForHeader::Condition(expr) => assert_eq!(sexpr(&expr), "(a LessThan b)"),
+        header => panic!("expected a condition, got {:?}", header),
+    }
+
+    match for_header("for i := 0; i < n; i++ {}") {
+        ast::ForHeader::ForClause(clause) => {
+            assert!(clause.init.is_some());
+            assert_eq!(sexpr(&clause.condition.unwrap()), "(i LessThan n)");
+            assert!(clause.post.is_some());
+        }
+        header => panic!("expected a for clause, got {:?}", header),
+    }
+
+    match for_header("for ; ; {}") {
+        ast::ForHeader::ForClause(clause) => {
+            assert_eq!(clause,
+                       ast::ForClause {
+                           init: None,
+                           condition: None,
+                           post: None,
+                       });
+        }
+        header => panic!("expected a for clause, got {:?}", header),
+    }
+}
+
+#[test]
+fn parse_go_and_defer_method_calls() {
+    let block = parser("{\n\tgo server.Serve(listener)\n\tdefer f.Close()\n\tgo a.b().c(1)\n}")
+        .parse_block()
+        .unwrap();
+
+    let calls: Vec<_> = block.0
+        .iter()
+        .map(|stmt| match stmt.item {
+            ast::Statement::Go(ref stmt) => format!("go {}", sexpr(&stmt.call.item)),
+            ast::Statement::Defer(ref stmt) => format!("defer {}", sexpr(&stmt.call.item)),
+            ref stmt => panic!("expected a go or defer statement, got {:?}", stmt),
+        })
+        .collect();
+    assert_eq!(calls,
+               ["go (server.Serve Call listener)",
+                "defer (f.Close Call)",
+                "go (((a.b Call) Selector c) Call 1)"]);
+
+    let err = parser("go x + 1").parse_go_stmt().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("expression in go must be function call"));
+    assert_eq!(err.span, Span { start: 3, end: 8 });
+    let err = parser("defer (f())").parse_defer_stmt().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("expression in defer must be function call"));
+}
+
+#[test]
+fn parse_deferred_closure_call() {
+    let block = parser("{\n\tdefer func() {\n\t\trecover()\n\t}()\n}").parse_block().unwrap();
+    assert_eq!(block.0.len(), 1);
+
+    let call = match block.0[0].item {
+        ast::Statement::Defer(ref stmt) => {
+            match stmt.call.item {
+                ast::Expr::Unary(ast::UnaryExpr::Primary(ref primary)) => {
+                    match **primary {
+                        ast::PrimaryExpr::FuncCall(ref call) => call.clone(),
+                        ref expr => panic!("expected a call, got {:?}", expr),
+                    }
+                }
+                ref expr => panic!("expected a call, got {:?}", expr),
+            }
+        }
+        ref stmt => panic!("expected a defer statement, got {:?}", stmt),
+    };
+
+    assert!(call.args.expressions.is_empty());
+    assert_eq!(call.callee.span, Span { start: 9, end: 32 });
+
+    match call.callee.item {
+        ast::PrimaryExpr::Operand(ast::Operand::Lit(ast::Literal::Func(ref lit))) => {
+            assert!(lit.signature.parameters.decls.is_empty());
+            assert!(lit.signature.result.decls.is_empty());
+            assert_eq!(lit.body.0.len(), 1);
+            match lit.body.0[0].item {
+                ast::Statement::Simple(ast::SimpleStmt::Expr(ref expr)) => {
+                    assert_eq!(sexpr(&expr.item), "(recover Call)");
+                }
+                ref stmt => panic!("expected an expression statement, got {:?}", stmt),
+            }
+        }
+        ref callee => panic!("expected a function literal, got {:?}", callee),
+    }
+}
+
+#[test]
+fn parse_if_stmts() {
+    let stmt = parser("if v := f(); v > 0 {\n} else if v < 0 {\n\treturn v\n} else {\n}")
+        .parse_if_stmt()
+        .unwrap();
+
+    assert!(stmt.before_stmt.is_some());
+    assert_eq!(sexpr(&stmt.condition.item), "(v GreaterThan 0)");
+    assert!(stmt.block.0.is_empty());
+
+    match stmt.opt_else.map(|e| *e) {
+        Some(ast::Else::If(stmt)) => {
+            assert_eq!(stmt.before_stmt, None);
+            assert_eq!(sexpr(&stmt.condition.item), "(v LessThan 0)");
+            assert_eq!(stmt.block.0.len(), 1);
+            assert_eq!(stmt.opt_else.map(|e| *e), Some(ast::Else::Block(ast::Block(vec![]))));
+        }
+        opt_else => panic!("expected an else-if, got {:?}", opt_else),
+    }
+
+    let err = parser("if x := 1 {}").parse_if_stmt().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("expected condition"));
+}
+
+#[test]
+fn parse_init_stmts_in_headers() {
+    let stmt = parser("if x := f(); x > 0 {}").parse_if_stmt().unwrap();
+    assert_eq!(stmt.before_stmt.unwrap().to_string(), "x := f()");
+    assert_eq!(sexpr(&stmt.condition.item), "(x GreaterThan 0)");
+
+    match parser("switch y := g(); y {}").parse_switch_stmt().unwrap() {
+        ast::SwitchStmt::Expr(stmt) => {
+            assert_eq!(stmt.init.unwrap().to_string(), "y := g()");
+            assert_eq!(sexpr(&stmt.expr.unwrap().item), "y");
+        }
+        stmt => panic!("expected an expression switch, got {:?}", stmt),
+    }
+
+    match for_header("for i := 0; i < n; i++ {}") {
+        ast::ForHeader::ForClause(clause) => {
+            assert_eq!(clause.init.unwrap().to_string(), "i := 0");
+        }
+        header => panic!("expected a for clause, got {:?}", header),
+    }
+
+    // Any simple statement may be used, not just a short variable declaration.
+    let stmt = parser("if x, y = y, x; x {}").parse_if_stmt().unwrap();
+    assert_eq!(stmt.before_stmt.unwrap().to_string(), "x, y = y, x");
+
+    match parser("switch ch <- v; {}").parse_switch_stmt().unwrap() {
+        ast::SwitchStmt::Expr(stmt) => {
+            assert_eq!(stmt.init.unwrap().to_string(), "ch <- v");
+            assert_eq!(stmt.expr, None);
+        }
+        stmt => panic!("expected an expression switch, got {:?}", stmt),
+    }
+
+    let err = parser("if {}").parse_if_stmt().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("expected condition"));
+}
+
+#[test]
+fn parse_fallthrough_stmts() {
+    match parser("switch { case true: fallthrough; default: }").parse_switch_stmt().unwrap() {
+        ast::SwitchStmt::Expr(stmt) => {
+            assert_eq!(stmt.clauses.len(), 2);
+            assert_eq!(stmt.clauses[0].body.len(), 1);
+            assert_eq!(stmt.clauses[0].body[0].item,
+                       ast::Statement::Fallthrough(ast::FallthroughStmt));
+        }
+        stmt => panic!("expected an expression switch, got {:?}", stmt),
+    }
+
+    let out_of_place = ErrorKind::other("fallthrough statement out of place");
+
+    let err = parse_err("package p\n\nfunc f() {\n\tfallthrough\n}");
+    assert_eq!(err.kind, out_of_place);
+
+    // It must be the last statement of the clause, and not nested in another statement.
+    let err = parser("switch { case true: fallthrough; f(); default: }")
+        .parse_switch_stmt()
+        .unwrap_err();
+    assert_eq!(err.kind, out_of_place);
+
+    let err = parser("switch { case true: if x { fallthrough }; default: }")
+        .parse_switch_stmt()
+        .unwrap_err();
+    assert_eq!(err.kind, out_of_place);
+
+    // Type switches don't allow it at all.
+    let err = parser("switch x.(type) { case int: fallthrough; default: }")
+        .parse_switch_stmt()
+        .unwrap_err();
+    assert_eq!(err.kind, out_of_place);
+
+    let err = parser("switch { case true: fallthrough }").parse_switch_stmt().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("cannot fallthrough final case in switch"));
+    assert_eq!(err.span, Span { start: 20, end: 31 });
+}
+
+#[test]
+fn parse_nested_blocks() {
+    let file = ::parse("package p\n\nfunc f() { { ; } }\n").unwrap();
+
+    let body = match file.top_level_decls[0].item {
+        ast::TopLevelDecl::Func(ref decl) => decl.body.clone().unwrap(),
+        ref decl => panic!("expected a function declaration, got {:?}", decl),
+    };
+    assert_eq!(body.0.len(), 1);
+    assert_eq!(body.0[0].item, ast::Statement::Block(ast::Block(vec![])));
+
+    // Each block keeps its own statements, so a later pass can give each a scope.
+    let block = parser("{ x := 1; { x := 2; { x++ } } }").parse_block().unwrap();
+    assert_eq!(block.to_string(), "{ x := 1; { x := 2; { x++ } } }");
+
+    match block.0[1].item {
+        ast::Statement::Block(ref inner) => {
+            assert_eq!(inner.0.len(), 2);
+            match inner.0[1].item {
+                ast::Statement::Block(ref innermost) => assert_eq!(innermost.0.len(), 1),
+                ref stmt => panic!("expected a block, got {:?}", stmt),
+            }
+        }
+        ref stmt => panic!("expected a block, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_statements_after_blocks() {
+    // The newline after the closing brace of the `if` inserts a semicolon.
+    let block = parser("{ if x {}\n\tf() }").parse_block().unwrap();
+    assert_eq!(block.0.len(), 2);
+
+    let block = parser("{ for {}; {}\n\tswitch {}; f() }").parse_block().unwrap();
+    assert_eq!(block.0.len(), 4);
+
+    // Stray semicolons are empty statements.
+    let block = parser("{ ; f();; g(); }").parse_block().unwrap();
+    assert_eq!(block.0.len(), 2);
+
+    // Like Go, a semicolon is required between statements on the same line, even after a block.
+    let err = parser("{ if x {} f() }").parse_block().unwrap_err();
+    match err.kind {
+        ErrorKind::UnexpectedToken { ref expected, .. } => {
+            assert_eq!(*expected, vec![TokenKind::Semicolon]);
+        }
+        ref kind => panic!("expected an unexpected token error, got {:?}", kind),
+    }
+}
+
+#[test]
+fn statement_spans() {
+    let block = parser("{ x := a + b*c; y++ }").parse_block().unwrap();
+
+    let spans: Vec<_> = block.0.iter().map(|stmt| stmt.span).collect();
+    assert_eq!(spans,
+               vec![Span { start: 2, end: 14 }, Span { start: 16, end: 19 }]);
+
+    // The expression on the right-hand side spans its full text.
+    match block.0[0].item {
+        ast::Statement::Simple(ast::SimpleStmt::ShortVarDecl(ref decl)) => {
+            assert_eq!(decl.rhs[0].span, Span { start: 7, end: 14 });
+        }
+        ref stmt => panic!("expected a short variable declaration, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn top_level_decl_spans() {
+    let src = "package p
+
+func f() {}
+var x, y int
+";
+    let file = ::parse(src).unwrap();
+
+    let texts: Vec<_> = file.top_level_decls
+        .iter()
+        .map(|decl| &src[decl.span.start as usize..decl.span.end as usize])
+        .collect();
+    assert_eq!(texts, vec!["func f() {}", "var x, y int"]);
+}
+
+// Source files
+
+#[test]
+fn parse_multiple_top_level_decls() {
+    let src = r#"package main
+
+// Answer is the answer.
+const Answer = 42
+
+var (
+	x, y int
+	z = 1 // trailing comment
+)
+
+
+func f() {}
+
+/* g does nothing. */
+func g(a int) {
+	a++
+}
+"#;
+    let file = ::parse(src).unwrap();
+
+    let summary: Vec<_> = file.top_level_decls
+        .iter()
+        .map(|decl| match decl.item {
+            ast::TopLevelDecl::Statement(ast::DeclStmt::Const(ref decl)) => {
+                format!("const({})", decl.specs.len())
+            }
+            ast::TopLevelDecl::Statement(ast::DeclStmt::VarDecl(ref decl)) => {
+                format!("var({})", decl.specs.len())
+            }
+            ast::TopLevelDecl::Func(ref decl) => format!("func {}", decl.name.item),
+            ref other => panic!("unexpected declaration: {:?}", other),
+        })
+        .collect();
+    assert_eq!(summary, vec!["const(1)", "var(2)", "func f", "func g"]);
+}
+
+#[test]
+fn parse_const_and_type_decls() {
+    let decl = parser("const (\n\ta, b int = 1, 2\n\tc\n)").parse_const_decl().unwrap();
+    assert_eq!(decl.specs.len(), 2);
+    assert_eq!(decl.specs[0].item.inner.as_ref().unwrap().typ, Some(plain_type("int")));
+    assert_eq!(decl.specs[0].item.inner.as_ref().unwrap().exprs.len(), 2);
+    assert_eq!(decl.specs[1].item.inner, None);
+
+    let decl = parser("type Celsius float64").parse_type_decl().unwrap();
+    assert_eq!(decl.specs.len(), 1);
+    assert_eq!(decl.specs[0].item.ident.item, "Celsius");
+    assert_eq!(decl.specs[0].item.typ.item, plain_type("float64"));
+    assert!(!decl.specs[0].item.is_alias);
+}
+
+#[test]
+fn parse_type_aliases() {
+    let decl = parser("type Celsius = float64").parse_type_decl().unwrap();
+    assert_eq!(decl.specs[0].item.ident.item, "Celsius");
+    assert_eq!(decl.specs[0].item.typ.item, plain_type("float64"));
+    assert!(decl.specs[0].item.is_alias);
+
+    let decl = parser("type (\n\tA = []int\n\tB A\n)").parse_type_decl().unwrap();
+    let aliases: Vec<_> = decl.specs.iter().map(|spec| spec.item.is_alias).collect();
+    assert_eq!(aliases, [true, false]);
+    assert_eq!(ast::DeclStmt::TypeDecl(decl).to_string(), "type (A = []int; B A)");
+}
+
+#[test]
+fn iota_restarts_in_each_const_decl() {
+    let src = "package p\n\nconst (\n\ta = iota\n\tb\n\tc\n)\n\nconst (\n\td = iota\n\te\n)\n\n\
+               const f = iota\n";
+    let file = ::parse(src).unwrap();
+
+    let iotas: Vec<Vec<_>> = file.top_level_decls
+        .iter()
+        .map(|decl| match decl.item {
+            ast::TopLevelDecl::Statement(ast::DeclStmt::Const(ref decl)) => {
+                decl.specs
+                    .iter()
+                    .map(|spec| (&spec.item.idents[0].item[..], spec.item.iota))
+                    .collect()
+            }
+            ref decl => panic!("expected a constant declaration, got {:?}", decl),
+        })
+        .collect();
+    assert_eq!(iotas,
+               vec![vec![("a", 0), ("b", 1), ("c", 2)], vec![("d", 0), ("e", 1)], vec![("f", 0)]]);
+}
+
+/// Parse a single-spec constant declaration, and render its value as an S-expression.
+fn const_value_sexpr(src: &str) -> String {
+    match parser(src).parse_decl_stmt().unwrap() {
+        ast::DeclStmt::Const(decl) => {
+            assert_eq!(decl.specs.len(), 1);
+            let inner = decl.specs[0].item.inner.clone().expect("missing constant value");
+            assert_eq!(inner.exprs.len(), 1);
+            sexpr(&inner.exprs[0].item)
+        }
+        decl => panic!("expected a constant declaration, got {:?}", decl),
+    }
+}
+
+#[test]
+fn parse_builtin_calls_in_const_decls() {
+    assert_eq!(const_value_sexpr("const N = len(\"hello\")"), "(len Call \"hello\")");
+    assert_eq!(const_value_sexpr("const N = len(x)"), "(len Call x)");
+    assert_eq!(const_value_sexpr("const C = cap(a) * 2"), "((cap Call a) Mul 2)");
+    assert_eq!(const_value_sexpr("const S = unsafe.Sizeof(y)"), "(unsafe.Sizeof Call y)");
+}
+
+#[test]
+fn parse_package_only_file() {
+    let expected = ast::SourceFile {
+        package: "foo".into(),
+        import_decls: vec![],
+        top_level_decls: vec![],
+    };
+
+    assert_ast_eq(&expected, &::parse("package foo").unwrap());
+    assert_ast_eq(&expected, &::parse("package foo\n").unwrap());
+}
+
+#[test]
+fn parse_empty_file() {
+    let err = ::parse("").unwrap_err();
+
+    let expected = ::Error::Parse(Error {
+        span: Span { start: 0, end: 0 },
+        kind: ErrorKind::UnexpectedEof {
+            expected: vec![TokenKind::Package],
+            can_continue: false,
+        },
+    });
+    assert_eq!(err, expected);
+}
+
+#[test]
+fn parse_tokens_ending_with_eof() {
+    let tokens = lexer::lex_all("package foo\n").unwrap();
+    let file = Parser::new(tokens.into_iter()).parse().unwrap();
+    assert_ast_eq(&::parse("package foo\n").unwrap(), &file);
+
+    let tokens = lexer::lex_all("(1 +").unwrap();
+    let err = Parser::new(tokens.into_iter()).parse_expr().unwrap_err();
+    assert_eq!(err.to_string(), parser("(1 +").parse_expr().unwrap_err().to_string());
+    assert!(err.is_incomplete(), "got {:?}", err);
+}
+
+#[test]
+fn nesting_too_deeply_is_an_error() {
+    let depth = 10000;
+    let src = format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+    let err = parser(&src).parse_expr().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::RecursionLimit { max_depth: 100 });
+    assert_eq!(err.span, Span { start: 100, end: 101 });
+    assert_eq!(err.kind.to_string(), "nested too deeply (the maximum depth is 100)");
+
+    let src = format!("package p\nvar v {}int\n", "[]".repeat(depth));
+    match ::parse(&src) {
+        Err(::Error::Parse(Error { kind: ErrorKind::RecursionLimit { .. }, .. })) => {}
+        res => panic!("expected a recursion limit error, got {:?}", res),
+    }
+
+    let config = ParserConfig { max_depth: 3 };
+    let shallow = |src: &str| {
+        Parser::with_config(lexer::tokenize(src).unwrap().into_iter(), config)
+    };
+    assert_eq!(sexpr(&shallow("((x))").parse_expr().unwrap()), "x");
+    assert!(shallow("(((x)))").parse_expr().is_err());
+    assert_eq!(shallow("{ { x() } }").parse_block().unwrap().to_string(), "{ { x() } }");
+    assert!(shallow("{ { { x() } } }").parse_block().is_err());
+
+    // The depth is restored after an error, so that the parser can be used further.
+    let mut p = shallow("((((x)))) (y)");
+    assert!(p.parse_expr().is_err());
+    assert_eq!(p.depth, 0);
+}
+
+fn parse_err(src: &str) -> Error {
+    match ::parse(src).unwrap_err() {
+        ::Error::Parse(err) => err,
+        err => panic!("expected a parse error, got {:?}", err),
+    }
+}
+
+#[test]
+fn incomplete_input_can_continue() {
+    let err = parse_err("package p\n\nfunc f() {");
+    assert!(err.is_incomplete(), "got {:?}", err);
+    assert_eq!(err.span, Span { start: 21, end: 21 });
+
+    let err = parser("(1 +").parse_expr().unwrap_err();
+    assert!(err.is_incomplete(), "got {:?}", err);
+    match err.kind {
+        ErrorKind::UnexpectedEof { can_continue: true, .. } => {}
+        kind => panic!("expected a continuable EOF, got {:?}", kind),
+    }
+}
+
+#[test]
+fn invalid_input_cannot_continue() {
+    // Nothing is left open, so more input can't help.
+    let err = parse_err("package p\n\nfunc");
+    assert!(!err.is_incomplete(), "got {:?}", err);
+    match err.kind {
+        ErrorKind::UnexpectedEof { can_continue: false, .. } => {}
+        kind => panic!("expected a non-continuable EOF, got {:?}", kind),
+    }
+
+    // A hard syntax error is never incomplete, even with an unclosed brace.
+    let err = parse_err("package p\n\nfunc f() { ) ");
+    assert!(!err.is_incomplete(), "got {:?}", err);
+}
+
+#[test]
+fn parse_source_blank_import() {
+    let src = "package main\n\nimport _ \"github.com/lib/pq\"\n";
+    let file = ::parse(src).unwrap();
+
+    assert_eq!(file.package, "main");
+    assert!(file.top_level_decls.is_empty());
+    assert_eq!(file.import_decls.len(), 1);
+
+    let specs = &file.import_decls[0].item.specs;
+    assert_eq!(specs.len(), 1);
+    assert_eq!(specs[0].item.kind, ast::ImportKind::Blank);
+    assert_eq!(specs[0].item.path.item, b"github.com/lib/pq".to_vec());
+}
+
+#[test]
+fn parse_return_stmts() {
+    let decl = parser("func f() (n int) { return }").parse_func_decl().unwrap();
+    assert_ast_eq(&vec![param_decl(&["n"], "int")], &decl.signature.result.decls);
+
+    let body = decl.body.unwrap().0;
+    assert_eq!(body.len(), 1);
+    match body[0].item {
+        ast::Statement::Return(ref ret) => assert!(ret.exprs.is_empty()),
+        ref stmt => panic!("expected a return statement, got {:?}", stmt),
+    }
+
+    let decl = parser("func f() (int, error) {\n\tif x {\n\t\treturn\n\t}\n\treturn 1, nil\n}")
+        .parse_func_decl()
+        .unwrap();
+    let stmts: Vec<_> = decl.body.unwrap().0.iter().map(|s| s.item.to_string()).collect();
+    assert_eq!(stmts, ["if x { return }", "return 1, nil"]);
+}
+
+#[test]
+fn parse_nested_func_lits() {
+    fn func_lit(expr: &ast::Expr) -> &ast::FuncLit {
+        use ast::{Operand, Literal};
+
+        if let ast::Expr::Unary(ast::UnaryExpr::Primary(ref primary)) = *expr {
+            if let ast::PrimaryExpr::Operand(Operand::Lit(Literal::Func(ref lit))) = **primary {
+                return lit;
+            }
+        }
+        panic!("expected a function literal, got {:?}", expr);
+    }
+
+    fn returned(lit: &ast::FuncLit) -> &ast::Expr {
+        assert_eq!(lit.body.0.len(), 1);
+        match lit.body.0[0].item {
+            ast::Statement::Return(ref ret) if ret.exprs.len() == 1 => &ret.exprs[0].item,
+            ref stmt => panic!("expected a return of one value, got {:?}", stmt),
+        }
+    }
+
+    let src = "func() func() func() int { return func() func() int { return func() int { return 0 \
+               } } }";
+    let outer = parser(src).parse_expr().unwrap();
+    let outer = func_lit(&outer);
+    let middle = func_lit(returned(outer));
+    let inner = func_lit(returned(middle));
+    assert_eq!(sexpr(returned(inner)), "0");
+
+    assert_eq!(outer.signature.to_string(), "() func() func() int");
+    assert_eq!(middle.signature.to_string(), "() func() int");
+    assert_eq!(inner.signature.to_string(), "() int");
+}
+
+/// Display `src` as an expression, check it against `expect`, and check that the output parses to
+/// a tree that displays the same way.
+fn assert_expr_roundtrip(src: &str, expect: &str) {
+    let printed = parser(src).parse_expr().unwrap().to_string();
+    assert_eq!(printed, expect, "displaying `{}`", src);
+
+    let reparsed = parser(&printed).parse_expr().unwrap();
+    assert_eq!(reparsed.to_string(), printed, "reparsing `{}`", printed);
+}
+
+#[test]
+fn display_expr_roundtrip() {
+    let expr = parser("1 + 2 * 3").parse_expr().unwrap();
+    let reparsed = parser(&expr.to_string()).parse_expr().unwrap();
+    assert_eq!(sexpr(&reparsed), "(1 Add (2 Mul 3))");
+
+    assert_expr_roundtrip("(1+2)*3", "(1 + 2) * 3");
+    assert_expr_roundtrip("a-(b-c)", "a - (b - c)");
+    assert_expr_roundtrip("- -x", "- -x");
+    assert_expr_roundtrip("!*p && <-ch", "!*p && <-ch");
+    assert_expr_roundtrip("a.b[i](x, y)[1:n:m]", "a.b[i](x, y)[1:n:m]");
+    assert_expr_roundtrip("x.(fmt.Stringer)", "x.(fmt.Stringer)");
+    assert_expr_roundtrip("append(a, b...)[0]", "append(a, b...)[0]");
+    assert_expr_roundtrip("[]int{1, 2}", "[]int{1, 2}");
+    assert_expr_roundtrip("map[string][2]bool{\"a\":{true,false}}",
+                          "map[string][2]bool{\"a\": {true, false}}");
+    assert_expr_roundtrip("func(a, b int) (int, error) { return a }",
+                          "func(a, b int) (int, error) { return a }");
+}
+
+#[test]
+fn display_parenthesizes_by_precedence() {
+    // The parser records explicit parentheses, so build a tree that needs them by hand.
+    let sum = parser("a + b").parse_expr().unwrap();
+    let span = Span { start: 0, end: 0 };
+    let product = ast::Expr::Binary(ast::BinaryExpr {
+        lhs: Box::new(Spanned::new(span, sum.clone())),
+        op: ast::BinaryOperation::Mul,
+        rhs: Box::new(Spanned::new(span, sum)),
+    });
+
+    assert_eq!(product.to_string(), "(a + b) * (a + b)");
+}
+
+#[test]
+fn display_source_file_roundtrip() {
+    let src = r#"package main
+
+import "fmt"
+import str "strings"
+
+const (
+    a, b = iota, 1 << iota
+    c
+)
+
+type T struct {
+    x, y int
+    *fmt.Stringer `json:"s"`
+}
+
+func length(t T) int { return len(t.x) }
+
+func main() {
+    var xs []int
+    for i := 0; i < 10; i++ {
+        xs = append(xs, i)
+    }
+    for _, x := range xs {
+        if y := x % 2; y == 0 {
+            continue
+        } else if x > 5 {
+            break
+        }
+    }
+    switch v := f(); v {
+    case 1, 2:
+        fmt.Println(str.Repeat("a", v))
+        fallthrough
+    default:
+    }
+    switch t := x.(type) {
+    case int:
+    }
+    x += 1
+    ch <- x
+loop:
+    for {
+        goto loop
+    }
+}
+"#;
+
+    let printed = ::parse(src).unwrap().to_string();
+    let reprinted = ::parse(&printed).unwrap().to_string();
+    assert_eq!(printed, reprinted);
+}
+
+#[test]
+fn parse_long_import() {
+    let src = "package main\n\nimport (\n\t\"fmt\"\n\tm \"lib/math\"\n\t. \"os\"\n\t_ \"net\"\n)\n";
+    let file = ::parse(src).unwrap();
+
+    assert_eq!(file.import_decls.len(), 1);
+    assert_eq!(file.imports().collect::<Vec<_>>(),
+               vec![&b"fmt"[..], b"lib/math", b"os", b"net"]);
+
+    let kinds: Vec<_> = file.imports_with_kind().map(|(kind, _)| kind.clone()).collect();
+    assert_eq!(kinds,
+               vec![ast::ImportKind::Normal,
+                    ast::ImportKind::Alias("m".into()),
+                    ast::ImportKind::Glob,
+                    ast::ImportKind::Blank]);
+}
+
+#[test]
+fn interpret_valueless_string_token() {
+    let token = TokenAndSpan {
+        token: Token {
+            kind: TokenKind::Str,
+            value: None,
+        },
+        span: Span { start: 0, end: 2 },
+    };
+    let err = interpret_string_token(token).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::other("missing value in string literal"));
+    assert_eq!(err.span, Span { start: 0, end: 2 });
+}
+
+#[test]
+fn parse_labeled_continue_in_nested_loops() {
+    let src = "package p
+
+func f() {
+Outer:
+    for i := range xs {
+        for j := range ys {
+            if cond(i, j) {
+                continue Outer
+            }
+        }
+    }
+}
+";
+    let file = ::parse(src).unwrap();
+    let body = match file.top_level_decls[0].item {
+        ast::TopLevelDecl::Func(ref decl) => decl.body.clone().unwrap(),
+        ref decl => panic!("expected a function declaration, got {:?}", decl),
+    };
+    assert_eq!(body.0.len(), 1);
+
+    let labeled = match body.0[0].item {
+        ast::Statement::Labeled(ref stmt) => stmt.clone(),
+        ref stmt => panic!("expected a labeled statement, got {:?}", stmt),
+    };
+    assert_eq!(labeled.label.item, "Outer");
+
+    let range_body = |stmt: &ast::Statement, var: &str| {
+        match *stmt {
+            ast::Statement::For(ast::ForStmt {
+                header: ast::ForHeader::RangeClause(ref clause), ref body
+            }) => {
+                match clause.iter_vars {
+                    ast::IterVars::Idents(ref idents) => assert_eq!(idents[0].item, var),
+                    ref vars => panic!("expected declared iteration variables, got {:?}", vars),
+                }
+                body.clone()
+            }
+            ref stmt => panic!("expected a range loop, got {:?}", stmt),
+        }
+    };
+
+    let outer = range_body(&labeled.statement.item, "i");
+    let inner = range_body(&outer.0[0].item, "j");
+
+    let if_stmt = match inner.0[0].item {
+        ast::Statement::If(ref stmt) => stmt.clone(),
+        ref stmt => panic!("expected an if statement, got {:?}", stmt),
+    };
+    assert_eq!(sexpr(&if_stmt.condition.item), "(cond Call i j)");
+
+    match if_stmt.block.0[0].item {
+        ast::Statement::Continue(ref stmt) => {
+            assert_eq!(stmt.label.as_ref().map(|label| &label.item[..]), Some("Outer"));
+        }
+        ref stmt => panic!("expected a continue statement, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_decls_one_at_a_time() {
+    let src = "package p\n\nimport \"fmt\"\n\nconst a = 1\n\nfunc F() {}\n\nvar b int\n\n\
+               func g()\n";
+
+    // Tokens are lexed lazily as the parser asks for them.
+    let mut lazy = Parser::new(lexer::Lexer::new(src).unwrap());
+    let (package, imports) = lazy.parse_preamble().unwrap();
+    assert_eq!(package, "p");
+    assert_eq!(imports.len(), 1);
+
+    let mut names = Vec::new();
+    while let Some(decl) = lazy.next_decl() {
+        names.push(match decl.unwrap().item {
+            ast::TopLevelDecl::Func(decl) => decl.name.item,
+            ast::TopLevelDecl::Statement(decl) => decl.to_string(),
+            decl => panic!("unexpected declaration {:?}", decl),
+        });
+    }
+    assert_eq!(names, vec!["const a = 1", "F", "var b int", "g"]);
+    assert!(lazy.next_decl().is_none());
+
+    // Parsing stops at the first error.
+    let mut p = parser("package p\n\nfunc f() {}\n\n1\n\nfunc g() {}\n");
+    p.parse_preamble().unwrap();
+    assert!(p.next_decl().unwrap().is_ok());
+    assert!(p.next_decl().unwrap().is_err());
+    assert!(p.next_decl().is_none());
+}
+
+#[test]
+fn parse_recovering_reports_every_error() {
+    let src = "package p\n\nfunc f() {\n\tif x {\n\t\ty = )\n\t}\n}\n\nvar a int\n\n1\n\n\
+               func g() {}\n";
+    let (file, errors) = parser(src).parse_recovering();
+
+    let names: Vec<_> = file.unwrap()
+        .top_level_decls
+        .into_iter()
+        .map(|decl| {
+            match decl.item {
+                ast::TopLevelDecl::Func(decl) => decl.name.item,
+                ast::TopLevelDecl::Statement(decl) => decl.to_string(),
+                decl => panic!("unexpected declaration {:?}", decl),
+            }
+        })
+        .collect();
+    assert_eq!(names, vec!["var a int", "g"]);
+
+    let spans: Vec<_> = errors.iter().map(|err| err.span).collect();
+    assert_eq!(spans, vec![Span { start: 36, end: 37 }, Span { start: 55, end: 56 }]);
+
+    // An unclosed delimiter is closed by the closer of the one around it, so the declarations
+    // after it are still parsed.
+    let src = "package p\n\nfunc f() {\n\tg(;\n}\n\nfunc h() {}\n\nfunc i() {\n\ta[1, }\n\n\
+               var b = )\n";
+    let (file, errors) = parser(src).parse_recovering();
+
+    let decls = file.unwrap().top_level_decls;
+    assert_eq!(decls.len(), 1);
+    match decls[0].item {
+        ast::TopLevelDecl::Func(ref decl) => assert_eq!(decl.name.item, "h"),
+        ref decl => panic!("expected a function declaration, got {:?}", decl),
+    }
+
+    let spans: Vec<_> = errors.iter().map(|err| err.span).collect();
+    assert_eq!(spans,
+               vec![Span { start: 25, end: 26 },
+                    Span { start: 58, end: 59 },
+                    Span { start: 71, end: 72 }]);
+
+    // Nothing can be recovered after an error in the package clause.
+    let (file, errors) = parser("packag p\n\nfunc f() {}\n").parse_recovering();
+    assert!(file.is_none());
+    assert_eq!(errors.len(), 1);
+}
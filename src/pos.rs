@@ -1,3 +1,5 @@
+use std::cmp;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Position {
     /// 1-indexed row.
@@ -13,4 +15,37 @@ impl Position {
             column: 1,
         }
     }
+
+    /// Compute the position of the character starting at byte `offset` in `src`.
+    ///
+    /// A tab advances the column to the next tab stop, every `tab_width` columns, and every other
+    /// character advances it by one. Returns `None` if `offset` is past the end of `src` or not on
+    /// a character boundary.
+    pub fn from_offset(src: &str, offset: usize, tab_width: usize) -> Option<Position> {
+        if !src.is_char_boundary(offset) {
+            return None;
+        }
+
+        let mut pos = Position::start();
+        pos.advance(&src[..offset], tab_width);
+        Some(pos)
+    }
+
+    /// Move the position past `text`, which must directly follow it in the source.
+    ///
+    /// A tab width of 0 counts as 1.
+    pub fn advance(&mut self, text: &str, tab_width: usize) {
+        let tab_width = cmp::max(tab_width, 1);
+
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    self.row += 1;
+                    self.column = 1;
+                }
+                '\t' => self.column = ((self.column - 1) / tab_width + 1) * tab_width + 1,
+                _ => self.column += 1,
+            }
+        }
+    }
 }
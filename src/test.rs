@@ -0,0 +1,86 @@
+use super::*;
+use token::Span;
+
+#[test]
+fn parse_package_files() {
+    let main = "package main\n\nfunc main() {\n\thelper()\n}\n";
+    let helper = "package main\n\nfunc helper() {}\n";
+
+    let package = parse_package(&[("main.go".into(), main), ("helper.go".into(), helper)])
+        .unwrap();
+    assert_eq!(package.name, "main");
+    assert_eq!(package.files.len(), 2);
+    assert_eq!(package.files[1].top_level_decls.len(), 1);
+
+    // Every file is checked, and each error is reported with the name of its file.
+    let errors = parse_package(&[("main.go".into(), main),
+                                 ("util.go".into(), "package util\n"),
+                                 ("bad.go".into(), "package main\n\nfunc"),
+                                 ("helper.go".into(), helper)])
+        .unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0],
+               ("util.go".into(), Error::PackageMismatch("main".into(), "util".into())));
+    assert_eq!(errors[1].0, "bad.go");
+    match errors[1].1 {
+        Error::Parse(ref err) => assert_eq!(err.span, Span { start: 18, end: 18 }),
+        ref err => panic!("expected a parse error, got {:?}", err),
+    }
+
+    assert_eq!(parse_package(&[]), Err(vec![]));
+}
+
+#[test]
+fn extract_string_literals() {
+    let src = "package p\n\nvar s = \"a\\tb\\x41\\u00e9\" + `c\\d`\n";
+    let strings = extract_strings(src).unwrap();
+    assert_eq!(strings,
+               vec![(Span { start: 19, end: 35 }, "a\tbA\u{e9}".as_bytes().to_vec()),
+                    (Span { start: 38, end: 43 }, b"c\\d".to_vec())]);
+
+    match extract_strings("package p\n\nvar s = \"\\q\"\n") {
+        Err(Error::Parse(err)) => {
+            assert_eq!(err.kind, parser::ErrorKind::other("unknown escape sequence: q"));
+        }
+        res => panic!("expected an escape error, got {:?}", res),
+    }
+
+    match extract_strings("package p\n\nvar s = \"abc\n") {
+        Err(Error::Lex(err)) => {
+            assert_eq!(err.kind, lexer::ErrorKind::UnterminatedLiteral(token::TokenKind::Str));
+        }
+        res => panic!("expected an unterminated literal error, got {:?}", res),
+    }
+}
+
+#[test]
+fn try_parse_catches_panics() {
+    // A stray character is a lexical error rather than a panic.
+    let src = "package main\n\nfunc main() {\n\tx @ y\n}\n";
+    match try_parse(src) {
+        Err(Error::Lex(err)) => {
+            assert_eq!(err.kind, lexer::ErrorKind::IllegalCharacter('@'));
+            assert_eq!(err.span, Span { start: 31, end: 32 });
+        }
+        other => panic!("expected a lexical error, got {:?}", other),
+    }
+
+    let src = "package main\n\nfunc main() {}\n";
+    assert_eq!(try_parse(src), parse(src));
+}
+
+#[test]
+fn catch_panics_reports_the_panic_message() {
+    let src = "package main\n";
+    match catch_panics(src, || -> Result<()> { panic!("gave up at {}", 3) }) {
+        Err(Error::Panic(msg, span)) => {
+            assert_eq!(msg, "gave up at 3");
+            assert_eq!(span, Span { start: 0, end: 13 });
+        }
+        other => panic!("expected a panic error, got {:?}", other),
+    }
+
+    assert_eq!(panic_message(&"static message"), "static message");
+    assert_eq!(panic_message(&String::from("owned message")), "owned message");
+    assert_eq!(panic_message(&42), "unknown panic");
+}
@@ -13,6 +13,12 @@ pub struct Span {
     pub end: u32,
 }
 
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
 pub trait Spanner {
     fn span(&self) -> Span;
 }
@@ -73,12 +79,19 @@ pub struct Token {
     pub value: Option<String>,
 }
 
+impl Token {
+    /// The syntax highlighting category of this token.
+    pub fn highlight_class(&self) -> HighlightClass {
+        self.kind.highlight_class()
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // If the token contains a value, display it.
         match self.value {
-            Some(ref v) => write!(f, "{}({})", self, v),
-            None => write!(f, "{}", self),
+            Some(ref v) => write!(f, "{}({})", self.kind, v),
+            None => write!(f, "{}", self.kind),
         }
     }
 }
@@ -170,6 +183,8 @@ pub enum TokenKind {
     Rshift,
     /// &^
     BitClear,
+    /// ~
+    Tilde,
     /// &&
     AndAnd,
     /// ||
@@ -234,9 +249,36 @@ pub enum TokenKind {
     Colon,
     /// End of file
     Eof,
+    /// A character that can't start any token, only emitted by a lenient lexer. The token's value
+    /// is the character.
+    Error,
 }
 
 
+/// The category of a token for syntax highlighting, in the spirit of TextMate scopes or LSP
+/// semantic token types.
+///
+/// Only what can be told from a single token is covered: whether an identifier names a type, for
+/// example, depends on context. Comments are skipped by the lexer, and so have no class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightClass {
+    Keyword,
+    Identifier,
+    /// Interpreted and raw string literals.
+    String,
+    /// Rune literals.
+    Char,
+    /// Integer, floating-point and imaginary literals.
+    Number,
+    Operator,
+    /// Delimiters, separators and terminators.
+    Punctuation,
+    /// Tokens with no text of their own (the end of the file).
+    None,
+    /// Characters that are not valid Go.
+    Error,
+}
+
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // We're using the derived Debug impl for simplicity.
@@ -246,20 +288,199 @@ impl fmt::Display for TokenKind {
 }
 
 impl TokenKind {
-    pub fn precedence(self) -> i32 {
+    /// The keyword spelled `s`, or `None` if `s` isn't a keyword.
+    pub fn from_keyword(s: &str) -> Option<TokenKind> {
+        let first = match s.as_bytes().first() {
+            Some(&b) => b,
+            None => return None,
+        };
+
+        // Dispatching on the length and first byte first means that most identifiers are
+        // rejected without a single string comparison, and the rest with at most two.
+        Some(match (s.len(), first) {
+            (2, b'g') if s == "go" => Go,
+            (2, b'i') if s == "if" => If,
+            (3, b'f') if s == "for" => For,
+            (3, b'm') if s == "map" => Map,
+            (3, b'v') if s == "var" => Var,
+            (4, b'c') if s == "case" => Case,
+            (4, b'c') if s == "chan" => Chan,
+            (4, b'e') if s == "else" => Else,
+            (4, b'f') if s == "func" => Func,
+            (4, b'g') if s == "goto" => Goto,
+            (4, b't') if s == "type" => Type,
+            (5, b'b') if s == "break" => Break,
+            (5, b'c') if s == "const" => Const,
+            (5, b'd') if s == "defer" => Defer,
+            (5, b'r') if s == "range" => Range,
+            (6, b'i') if s == "import" => Import,
+            (6, b'r') if s == "return" => Return,
+            (6, b's') if s == "select" => Select,
+            (6, b's') if s == "struct" => Struct,
+            (6, b's') if s == "switch" => Switch,
+            (7, b'd') if s == "default" => Default,
+            (7, b'p') if s == "package" => Package,
+            (8, b'c') if s == "continue" => Continue,
+            (9, b'i') if s == "interface" => Interface,
+            (11, b'f') if s == "fallthrough" => Fallthrough,
+            _ => return None,
+        })
+    }
+
+    /// Whether `s` is a keyword, and so can't be used as an identifier.
+    pub fn is_keyword_str(s: &str) -> bool {
+        TokenKind::from_keyword(s).is_some()
+    }
+
+    /// How tokens of this kind are always spelled, or `None` for identifiers, literals and the
+    /// pseudo-tokens, whose text varies.
+    pub fn spelling(self) -> Option<&'static str> {
+        // No catch-all arm, so that adding a token kind forces a decision here.
+        Some(match self {
+            Ident | Decimal | Octal | Hex | Float | Imaginary | Rune | Str | StrRaw | Eof |
+            Error => return None,
+
+            LParen => "(",
+            RParen => ")",
+            LBracket => "[",
+            RBracket => "]",
+            LBrace => "{",
+            RBrace => "}",
+
+            Break => "break",
+            Case => "case",
+            Chan => "chan",
+            Const => "const",
+            Continue => "continue",
+            Default => "default",
+            Defer => "defer",
+            Else => "else",
+            Fallthrough => "fallthrough",
+            For => "for",
+            Func => "func",
+            Go => "go",
+            Goto => "goto",
+            If => "if",
+            Import => "import",
+            Interface => "interface",
+            Map => "map",
+            Package => "package",
+            Range => "range",
+            Return => "return",
+            Select => "select",
+            Struct => "struct",
+            Switch => "switch",
+            Type => "type",
+            Var => "var",
+
+            Plus => "+",
+            Minus => "-",
+            Star => "*",
+            Slash => "/",
+            Percent => "%",
+            And => "&",
+            Or => "|",
+            Caret => "^",
+            Lshift => "<<",
+            Rshift => ">>",
+            BitClear => "&^",
+            Tilde => "~",
+            AndAnd => "&&",
+            OrOr => "||",
+            Equals => "==",
+            NotEqual => "!=",
+            LessThan => "<",
+            GreaterThan => ">",
+            LessThanOrEqual => "<=",
+            GreaterThanOrEqual => ">=",
+            Increment => "++",
+            Decrement => "--",
+            PlusAssign => "+=",
+            MinusAssign => "-=",
+            StarAssign => "*=",
+            SlashAssign => "/=",
+            PercentAssign => "%=",
+            AndAssign => "&=",
+            OrAssign => "|=",
+            CaretAssign => "^=",
+            LshiftAssign => "<<=",
+            RshiftAssign => ">>=",
+            BitClearAssign => "&^=",
+            Not => "!",
+            Assign => "=",
+            ColonAssign => ":=",
+            Arrow => "<-",
+
+            Ellipsis => "...",
+            Comma => ",",
+            Dot => ".",
+            Semicolon => ";",
+            Colon => ":",
+        })
+    }
+
+    /// The syntax highlighting category of this kind of token.
+    pub fn highlight_class(self) -> HighlightClass {
+        // No catch-all arm, so that adding a token kind forces a decision here.
+        match self {
+            Ident => HighlightClass::Identifier,
+
+            LParen | RParen | LBracket | RBracket | LBrace | RBrace | Ellipsis | Comma | Dot |
+            Semicolon | Colon => HighlightClass::Punctuation,
+
+            Decimal | Octal | Hex | Float | Imaginary => HighlightClass::Number,
+            Rune => HighlightClass::Char,
+            Str | StrRaw => HighlightClass::String,
+
+            Break | Case | Chan | Const | Continue | Default | Defer | Else | Fallthrough | For |
+            Func | Go | Goto | If | Import | Interface | Map | Package | Range | Return | Select |
+            Struct | Switch | Type | Var => HighlightClass::Keyword,
+
+            Plus | Minus | Star | Slash | Percent | And | Or | Caret | Lshift | Rshift |
+            BitClear | Tilde | AndAnd | OrOr | Equals | NotEqual | LessThan | GreaterThan |
+            LessThanOrEqual | GreaterThanOrEqual | Increment | Decrement | PlusAssign |
+            MinusAssign | StarAssign | SlashAssign | PercentAssign | AndAssign | OrAssign |
+            CaretAssign | LshiftAssign | RshiftAssign | BitClearAssign | Not | Assign |
+            ColonAssign | Arrow => HighlightClass::Operator,
+
+            Eof => HighlightClass::None,
+            Error => HighlightClass::Error,
+        }
+    }
+
+    /// The precedence of this token as a binary operator, or `None` if it isn't one.
+    /// Higher values bind more tightly.
+    pub fn binary_precedence(self) -> Option<u8> {
         // Precedence    Operator
         //    5             *  /  %  <<  >>  &  &^
         //    4             +  -  |  ^
         //    3             ==  !=  <  <=  >  >=
         //    2             &&
         //    1             ||
-        match self {
+        Some(match self {
             Star | Slash | Percent | Lshift | Rshift | And | BitClear => 5,
             Plus | Minus | Or | Caret => 4,
             Equals | NotEqual | LessThan | LessThanOrEqual | GreaterThan | GreaterThanOrEqual => 3,
             AndAnd => 2,
             OrOr => 1,
-            _ => panic!("BUG: calling .precedence() on a token which is not a binary operator"),
+            _ => return None,
+        })
+    }
+
+    /// If this is an opening delimiter, return the matching closing delimiter.
+    pub fn closing_delim(self) -> Option<TokenKind> {
+        match self {
+            LParen => Some(RParen),
+            LBracket => Some(RBracket),
+            LBrace => Some(RBrace),
+            _ => None,
+        }
+    }
+
+    pub fn is_closing_delim(self) -> bool {
+        match self {
+            RParen | RBracket | RBrace => true,
+            _ => false,
         }
     }
 
@@ -479,11 +700,11 @@ impl TokenKind {
     }
 
     pub fn can_start_array_type(self) -> bool {
-        self == RBracket
+        self == LBracket
     }
 
     pub fn can_start_slice_type(self) -> bool {
-        self == RBracket
+        self == LBracket
     }
 
     pub fn can_start_map_type(self) -> bool {
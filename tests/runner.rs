@@ -49,7 +49,7 @@ fn lexing_does_not_panic() {
     flush();
 
     for_all_in("pass", |src| {
-        rgo::lexer::tokenize(&src);
+        rgo::lexer::tokenize(&src).unwrap();
     });
     println!("{}", "OK.".green());
 }
@@ -59,7 +59,7 @@ fn parsing_does_not_panic() {
     flush();
 
     for_all_in("pass", |src| {
-        let tokens: Vec<_> = rgo::lexer::Lexer::new(&src).collect();
+        let tokens: Vec<_> = rgo::lexer::Lexer::new(&src).unwrap().collect();
         match rgo::Parser::new(tokens.clone().into_iter()).parse() {
             Ok(_) => {}
             Err(e) => {